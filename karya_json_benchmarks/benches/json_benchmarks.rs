@@ -1,8 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use karya_json::types::{JsonParser, JsonValue};
+use karya_json::types::{JsonMap, JsonParser, JsonValue};
 use memory_stats::memory_stats;
 use serde_json::Value as SerdeValue;
-use std::collections::HashMap;
 
 // Sample JSON data for benchmarking
 const SMALL_JSON: &str = r#"{"name":"John Doe","age":30,"is_active":true}"#;
@@ -43,14 +42,14 @@ fn bench_karya_json_parse(c: &mut Criterion) {
 
     group.bench_function("karya_json - small", |b| {
         b.iter(|| {
-            let mut parser = JsonParser::new(black_box(SMALL_JSON.to_string()));
+            let mut parser = JsonParser::from_str(black_box(SMALL_JSON));
             let _value = parser.parse().unwrap();
         })
     });
 
     group.bench_function("karya_json - medium", |b| {
         b.iter(|| {
-            let mut parser = JsonParser::new(black_box(MEDIUM_JSON.to_string()));
+            let mut parser = JsonParser::from_str(black_box(MEDIUM_JSON));
             let _value = parser.parse().unwrap();
         })
     });
@@ -63,14 +62,14 @@ fn bench_karya_json_stringify(c: &mut Criterion) {
     let mut group = c.benchmark_group("Value to String - Stringify");
 
     // Create a JsonValue for small JSON
-    let mut small_map = HashMap::new();
-    small_map.insert("name".to_string(), JsonValue::Str("John Doe".to_string()));
-    small_map.insert("age".to_string(), JsonValue::Int(30));
-    small_map.insert("is_active".to_string(), JsonValue::Bool(true));
-    let small_value = JsonValue::Obj(small_map);
+    let mut small_map = JsonMap::new();
+    small_map.insert("name".to_string(), "John Doe".into());
+    small_map.insert("age".to_string(), 30.into());
+    small_map.insert("is_active".to_string(), true.into());
+    let small_value = JsonValue::Obj(small_map.into());
 
     // Parse medium JSON to get a JsonValue
-    let mut parser = JsonParser::new(MEDIUM_JSON.to_string());
+    let mut parser = JsonParser::from_str(MEDIUM_JSON);
     let medium_value = parser.parse().unwrap();
 
     group.bench_function("karya_json - small", |b| {
@@ -139,7 +138,7 @@ fn bench_memory_usage(c: &mut Criterion) {
         b.iter(|| {
             let (before, after) = measure_memory(|| {
                 for _ in 0..1000 {
-                    let mut parser = JsonParser::new(SMALL_JSON.to_string());
+                    let mut parser = JsonParser::from_str(SMALL_JSON);
                     black_box(parser.parse().unwrap());
                 }
             });
@@ -152,7 +151,7 @@ fn bench_memory_usage(c: &mut Criterion) {
         b.iter(|| {
             let (before, after) = measure_memory(|| {
                 for _ in 0..100 {  // Using fewer iterations for larger JSON
-                    let mut parser = JsonParser::new(MEDIUM_JSON.to_string());
+                    let mut parser = JsonParser::from_str(MEDIUM_JSON);
                     black_box(parser.parse().unwrap());
                 }
             });
@@ -187,11 +186,11 @@ fn bench_memory_usage(c: &mut Criterion) {
     // Memory usage for karya_json stringify - small JSON
     group.bench_function("karya_json stringify - small", |b| {
         b.iter(|| {
-            let mut small_map = HashMap::new();
-            small_map.insert("name".to_string(), JsonValue::Str("John Doe".to_string()));
-            small_map.insert("age".to_string(), JsonValue::Int(30));
-            small_map.insert("is_active".to_string(), JsonValue::Bool(true));
-            let small_value = JsonValue::Obj(small_map);
+            let mut small_map = JsonMap::new();
+            small_map.insert("name".to_string(), "John Doe".into());
+            small_map.insert("age".to_string(), 30.into());
+            small_map.insert("is_active".to_string(), true.into());
+            let small_value = JsonValue::Obj(small_map.into());
 
             let (before, after) = measure_memory(|| {
                 for _ in 0..1000 {
@@ -205,7 +204,7 @@ fn bench_memory_usage(c: &mut Criterion) {
     // Memory usage for karya_json stringify - medium JSON
     group.bench_function("karya_json stringify - medium", |b| {
         b.iter(|| {
-            let mut parser = JsonParser::new(MEDIUM_JSON.to_string());
+            let mut parser = JsonParser::from_str(MEDIUM_JSON);
             let medium_value = parser.parse().unwrap();
 
             let (before, after) = measure_memory(|| {
@@ -16,18 +16,16 @@
 //! ## Quick Start
 //!
 //! ```rust
-//! use karya_json::types::{JsonParser, JsonValue};
+//! use karya_json::types::JsonParser;
 //!
 //! // Parse a JSON string
 //! let json_str = r#"{"name": "Alice", "age": 30, "is_active": true}"#;
-//! let mut parser = JsonParser::new(json_str.to_string());
+//! let mut parser = JsonParser::from_str(json_str);
 //! let value = parser.parse().expect("Failed to parse JSON");
 //!
 //! // Access data from the parsed JSON
-//! if let JsonValue::Obj(map) = value {
-//!     if let Some(JsonValue::Str(name)) = map.get("name") {
-//!         println!("Name: {}", name);
-//!     }
+//! if let Some(name) = value["name"].as_str() {
+//!     println!("Name: {}", name);
 //! }
 //! ```
 //!
@@ -35,10 +33,16 @@
 //!
 //! Karya JSON provides detailed error types for both serialization and deserialization:
 //!
-//! - `DeserializeError::InvalidJson`: When the JSON syntax is invalid
+//! - `DeserializeError::InvalidJson`: When the JSON syntax is invalid. The attached `SyntaxError`
+//!   reports the byte offset, line, column and JSON Pointer path of the problem
 //! - `DeserializeError::MissingField`: When a required field is missing
 //! - `DeserializeError::TypeMismatch`: When a value has an unexpected type
 //! - `DeserializeError::InvalidValue`: When a value is invalid for its context
+//! - `DeserializeError::Io`: When reading the input from a `std::io::Read` fails
+//!
+//! Syntax errors also carry a machine-readable `ErrorCode`, available through
+//! `DeserializeError::code()`, so callers can react to specific problems such as
+//! `ErrorCode::DuplicateKey` without inspecting the message text.
 //!
 //! ## Modules
 //!
@@ -0,0 +1,303 @@
+//! Reading and changing parts of a value without matching on it.
+//!
+//! The `as_*` methods return `None` for a value of another kind, so they
+//! chain with `?` and `Option` combinators. Indexing goes further, as in
+//! JavaScript: a missing key or element, or an index into the wrong kind of
+//! value, reads as `Null`, so `value["a"]["b"][0]` never panics.
+
+use crate::types::map::ObjectMap;
+use crate::types::{GenericJsonValue, JsonObject, MapBackend};
+use std::ops::{Index, IndexMut};
+
+impl<B: MapBackend> GenericJsonValue<B> {
+    /// `Null`, for indexing to return when there is no value
+    const NULL: &'static Self = &GenericJsonValue::Null;
+
+    /// Returns the member of an object with this key, or `None` if the value
+    /// is not an object or has no such member.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::JsonParser;
+    ///
+    /// let value = JsonParser::from_str(r#"{"user": {"name": "Alice"}}"#).parse().unwrap();
+    ///
+    /// let name = value.get("user").and_then(|user| user.get("name")).and_then(|name| name.as_str());
+    /// assert_eq!(name, Some("Alice"));
+    /// assert!(value.get("missing").is_none());
+    /// ```
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        self.as_object()?.get(key)
+    }
+
+    /// Returns a mutable reference to the member of an object with this key,
+    /// or `None` if the value is not an object or has no such member.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Self> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    /// Returns the string, if the value is a `Str`.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            GenericJsonValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as an `i64`, if it is an integer in range.
+    ///
+    /// Any integer variant converts, as do a `Number` or `Decimal` holding an
+    /// integer; floats do not, even when they have no fractional part.
+    pub fn as_i64(&self) -> Option<i64> {
+        i64::try_from(self).ok()
+    }
+
+    /// Returns the number as a `u64`, if it is an integer in range.
+    ///
+    /// Converts the same values as `as_i64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        u64::try_from(self).ok()
+    }
+
+    /// Returns the number as an `f64`, if the value is a number of any kind.
+    ///
+    /// Integers and exact numbers too large or precise for an `f64` are
+    /// rounded to the nearest one.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            GenericJsonValue::Int(n) => Some(*n as f64),
+            GenericJsonValue::UInt(n) => Some(*n as f64),
+            GenericJsonValue::Int128(n) => Some(*n as f64),
+            GenericJsonValue::UInt128(n) => Some(*n as f64),
+            GenericJsonValue::Float(n) => Some(*n),
+            GenericJsonValue::Number(n) => Some(n.as_f64()),
+            #[cfg(feature = "decimal")]
+            GenericJsonValue::Decimal(n) => Some(n.to_f64()),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean, if the value is a `Bool`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            GenericJsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements, if the value is an `Arr`.
+    pub fn as_array(&self) -> Option<&Vec<Self>> {
+        match self {
+            GenericJsonValue::Arr(items) => Some(&**items),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements mutably, if the value is an `Arr`.
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match self {
+            GenericJsonValue::Arr(items) => Some(&mut **items),
+            _ => None,
+        }
+    }
+
+    /// Returns the members, if the value is an `Obj`.
+    pub fn as_object(&self) -> Option<&B::Map<Self>> {
+        match self {
+            GenericJsonValue::Obj(map) => Some(&**map),
+            _ => None,
+        }
+    }
+
+    /// Returns the members mutably, if the value is an `Obj`.
+    pub fn as_object_mut(&mut self) -> Option<&mut B::Map<Self>> {
+        match self {
+            GenericJsonValue::Obj(map) => Some(&mut **map),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the value is `Null`.
+    pub fn is_null(&self) -> bool {
+        matches!(self, GenericJsonValue::Null)
+    }
+
+    /// Takes the value out, leaving `Null` in its place.
+    ///
+    /// This moves a value out from behind a reference, such as a member of
+    /// a larger value, where a pattern could only borrow it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::JsonParser;
+    ///
+    /// let mut value = JsonParser::from_str(r#"{"items": [1, 2]}"#).parse().unwrap();
+    ///
+    /// let items = value["items"].take();
+    /// assert_eq!(items.to_string(), "[1,2]");
+    /// assert_eq!(value.to_string(), r#"{"items":null}"#);
+    /// ```
+    pub fn take(&mut self) -> Self {
+        std::mem::take(self)
+    }
+}
+
+/// Looks up an object member, giving `Null` if the value is not an object or
+/// has no such member.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::JsonParser;
+///
+/// let value = JsonParser::from_str(r#"{"users": [{"name": "Alice"}]}"#).parse().unwrap();
+///
+/// assert_eq!(value["users"][0]["name"].as_str(), Some("Alice"));
+/// assert!(value["users"][5]["name"].is_null());
+/// assert!(value["name"]["first"].is_null());
+/// ```
+impl<B: MapBackend> Index<&str> for GenericJsonValue<B> {
+    type Output = Self;
+
+    fn index(&self, key: &str) -> &Self {
+        self.get(key).unwrap_or(Self::NULL)
+    }
+}
+
+/// Looks up an array element, giving `Null` if the value is not an array or
+/// the index is out of bounds.
+impl<B: MapBackend> Index<usize> for GenericJsonValue<B> {
+    type Output = Self;
+
+    fn index(&self, index: usize) -> &Self {
+        self.as_array().and_then(|items| items.get(index)).unwrap_or(Self::NULL)
+    }
+}
+
+/// Looks up an object member to change it, inserting `Null` for a missing
+/// key. A `Null` value is first replaced by an empty object, so nested
+/// objects can be built up one key at a time.
+///
+/// # Panics
+///
+/// Panics if the value is neither an object nor `Null`.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::JsonValue;
+///
+/// let mut value = JsonValue::Null;
+/// value["server"]["port"] = JsonValue::Int(8080);
+/// value["server"]["host"] = JsonValue::Str("localhost".to_string());
+///
+/// assert_eq!(value.to_string(), r#"{"server":{"port":8080,"host":"localhost"}}"#);
+/// ```
+impl<B: MapBackend> IndexMut<&str> for GenericJsonValue<B> {
+    fn index_mut(&mut self, key: &str) -> &mut Self {
+        if self.is_null() {
+            *self = GenericJsonValue::Obj(JsonObject::new());
+        }
+        let GenericJsonValue::Obj(map) = self else {
+            panic!("cannot index into {} with key '{}'", self.kind(), key);
+        };
+        if !map.contains_key(key) {
+            map.insert(key.to_owned(), GenericJsonValue::Null);
+        }
+        map.get_mut(key).unwrap_or_else(|| unreachable!("the key was just inserted"))
+    }
+}
+
+/// Looks up an array element to change it.
+///
+/// # Panics
+///
+/// Panics if the value is not an array, or the index is out of bounds.
+impl<B: MapBackend> IndexMut<usize> for GenericJsonValue<B> {
+    fn index_mut(&mut self, index: usize) -> &mut Self {
+        let kind = self.kind();
+        match self.as_array_mut() {
+            Some(items) => {
+                let len = items.len();
+                items
+                    .get_mut(index)
+                    .unwrap_or_else(|| panic!("index {} is out of bounds for an array of length {}", index, len))
+            }
+            None => panic!("cannot index into {} with {}", kind, index),
+        }
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use crate::types::{JsonParser, JsonValue, Sorted};
+
+    fn parse(input: &str) -> JsonValue {
+        JsonParser::from_str(input).parse().unwrap()
+    }
+
+    #[test]
+    fn test_accessors() {
+        let value = parse(r#"{"s": "text", "i": -4, "u": 18446744073709551615, "f": 2.5, "b": false, "a": [null], "o": {}}"#);
+
+        assert_eq!(value["s"].as_str(), Some("text"));
+        assert_eq!(value["i"].as_i64(), Some(-4));
+        assert_eq!(value["i"].as_u64(), None);
+        assert_eq!(value["u"].as_i64(), None);
+        assert_eq!(value["u"].as_u64(), Some(u64::MAX));
+        assert_eq!(value["i"].as_f64(), Some(-4.0));
+        assert_eq!(value["f"].as_f64(), Some(2.5));
+        assert_eq!(value["f"].as_i64(), None);
+        assert_eq!(value["b"].as_bool(), Some(false));
+        assert_eq!(value["a"].as_array().map(Vec::len), Some(1));
+        assert!(value["a"][0].is_null());
+        assert!(value["o"].as_object().is_some_and(|map| map.is_empty()));
+
+        // Asking for the wrong kind gives `None`, or `Null` when indexing
+        assert_eq!(value["s"].as_i64(), None);
+        assert_eq!(value.as_array(), None);
+        assert!(value["s"]["x"].is_null());
+        assert!(value["missing"][3].is_null());
+        assert!(value.get("missing").is_none());
+        assert!(value["s"].get("x").is_none());
+    }
+
+    #[test]
+    fn test_changing_values() {
+        let mut value = parse(r#"{"list": [1, 2], "name": "a"}"#);
+
+        value["list"][1] = JsonValue::Int(20);
+        value["list"].as_array_mut().unwrap().push(JsonValue::Int(3));
+        *value.get_mut("name").unwrap() = JsonValue::Str("b".to_string());
+        value["new"]["nested"] = JsonValue::Bool(true);
+        assert_eq!(value.to_string(), r#"{"list":[1,20,3],"name":"b","new":{"nested":true}}"#);
+
+        let list = value["list"].take();
+        assert_eq!(list, parse("[1, 20, 3]"));
+        assert!(value["list"].is_null());
+        assert!(value.as_object_mut().unwrap().remove("list").is_some());
+
+        // Other backends create their own kind of map
+        let mut sorted = JsonParser::from_str("null").parse_with_maps::<Sorted>().unwrap();
+        sorted["b"] = 1.into();
+        sorted["a"] = 2.into();
+        assert_eq!(sorted.to_string(), r#"{"a":2,"b":1}"#);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot index into an array with key 'k'")]
+    fn test_index_mut_wrong_kind() {
+        let mut value = parse("[]");
+        value["k"] = JsonValue::Null;
+    }
+
+    #[test]
+    #[should_panic(expected = "index 2 is out of bounds for an array of length 2")]
+    fn test_index_mut_out_of_bounds() {
+        let mut value = parse("[1, 2]");
+        value[2] = JsonValue::Null;
+    }
+}
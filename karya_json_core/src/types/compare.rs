@@ -0,0 +1,358 @@
+//! Equality, ordering and hashing of values.
+//!
+//! Values compare structurally, by kind first and then by content, in a total
+//! order that `Hash` agrees with, so values can be sorted, deduplicated and
+//! used as keys in a `HashMap` or `BTreeMap`:
+//!
+//! - Kinds order as null, booleans, integers, floats, lossless numbers,
+//!   decimals, strings, arrays, objects. Numbers of different kinds are never
+//!   equal: `Int(1)` and `Float(1.0)` are distinct values.
+//! - The integer variants are one kind, compared by value, so `Int(1)` equals
+//!   `UInt(1)`.
+//! - Floats compare by value, except that `-0.0` equals `0.0` and every NaN
+//!   equals every other, above all other floats.
+//! - A `Number` compares by its value as an `f64` and then by its literal, so
+//!   `1.0` and `1` written differently are distinct, in that order.
+//! - Arrays compare element by element, and shorter first when one is a
+//!   prefix of the other.
+//! - Objects compare as their members sorted by key, whatever order the map
+//!   keeps them in, so two objects with the same members are equal.
+//!
+//! Both values are walked with explicit stacks, as for `Display` and `Drop`,
+//! so values nested to any depth can be compared and hashed.
+
+use crate::types::map::ObjectMap;
+use crate::types::{GenericJsonValue, MapBackend};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+impl<B: MapBackend> PartialEq for GenericJsonValue<B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<B: MapBackend> Eq for GenericJsonValue<B> {}
+
+impl<B: MapBackend> PartialOrd for GenericJsonValue<B> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<B: MapBackend> Ord for GenericJsonValue<B> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let mut left = Walk::new(self);
+        let mut right = Walk::new(other);
+        loop {
+            let ordering = match (left.next(), right.next()) {
+                (None, None) => return Ordering::Equal,
+                (Some(Token::End), Some(Token::End)) => Ordering::Equal,
+                (Some(Token::End), _) => Ordering::Less,
+                (_, Some(Token::End)) => Ordering::Greater,
+                (Some(Token::Key(a)), Some(Token::Key(b))) => a.cmp(b),
+                (Some(Token::Value(a)), Some(Token::Value(b))) => compare_shallow(a, b),
+                _ => unreachable!("walks that have matched so far stay in step"),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+    }
+}
+
+impl<B: MapBackend> Hash for GenericJsonValue<B> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for token in Walk::new(self) {
+            match token {
+                Token::Value(value) => {
+                    state.write_u8(rank(value));
+                    hash_shallow(value, state);
+                }
+                Token::Key(key) => key.hash(state),
+                Token::End => state.write_u8(u8::MAX),
+            }
+        }
+    }
+}
+
+// The position of the value's kind in the order of kinds
+fn rank<B: MapBackend>(value: &GenericJsonValue<B>) -> u8 {
+    match value {
+        GenericJsonValue::Null => 0,
+        GenericJsonValue::Bool(_) => 1,
+        GenericJsonValue::Int(_)
+        | GenericJsonValue::UInt(_)
+        | GenericJsonValue::Int128(_)
+        | GenericJsonValue::UInt128(_) => 2,
+        GenericJsonValue::Float(_) => 3,
+        GenericJsonValue::Number(_) => 4,
+        #[cfg(feature = "decimal")]
+        GenericJsonValue::Decimal(_) => 5,
+        GenericJsonValue::Str(_) => 6,
+        GenericJsonValue::Arr(_) => 7,
+        GenericJsonValue::Obj(_) => 8,
+    }
+}
+
+// Compares two values by kind and, for scalars, by value. The contents of
+// containers are compared by the walk.
+fn compare_shallow<B: MapBackend>(a: &GenericJsonValue<B>, b: &GenericJsonValue<B>) -> Ordering {
+    match (a, b) {
+        (GenericJsonValue::Bool(a), GenericJsonValue::Bool(b)) => a.cmp(b),
+        (GenericJsonValue::Float(a), GenericJsonValue::Float(b)) => canonical(*a).total_cmp(&canonical(*b)),
+        (GenericJsonValue::Number(a), GenericJsonValue::Number(b)) => canonical(a.as_f64())
+            .total_cmp(&canonical(b.as_f64()))
+            .then_with(|| a.as_str().cmp(b.as_str())),
+        #[cfg(feature = "decimal")]
+        (GenericJsonValue::Decimal(a), GenericJsonValue::Decimal(b)) => a.cmp(b),
+        (GenericJsonValue::Str(a), GenericJsonValue::Str(b)) => a.cmp(b),
+        _ => match (integer(a), integer(b)) {
+            (Some(a), Some(b)) => compare_integers(a, b),
+            _ => rank(a).cmp(&rank(b)),
+        },
+    }
+}
+
+fn hash_shallow<B: MapBackend, H: Hasher>(value: &GenericJsonValue<B>, state: &mut H) {
+    match value {
+        GenericJsonValue::Bool(b) => b.hash(state),
+        GenericJsonValue::Float(n) => canonical(*n).to_bits().hash(state),
+        GenericJsonValue::Number(n) => n.hash(state),
+        #[cfg(feature = "decimal")]
+        GenericJsonValue::Decimal(n) => n.hash(state),
+        GenericJsonValue::Str(s) => s.hash(state),
+        _ => {
+            if let Some(n) = integer(value) {
+                n.hash(state);
+            }
+        }
+    }
+}
+
+// The value of any integer variant: an i128 where it fits, and otherwise
+// the u128 above `i128::MAX` it must be
+fn integer<B: MapBackend>(value: &GenericJsonValue<B>) -> Option<Result<i128, u128>> {
+    match value {
+        GenericJsonValue::Int(n) => Some(Ok(i128::from(*n))),
+        GenericJsonValue::UInt(n) => Some(Ok(i128::from(*n))),
+        GenericJsonValue::Int128(n) => Some(Ok(*n)),
+        GenericJsonValue::UInt128(n) => Some(i128::try_from(*n).map_err(|_| *n)),
+        _ => None,
+    }
+}
+
+fn compare_integers(a: Result<i128, u128>, b: Result<i128, u128>) -> Ordering {
+    match (a, b) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Err(a), Err(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+    }
+}
+
+// The float that stands for `n` in comparisons: zero for either zero and the
+// standard NaN for any NaN, which `total_cmp` places above infinity
+fn canonical(n: f64) -> f64 {
+    if n.is_nan() {
+        f64::NAN
+    } else if n == 0.0 {
+        0.0
+    } else {
+        n
+    }
+}
+
+/// One step in a walk over a value: a value, then for containers their
+/// contents, with each object member's key before its value, and then `End`.
+enum Token<'v, B: MapBackend> {
+    Value(&'v GenericJsonValue<B>),
+    Key(&'v str),
+    End,
+}
+
+/// A walk over a value and everything in it, with object members visited in
+/// key order.
+struct Walk<'v, B: MapBackend> {
+    /// The root value, until it has been visited
+    root: Option<&'v GenericJsonValue<B>>,
+    /// The containers entered, with the members still to visit
+    stack: Vec<Members<'v, B>>,
+}
+
+enum Members<'v, B: MapBackend> {
+    Arr(std::slice::Iter<'v, GenericJsonValue<B>>),
+    /// The members sorted by key, and the value of the member whose key was
+    /// visited last
+    Obj(std::vec::IntoIter<(&'v str, &'v GenericJsonValue<B>)>, Option<&'v GenericJsonValue<B>>),
+}
+
+impl<'v, B: MapBackend> Walk<'v, B> {
+    fn new(root: &'v GenericJsonValue<B>) -> Self {
+        Self {
+            root: Some(root),
+            stack: Vec::new(),
+        }
+    }
+
+    // Visits a value, entering it if it is a container
+    fn visit(&mut self, value: &'v GenericJsonValue<B>) -> Token<'v, B> {
+        match value {
+            GenericJsonValue::Arr(items) => self.stack.push(Members::Arr(items.iter())),
+            GenericJsonValue::Obj(map) => {
+                let mut members: Vec<_> = map.iter().map(|(key, val)| (key.as_str(), val)).collect();
+                members.sort_unstable_by(|a, b| a.0.cmp(b.0));
+                self.stack.push(Members::Obj(members.into_iter(), None));
+            }
+            _ => {}
+        }
+        Token::Value(value)
+    }
+}
+
+impl<'v, B: MapBackend> Iterator for Walk<'v, B> {
+    type Item = Token<'v, B>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            return Some(self.visit(root));
+        }
+        let next = match self.stack.last_mut()? {
+            Members::Arr(items) => items.next(),
+            Members::Obj(members, pending) => match pending.take() {
+                Some(value) => Some(value),
+                None => {
+                    if let Some((key, value)) = members.next() {
+                        *pending = Some(value);
+                        return Some(Token::Key(key));
+                    }
+                    None
+                }
+            },
+        };
+        match next {
+            Some(value) => Some(self.visit(value)),
+            None => {
+                self.stack.pop();
+                Some(Token::End)
+            }
+        }
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{JsonMap, JsonParser, JsonValue, Sorted};
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+
+    fn parse(input: &str) -> JsonValue {
+        JsonParser::from_str(input).parse().unwrap()
+    }
+
+    fn hash(value: &JsonValue) -> u64 {
+        let mut state = DefaultHasher::new();
+        value.hash(&mut state);
+        state.finish()
+    }
+
+    #[test]
+    fn test_equality() {
+        // Objects are equal whatever order their members are in
+        let a = parse(r#"{"x": [1, 2.5, "s"], "y": {"p": null, "q": true}}"#);
+        let b = parse(r#"{"y": {"q": true, "p": null}, "x": [1, 2.5, "s"]}"#);
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(a, parse(r#"{"x": [1, 2.5, "s"], "y": {"p": null}}"#));
+
+        // Integers are equal whatever their width, but not to floats
+        assert_eq!(JsonValue::Int(7), JsonValue::UInt128(7));
+        assert_eq!(hash(&JsonValue::Int(7)), hash(&JsonValue::UInt128(7)));
+        assert_ne!(JsonValue::Int(1), JsonValue::Float(1.0));
+
+        // Zeros are equal, and so are NaNs
+        assert_eq!(JsonValue::Float(-0.0), JsonValue::Float(0.0));
+        assert_eq!(hash(&JsonValue::Float(-0.0)), hash(&JsonValue::Float(0.0)));
+        let other_nan = f64::from_bits(f64::NAN.to_bits() | 1);
+        assert_eq!(JsonValue::Float(f64::NAN), JsonValue::Float(-other_nan));
+        assert_eq!(hash(&JsonValue::Float(f64::NAN)), hash(&JsonValue::Float(-other_nan)));
+
+        let set: HashSet<JsonValue> = ["[1]", "[1.0]", "[1]", "{}", "[]", "[{}]"].iter().map(|s| parse(s)).collect();
+        assert_eq!(set.len(), 5);
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut values: Vec<JsonValue> = [
+            r#"{"a": 1}"#,
+            "[1, 2]",
+            r#""b""#,
+            "1e400",
+            "-1e400",
+            "0.5",
+            "340282366920938463463374607431768211455",
+            "-3",
+            "true",
+            "null",
+            "[1]",
+            r#"{"a": 0, "b": 0}"#,
+            r#"[1, {"a": 0}]"#,
+            r#""a""#,
+            "[]",
+        ]
+        .iter()
+        .map(|s| parse(s))
+        .collect();
+        values.push(JsonValue::Float(f64::NAN));
+        values.sort();
+
+        let sorted: Vec<String> = values.iter().map(|value| format!("{:?}", value)).collect();
+        assert_eq!(
+            sorted,
+            [
+                "Null",
+                "Bool(true)",
+                "Int(-3)",
+                "UInt128(340282366920938463463374607431768211455)",
+                "Float(-inf)",
+                "Float(0.5)",
+                "Float(inf)",
+                "Float(NaN)",
+                "Str(\"a\")",
+                "Str(\"b\")",
+                "Arr([])",
+                "Arr([Int(1)])",
+                "Arr([Int(1), Int(2)])",
+                "Arr([Int(1), Obj({\"a\": Int(0)})])",
+                "Obj({\"a\": Int(0), \"b\": Int(0)})",
+                "Obj({\"a\": Int(1)})",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clone_and_default() {
+        let value = parse(r#"{"b": [1, {"c": "d"}], "a": 2.5}"#);
+        let copy = value.clone();
+        assert_eq!(copy, value);
+        assert_eq!(copy.to_string(), value.to_string());
+        assert_eq!(JsonValue::default(), JsonValue::Null);
+
+        // Values nested too deeply to recurse through still clone and compare
+        let depth = 100_000;
+        let mut deep = JsonValue::Null;
+        for _ in 0..depth {
+            let mut map = JsonMap::new();
+            map.insert("k".to_string(), JsonValue::Arr(vec![deep].into()));
+            deep = JsonValue::Obj(map.into());
+        }
+        let copy = deep.clone();
+        assert_eq!(copy, deep);
+        assert_eq!(hash(&copy), hash(&deep));
+
+        let sorted = JsonParser::from_str(r#"{"b": 1, "a": 2}"#).parse_with_maps::<Sorted>().unwrap();
+        assert_eq!(sorted.clone().to_string(), r#"{"a":2,"b":1}"#);
+    }
+}
@@ -0,0 +1,398 @@
+//! The containers that hold the members of arrays and objects.
+//!
+//! Arrays and objects can be nested to any depth, and dropping them
+//! recursively would overflow the stack on a deep enough document. `JsonArray`
+//! and `JsonObject` wrap the `Vec` and the map that hold the members and take
+//! nested values apart one at a time when they are dropped. Doing this in the
+//! containers leaves the value enums themselves without a `Drop`, so their
+//! payloads can still be moved out by a pattern.
+
+use crate::types::map::ObjectMap;
+use crate::types::{GenericJsonValue, Hashed, JsonMap, JsonValue, JsonValueRef, MapBackend, Ordered, Small, Sorted, VecMap};
+use private::Shape;
+use std::borrow::{Borrow, Cow};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+/// A value type whose arrays and objects hold values of the same type:
+/// `GenericJsonValue` with any backend, and `JsonValueRef`.
+///
+/// This trait is sealed: it is implemented for those types only.
+pub trait Nested: private::Nested {
+    /// The type of object keys
+    type Key: Borrow<str>;
+    /// The map that holds the members of an object
+    type Map: ObjectMap<Self::Key, Self>;
+}
+
+impl<B: MapBackend> Nested for GenericJsonValue<B> {
+    type Key = String;
+    type Map = B::Map<Self>;
+}
+
+impl<'a> Nested for JsonValueRef<'a> {
+    type Key = Cow<'a, str>;
+    type Map = JsonMap<Cow<'a, str>, Self>;
+}
+
+pub(crate) mod private {
+    #[cfg(feature = "decimal")]
+    use crate::types::Decimal;
+    use crate::types::JsonNumber;
+
+    /// The operations the crate needs from the values the containers hold.
+    pub trait Nested: Sized {
+        /// Whether the value is an array or object
+        fn is_container(&self) -> bool;
+
+        /// Moves the members of an array or object onto `pending`, leaving
+        /// the container empty
+        fn take_members(&mut self, pending: &mut Vec<Self>);
+
+        /// Views the value in a form common to all value types
+        fn shape(&self) -> Shape<'_, Self>
+        where
+            Self: super::Nested;
+    }
+
+    /// A value of any value type, as the serializer sees it.
+    pub enum Shape<'v, V: super::Nested> {
+        Int(i64),
+        UInt(u64),
+        Int128(i128),
+        UInt128(u128),
+        Float(f64),
+        Number(&'v JsonNumber),
+        #[cfg(feature = "decimal")]
+        Decimal(&'v Decimal),
+        Bool(bool),
+        Str(&'v str),
+        Arr(&'v [V]),
+        Obj(&'v V::Map),
+        Null,
+    }
+}
+
+impl<B: MapBackend> private::Nested for GenericJsonValue<B> {
+    fn is_container(&self) -> bool {
+        matches!(self, GenericJsonValue::Arr(_) | GenericJsonValue::Obj(_))
+    }
+
+    fn take_members(&mut self, pending: &mut Vec<Self>) {
+        match self {
+            GenericJsonValue::Arr(items) => pending.append(&mut items.0),
+            GenericJsonValue::Obj(map) => pending.extend(std::mem::take(&mut map.0).into_iter().map(|(_, val)| val)),
+            _ => {}
+        }
+    }
+
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            GenericJsonValue::Int(n) => Shape::Int(*n),
+            GenericJsonValue::UInt(n) => Shape::UInt(*n),
+            GenericJsonValue::Int128(n) => Shape::Int128(*n),
+            GenericJsonValue::UInt128(n) => Shape::UInt128(*n),
+            GenericJsonValue::Float(n) => Shape::Float(*n),
+            GenericJsonValue::Number(n) => Shape::Number(n),
+            #[cfg(feature = "decimal")]
+            GenericJsonValue::Decimal(n) => Shape::Decimal(n),
+            GenericJsonValue::Bool(b) => Shape::Bool(*b),
+            GenericJsonValue::Str(s) => Shape::Str(s),
+            GenericJsonValue::Arr(items) => Shape::Arr(&items.0),
+            GenericJsonValue::Obj(map) => Shape::Obj(&map.0),
+            GenericJsonValue::Null => Shape::Null,
+        }
+    }
+}
+
+impl private::Nested for JsonValueRef<'_> {
+    fn is_container(&self) -> bool {
+        matches!(self, JsonValueRef::Arr(_) | JsonValueRef::Obj(_))
+    }
+
+    fn take_members(&mut self, pending: &mut Vec<Self>) {
+        match self {
+            JsonValueRef::Arr(items) => pending.append(&mut items.0),
+            JsonValueRef::Obj(map) => pending.extend(map.0.drain().map(|(_, val)| val)),
+            _ => {}
+        }
+    }
+
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            JsonValueRef::Int(n) => Shape::Int(*n),
+            JsonValueRef::UInt(n) => Shape::UInt(*n),
+            JsonValueRef::Int128(n) => Shape::Int128(*n),
+            JsonValueRef::UInt128(n) => Shape::UInt128(*n),
+            JsonValueRef::Float(n) => Shape::Float(*n),
+            JsonValueRef::Number(n) => Shape::Number(n),
+            #[cfg(feature = "decimal")]
+            JsonValueRef::Decimal(n) => Shape::Decimal(n),
+            JsonValueRef::Bool(b) => Shape::Bool(*b),
+            JsonValueRef::Str(s) => Shape::Str(s),
+            JsonValueRef::Arr(items) => Shape::Arr(&items.0),
+            JsonValueRef::Obj(map) => Shape::Obj(&map.0),
+            JsonValueRef::Null => Shape::Null,
+        }
+    }
+}
+
+// Drops the values one at a time, emptying each before it goes so that its
+// own drop stays shallow
+fn drop_flat<V: Nested>(mut pending: Vec<V>) {
+    while let Some(mut value) = pending.pop() {
+        value.take_members(&mut pending);
+    }
+}
+
+/// The elements of an array, held in a `Vec`.
+///
+/// It dereferences to the `Vec`, so it can be read and changed as one, and
+/// `into_vec` takes the `Vec` out. Dropping it drops nested arrays and objects
+/// one at a time rather than recursively, so that values nested to any depth
+/// can be dropped without overflowing the stack.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::JsonValue;
+///
+/// let value = JsonValue::Arr(vec![JsonValue::Int(1), JsonValue::Int(2)].into());
+///
+/// if let JsonValue::Arr(items) = value {
+///     assert_eq!(items.len(), 2);
+///     let items: Vec<JsonValue> = items.into_vec();
+///     assert!(matches!(items[..], [JsonValue::Int(1), JsonValue::Int(2)]));
+/// }
+/// ```
+pub struct JsonArray<V: Nested = JsonValue>(Vec<V>);
+
+impl<V: Nested> JsonArray<V> {
+    /// Creates an empty array.
+    pub fn new() -> Self {
+        JsonArray(Vec::new())
+    }
+
+    /// Takes the elements out as a `Vec`.
+    pub fn into_vec(mut self) -> Vec<V> {
+        std::mem::take(&mut self.0)
+    }
+}
+
+impl<V: Nested> Drop for JsonArray<V> {
+    fn drop(&mut self) {
+        if self.0.iter().any(V::is_container) {
+            drop_flat(std::mem::take(&mut self.0));
+        }
+    }
+}
+
+impl<V: Nested> Default for JsonArray<V> {
+    fn default() -> Self {
+        JsonArray::new()
+    }
+}
+
+impl<V: Nested + Clone> Clone for JsonArray<V> {
+    fn clone(&self) -> Self {
+        JsonArray(self.0.clone())
+    }
+}
+
+impl<V: Nested + fmt::Debug> fmt::Debug for JsonArray<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<V: Nested> Deref for JsonArray<V> {
+    type Target = Vec<V>;
+
+    fn deref(&self) -> &Vec<V> {
+        &self.0
+    }
+}
+
+impl<V: Nested> DerefMut for JsonArray<V> {
+    fn deref_mut(&mut self) -> &mut Vec<V> {
+        &mut self.0
+    }
+}
+
+impl<V: Nested> From<Vec<V>> for JsonArray<V> {
+    fn from(items: Vec<V>) -> Self {
+        JsonArray(items)
+    }
+}
+
+impl<V: Nested> FromIterator<V> for JsonArray<V> {
+    fn from_iter<I: IntoIterator<Item = V>>(iter: I) -> Self {
+        JsonArray(iter.into_iter().collect())
+    }
+}
+
+impl<V: Nested> IntoIterator for JsonArray<V> {
+    type Item = V;
+    type IntoIter = std::vec::IntoIter<V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+impl<'v, V: Nested> IntoIterator for &'v JsonArray<V> {
+    type Item = &'v V;
+    type IntoIter = std::slice::Iter<'v, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'v, V: Nested> IntoIterator for &'v mut JsonArray<V> {
+    type Item = &'v mut V;
+    type IntoIter = std::slice::IterMut<'v, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+/// The members of an object, held in the map the value type chooses.
+///
+/// It dereferences to the map, so it can be read and changed as one, and
+/// `into_map` takes the map out. Like `JsonArray`, dropping it drops nested
+/// arrays and objects one at a time rather than recursively.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{JsonMap, JsonValue};
+///
+/// let mut map = JsonMap::new();
+/// map.insert("id".to_string(), JsonValue::Int(7));
+/// let value = JsonValue::Obj(map.into());
+///
+/// if let JsonValue::Obj(members) = value {
+///     assert!(matches!(members.get("id"), Some(JsonValue::Int(7))));
+///     let map: JsonMap = members.into_map();
+///     assert_eq!(map.len(), 1);
+/// }
+/// ```
+pub struct JsonObject<V: Nested = JsonValue>(V::Map);
+
+impl<V: Nested> JsonObject<V> {
+    /// Creates an empty object.
+    pub fn new() -> Self {
+        JsonObject(V::Map::default())
+    }
+
+    /// Wraps a map of members. `From` does the same for the maps that come
+    /// with the crate.
+    pub fn from_map(map: V::Map) -> Self {
+        JsonObject(map)
+    }
+
+    /// Takes the members out as a map.
+    pub fn into_map(mut self) -> V::Map {
+        std::mem::take(&mut self.0)
+    }
+}
+
+impl<V: Nested> Drop for JsonObject<V> {
+    fn drop(&mut self) {
+        if self.0.iter().any(|(_, val)| val.is_container()) {
+            drop_flat(std::mem::take(&mut self.0).into_iter().map(|(_, val)| val).collect());
+        }
+    }
+}
+
+impl<V: Nested> Default for JsonObject<V> {
+    fn default() -> Self {
+        JsonObject::new()
+    }
+}
+
+impl<V: Nested> Clone for JsonObject<V>
+where
+    V::Map: Clone,
+{
+    fn clone(&self) -> Self {
+        JsonObject(self.0.clone())
+    }
+}
+
+/// Formats the members as a map, which the map itself cannot be relied on to
+/// do.
+impl<V: Nested + fmt::Debug> fmt::Debug for JsonObject<V>
+where
+    V::Key: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.0.iter()).finish()
+    }
+}
+
+impl<V: Nested> Deref for JsonObject<V> {
+    type Target = V::Map;
+
+    fn deref(&self) -> &V::Map {
+        &self.0
+    }
+}
+
+impl<V: Nested> DerefMut for JsonObject<V> {
+    fn deref_mut(&mut self) -> &mut V::Map {
+        &mut self.0
+    }
+}
+
+// Wrapping a map is `from_map` for any value type, but `From` can only be
+// given for each map by name
+macro_rules! map_conversions {
+    ($($backend:ty => $map:ty),*) => {$(
+        impl From<$map> for JsonObject<GenericJsonValue<$backend>> {
+            fn from(map: $map) -> Self {
+                JsonObject(map)
+            }
+        }
+    )*};
+}
+
+map_conversions! {
+    Ordered => JsonMap<String, GenericJsonValue<Ordered>>,
+    Sorted => BTreeMap<String, GenericJsonValue<Sorted>>,
+    Hashed => HashMap<String, GenericJsonValue<Hashed>>,
+    Small => VecMap<String, GenericJsonValue<Small>>
+}
+
+impl<'a> From<JsonMap<Cow<'a, str>, JsonValueRef<'a>>> for JsonObject<JsonValueRef<'a>> {
+    fn from(map: JsonMap<Cow<'a, str>, JsonValueRef<'a>>) -> Self {
+        JsonObject(map)
+    }
+}
+
+impl<V: Nested> FromIterator<(V::Key, V)> for JsonObject<V> {
+    fn from_iter<I: IntoIterator<Item = (V::Key, V)>>(iter: I) -> Self {
+        JsonObject(iter.into_iter().collect())
+    }
+}
+
+impl<V: Nested> IntoIterator for JsonObject<V> {
+    type Item = (V::Key, V);
+    type IntoIter = <V::Map as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_map().into_iter()
+    }
+}
+
+impl<'m, V: Nested> IntoIterator for &'m JsonObject<V> {
+    type Item = (&'m V::Key, &'m V);
+    type IntoIter = <V::Map as ObjectMap<V::Key, V>>::Iter<'m>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
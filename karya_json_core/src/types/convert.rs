@@ -0,0 +1,461 @@
+//! Conversions between `JsonValue` and Rust types.
+//!
+//! Conversions into `JsonValue` always succeed. Conversions out of it are
+//! checked: a value of the wrong kind is a `DeserializeError::TypeMismatch`,
+//! and a number that doesn't fit the target type is a
+//! `DeserializeError::InvalidValue`, never a silently wrapped or rounded one.
+//! Floats are the exception, since any number rounds to the nearest float.
+//!
+//! # Examples
+//!
+//! ```
+//! use karya_json::types::JsonValue;
+//! use std::collections::BTreeMap;
+//!
+//! let mut user = BTreeMap::new();
+//! user.insert("name".to_string(), JsonValue::from("Alice"));
+//! user.insert("scores".to_string(), JsonValue::from(vec![90, 85]));
+//! user.insert("team".to_string(), JsonValue::from(None::<String>));
+//! let value = JsonValue::from(user);
+//! assert_eq!(value.to_string(), r#"{"name":"Alice","scores":[90,85],"team":null}"#);
+//!
+//! let parsed: JsonValue = r#"{"scores": [90, 85]}"#.parse().unwrap();
+//! let scores = Vec::<u8>::try_from(parsed["scores"].clone()).unwrap();
+//! assert_eq!(scores, [90, 85]);
+//! ```
+
+use crate::types::{GenericJsonValue, JsonObject, JsonParser, MapBackend};
+use crate::types::error::DeserializeError;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+use std::str::FromStr;
+
+impl<B: MapBackend> GenericJsonValue<B> {
+    // Builds the variant the parser would produce for the integer: the first
+    // of `Int`, `UInt` and `Int128` that holds it
+    fn from_i128(n: i128) -> Self {
+        if let Ok(n) = i64::try_from(n) {
+            GenericJsonValue::Int(n)
+        } else if let Ok(n) = u64::try_from(n) {
+            GenericJsonValue::UInt(n)
+        } else {
+            GenericJsonValue::Int128(n)
+        }
+    }
+
+    // Likewise for an unsigned integer, which may need `UInt128`
+    fn from_u128(n: u128) -> Self {
+        match i128::try_from(n) {
+            Ok(n) => Self::from_i128(n),
+            Err(_) => GenericJsonValue::UInt128(n),
+        }
+    }
+
+    // The value as an integer of whichever variant holds it, with any
+    // `Number` or `Decimal` that is an integer converted first. The error side of the
+    // inner result holds a u128 too large for an i128.
+    fn as_integer(&self, target: &str) -> Result<Result<i128, u128>, DeserializeError> {
+        match self {
+            GenericJsonValue::Int(n) => Ok(Ok(i128::from(*n))),
+            GenericJsonValue::UInt(n) => Ok(Ok(i128::from(*n))),
+            GenericJsonValue::Int128(n) => Ok(Ok(*n)),
+            GenericJsonValue::UInt128(n) => Ok(i128::try_from(*n).map_err(|_| *n)),
+            GenericJsonValue::Number(n) => match (n.as_i128(), n.as_u128()) {
+                (Some(n), _) => Ok(Ok(n)),
+                (None, Some(n)) => Ok(Err(n)),
+                (None, None) if n.is_integer() => Err(out_of_range(self, target)),
+                (None, None) => Err(type_mismatch("an integer", self)),
+            },
+            #[cfg(feature = "decimal")]
+            GenericJsonValue::Decimal(n) => match (n.to_i128(), n.to_u128()) {
+                (Some(n), _) => Ok(Ok(n)),
+                (None, Some(n)) => Ok(Err(n)),
+                (None, None) if n.is_integer() => Err(out_of_range(self, target)),
+                (None, None) => Err(type_mismatch("an integer", self)),
+            },
+            _ => Err(type_mismatch("an integer", self)),
+        }
+    }
+
+    // A short description of the kind of value, for error messages
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            GenericJsonValue::Int(_) | GenericJsonValue::UInt(_) | GenericJsonValue::Int128(_) | GenericJsonValue::UInt128(_) => "an integer",
+            GenericJsonValue::Float(_) | GenericJsonValue::Number(_) => "a number",
+            #[cfg(feature = "decimal")]
+            GenericJsonValue::Decimal(_) => "a number",
+            GenericJsonValue::Bool(_) => "a boolean",
+            GenericJsonValue::Str(_) => "a string",
+            GenericJsonValue::Arr(_) => "an array",
+            GenericJsonValue::Obj(_) => "an object",
+            GenericJsonValue::Null => "null",
+        }
+    }
+}
+
+fn type_mismatch<B: MapBackend>(expected: &str, value: &GenericJsonValue<B>) -> DeserializeError {
+    DeserializeError::TypeMismatch(format!("expected {}, found {}", expected, value.kind()))
+}
+
+fn out_of_range<B: MapBackend>(value: &GenericJsonValue<B>, target: &str) -> DeserializeError {
+    DeserializeError::InvalidValue(format!("{} is out of range for {}", value, target))
+}
+
+// Implements `From<$t> for JsonValue` and the checked `TryFrom<&JsonValue>`
+// and `TryFrom<JsonValue>` for integer types, going through the widest
+// integer of the same signedness
+macro_rules! integer_conversions {
+    ($($t:ty => $from:ident),* $(,)?) => {$(
+        impl<B: MapBackend> From<$t> for GenericJsonValue<B> {
+            fn from(n: $t) -> Self {
+                // Every integer type is at most 128 bits wide
+                GenericJsonValue::$from(n as _)
+            }
+        }
+
+        impl<B: MapBackend> TryFrom<&GenericJsonValue<B>> for $t {
+            type Error = DeserializeError;
+
+            fn try_from(value: &GenericJsonValue<B>) -> Result<Self, Self::Error> {
+                let converted = match value.as_integer(stringify!($t))? {
+                    Ok(n) => <$t>::try_from(n).ok(),
+                    Err(n) => <$t>::try_from(n).ok(),
+                };
+                converted.ok_or_else(|| out_of_range(value, stringify!($t)))
+            }
+        }
+
+        impl<B: MapBackend> TryFrom<GenericJsonValue<B>> for $t {
+            type Error = DeserializeError;
+
+            fn try_from(value: GenericJsonValue<B>) -> Result<Self, Self::Error> {
+                <$t>::try_from(&value)
+            }
+        }
+    )*};
+}
+
+integer_conversions! {
+    i8 => from_i128,
+    i16 => from_i128,
+    i32 => from_i128,
+    i64 => from_i128,
+    i128 => from_i128,
+    isize => from_i128,
+    u8 => from_u128,
+    u16 => from_u128,
+    u32 => from_u128,
+    u64 => from_u128,
+    u128 => from_u128,
+    usize => from_u128,
+}
+
+impl<B: MapBackend> From<f64> for GenericJsonValue<B> {
+    fn from(n: f64) -> Self {
+        GenericJsonValue::Float(n)
+    }
+}
+
+impl<B: MapBackend> From<f32> for GenericJsonValue<B> {
+    fn from(n: f32) -> Self {
+        GenericJsonValue::Float(f64::from(n))
+    }
+}
+
+impl<B: MapBackend> From<bool> for GenericJsonValue<B> {
+    fn from(b: bool) -> Self {
+        GenericJsonValue::Bool(b)
+    }
+}
+
+impl<B: MapBackend> From<String> for GenericJsonValue<B> {
+    fn from(s: String) -> Self {
+        GenericJsonValue::Str(s)
+    }
+}
+
+impl<B: MapBackend> From<&str> for GenericJsonValue<B> {
+    fn from(s: &str) -> Self {
+        GenericJsonValue::Str(s.to_owned())
+    }
+}
+
+/// `None` becomes `Null`.
+impl<B: MapBackend, T: Into<GenericJsonValue<B>>> From<Option<T>> for GenericJsonValue<B> {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(GenericJsonValue::Null, Into::into)
+    }
+}
+
+impl<B: MapBackend, T: Into<GenericJsonValue<B>>> From<Vec<T>> for GenericJsonValue<B> {
+    fn from(items: Vec<T>) -> Self {
+        GenericJsonValue::Arr(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<B: MapBackend, T: Clone + Into<GenericJsonValue<B>>> From<&[T]> for GenericJsonValue<B> {
+    fn from(items: &[T]) -> Self {
+        GenericJsonValue::Arr(items.iter().cloned().map(Into::into).collect())
+    }
+}
+
+/// The members are inserted in the map's iteration order, which is
+/// arbitrary, so an `Ordered` object keeps no particular order.
+impl<B: MapBackend, T: Into<GenericJsonValue<B>>, S> From<HashMap<String, T, S>> for GenericJsonValue<B> {
+    fn from(map: HashMap<String, T, S>) -> Self {
+        GenericJsonValue::Obj(map.into_iter().map(|(key, val)| (key, val.into())).collect())
+    }
+}
+
+/// The members are inserted in key order.
+impl<B: MapBackend, T: Into<GenericJsonValue<B>>> From<BTreeMap<String, T>> for GenericJsonValue<B> {
+    fn from(map: BTreeMap<String, T>) -> Self {
+        GenericJsonValue::Obj(map.into_iter().map(|(key, val)| (key, val.into())).collect())
+    }
+}
+
+/// Converts any number, rounding to the nearest `f64` as `as_f64` does.
+impl<B: MapBackend> TryFrom<GenericJsonValue<B>> for f64 {
+    type Error = DeserializeError;
+
+    fn try_from(value: GenericJsonValue<B>) -> Result<Self, Self::Error> {
+        value.as_f64().ok_or_else(|| type_mismatch("a number", &value))
+    }
+}
+
+/// Converts any number, rounding to the nearest `f32`. A finite number
+/// beyond the range of `f32` is out of range rather than infinite.
+impl<B: MapBackend> TryFrom<GenericJsonValue<B>> for f32 {
+    type Error = DeserializeError;
+
+    fn try_from(value: GenericJsonValue<B>) -> Result<Self, Self::Error> {
+        let n = value.as_f64().ok_or_else(|| type_mismatch("a number", &value))?;
+        let rounded = n as f32;
+        if rounded.is_infinite() && n.is_finite() {
+            return Err(out_of_range(&value, "f32"));
+        }
+        Ok(rounded)
+    }
+}
+
+impl<B: MapBackend> TryFrom<GenericJsonValue<B>> for bool {
+    type Error = DeserializeError;
+
+    fn try_from(value: GenericJsonValue<B>) -> Result<Self, Self::Error> {
+        value.as_bool().ok_or_else(|| type_mismatch("a boolean", &value))
+    }
+}
+
+impl<B: MapBackend> TryFrom<GenericJsonValue<B>> for String {
+    type Error = DeserializeError;
+
+    fn try_from(value: GenericJsonValue<B>) -> Result<Self, Self::Error> {
+        match value {
+            GenericJsonValue::Str(s) => Ok(s),
+            _ => Err(type_mismatch("a string", &value)),
+        }
+    }
+}
+
+/// Converts an array whose elements all convert. With `Vec<JsonValue>` this
+/// takes the elements as they are.
+impl<B: MapBackend, T: TryFrom<GenericJsonValue<B>, Error: Into<DeserializeError>>> TryFrom<GenericJsonValue<B>>
+    for Vec<T>
+{
+    type Error = DeserializeError;
+
+    fn try_from(value: GenericJsonValue<B>) -> Result<Self, Self::Error> {
+        match value {
+            GenericJsonValue::Arr(items) => items.into_iter().map(|item| T::try_from(item).map_err(Into::into)).collect(),
+            _ => Err(type_mismatch("an array", &value)),
+        }
+    }
+}
+
+/// Converts an object whose values all convert.
+impl<B: MapBackend, T: TryFrom<GenericJsonValue<B>, Error: Into<DeserializeError>>, S: BuildHasher + Default>
+    TryFrom<GenericJsonValue<B>> for HashMap<String, T, S>
+{
+    type Error = DeserializeError;
+
+    fn try_from(value: GenericJsonValue<B>) -> Result<Self, Self::Error> {
+        match value {
+            GenericJsonValue::Obj(map) => members(map).collect(),
+            _ => Err(type_mismatch("an object", &value)),
+        }
+    }
+}
+
+/// Converts an object whose values all convert.
+impl<B: MapBackend, T: TryFrom<GenericJsonValue<B>, Error: Into<DeserializeError>>> TryFrom<GenericJsonValue<B>>
+    for BTreeMap<String, T>
+{
+    type Error = DeserializeError;
+
+    fn try_from(value: GenericJsonValue<B>) -> Result<Self, Self::Error> {
+        match value {
+            GenericJsonValue::Obj(map) => members(map).collect(),
+            _ => Err(type_mismatch("an object", &value)),
+        }
+    }
+}
+
+// Converts the values of an object's members, keeping their keys
+fn members<B: MapBackend, T: TryFrom<GenericJsonValue<B>, Error: Into<DeserializeError>>>(
+    map: JsonObject<GenericJsonValue<B>>,
+) -> impl Iterator<Item = Result<(String, T), DeserializeError>> {
+    map.into_iter().map(|(key, val)| T::try_from(val).map(|val| (key, val)).map_err(Into::into))
+}
+
+/// Parses JSON text with `JsonParser` and its default options.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::JsonValue;
+///
+/// let value: JsonValue = "[1, 2.5, null]".parse().unwrap();
+/// assert_eq!(value[1].as_f64(), Some(2.5));
+/// assert!("[1, 2".parse::<JsonValue>().is_err());
+/// ```
+impl<B: MapBackend> FromStr for GenericJsonValue<B> {
+    type Err = DeserializeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonParser::from_str(s).parse_with_maps()
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{JsonNumber, JsonParser, JsonValue};
+
+    #[test]
+    fn test_integers_into_values() {
+        assert!(matches!(JsonValue::from(-5i8), JsonValue::Int(-5)));
+        assert!(matches!(JsonValue::from(7usize), JsonValue::Int(7)));
+        assert!(matches!(JsonValue::from(u64::MAX), JsonValue::UInt(u64::MAX)));
+        assert!(matches!(JsonValue::from(i128::MIN), JsonValue::Int128(i128::MIN)));
+        assert!(matches!(JsonValue::from(u128::MAX), JsonValue::UInt128(u128::MAX)));
+        // Wide types holding small numbers give the same variant the parser would
+        assert!(matches!(JsonValue::from(1u128), JsonValue::Int(1)));
+        assert!(matches!(JsonValue::from(1i128 << 64), JsonValue::Int128(_)));
+        assert_eq!(JsonValue::from(u128::MAX).to_string(), u128::MAX.to_string());
+    }
+
+    #[test]
+    fn test_checked_integers_from_values() {
+        let value = JsonValue::from(300u16);
+        assert_eq!(u16::try_from(&value).unwrap(), 300);
+        assert_eq!(i64::try_from(&value).unwrap(), 300);
+        assert!(matches!(u8::try_from(&value), Err(DeserializeError::InvalidValue(msg)) if msg == "300 is out of range for u8"));
+        assert!(i8::try_from(&JsonValue::from(-129)).is_err());
+        assert!(u32::try_from(&JsonValue::from(-1)).is_err());
+
+        let value = JsonParser::from_str("18446744073709551615").parse().unwrap();
+        assert_eq!(u64::try_from(&value).unwrap(), u64::MAX);
+        assert_eq!(i128::try_from(&value).unwrap(), i128::from(u64::MAX));
+        assert!(i64::try_from(&value).is_err());
+        assert_eq!(u128::try_from(&JsonValue::UInt128(u128::MAX)).unwrap(), u128::MAX);
+        assert!(i128::try_from(&JsonValue::UInt128(u128::MAX)).is_err());
+
+        // Lossless numbers convert when they are integers
+        let number = |literal: &str| JsonValue::Number(literal.parse::<JsonNumber>().unwrap());
+        assert_eq!(u64::try_from(&number("-0")).unwrap(), 0);
+        assert_eq!(u128::try_from(&number("340282366920938463463374607431768211455")).unwrap(), u128::MAX);
+        assert!(matches!(u128::try_from(&number("340282366920938463463374607431768211456")), Err(DeserializeError::InvalidValue(_))));
+        assert!(matches!(i64::try_from(&number("1.0")), Err(DeserializeError::TypeMismatch(_))));
+
+        // Other kinds of value are never integers
+        for value in [JsonValue::Float(1.0), JsonValue::Str("1".to_string()), JsonValue::Null] {
+            assert!(matches!(i64::try_from(&value), Err(DeserializeError::TypeMismatch(_))));
+        }
+        let error = i32::try_from(&JsonValue::Bool(true)).unwrap_err();
+        assert_eq!(error.to_string(), "Type mismatch in JSON: expected an integer, found a boolean");
+    }
+
+    #[test]
+    fn test_values_from_std_types() {
+        assert!(matches!(JsonValue::from(2.5f32), JsonValue::Float(n) if n == 2.5));
+        assert!(matches!(JsonValue::from(true), JsonValue::Bool(true)));
+        assert_eq!(JsonValue::from("a\"b").to_string(), r#""a\"b""#);
+        assert_eq!(JsonValue::from(String::from("text")), JsonValue::from("text"));
+        assert!(JsonValue::from(None::<i32>).is_null());
+        assert_eq!(JsonValue::from(Some(3)), JsonValue::Int(3));
+        assert_eq!(JsonValue::from(vec![Some(1.5), None]).to_string(), "[1.5,null]");
+        assert_eq!(JsonValue::from(&["a", "b"][..]).to_string(), r#"["a","b"]"#);
+        assert_eq!(JsonValue::from(Vec::<JsonValue>::new()).to_string(), "[]");
+
+        let map = BTreeMap::from([("b".to_string(), vec![1, 2]), ("a".to_string(), vec![])]);
+        assert_eq!(JsonValue::from(map).to_string(), r#"{"a":[],"b":[1,2]}"#);
+        let map = HashMap::from([("key".to_string(), JsonValue::from(1))]);
+        assert_eq!(JsonValue::from(map).to_string(), r#"{"key":1}"#);
+
+        // Values built from nested types are the same as parsed ones
+        let nested = JsonValue::from(vec![BTreeMap::from([("x".to_string(), Some("y"))])]);
+        assert_eq!(nested, r#"[{"x": "y"}]"#.parse().unwrap());
+    }
+
+    #[test]
+    fn test_checked_conversions() {
+        let value: JsonValue = r#"{"n": 3, "f": 0.1, "s": "text", "b": true, "a": [1, 2], "o": {"k": "v"}}"#.parse().unwrap();
+
+        assert_eq!(u8::try_from(value["n"].clone()).unwrap(), 3);
+        assert_eq!(f64::try_from(value["n"].clone()).unwrap(), 3.0);
+        assert_eq!(f64::try_from(value["f"].clone()).unwrap(), 0.1);
+        assert_eq!(f32::try_from(value["f"].clone()).unwrap(), 0.1f32);
+        assert_eq!(String::try_from(value["s"].clone()).unwrap(), "text");
+        assert!(bool::try_from(value["b"].clone()).unwrap());
+        assert_eq!(Vec::<i64>::try_from(value["a"].clone()).unwrap(), [1, 2]);
+        let map = BTreeMap::<String, String>::try_from(value["o"].clone()).unwrap();
+        assert_eq!(map, BTreeMap::from([("k".to_string(), "v".to_string())]));
+        let map = HashMap::<String, JsonValue>::try_from(value.clone()).unwrap();
+        assert_eq!(map.len(), 6);
+        assert_eq!(Vec::<JsonValue>::try_from(value["a"].clone()).unwrap(), [JsonValue::Int(1), JsonValue::Int(2)]);
+
+        // Each kind of value is rejected as the wrong type by the others
+        let error = String::try_from(value["n"].clone()).unwrap_err();
+        assert_eq!(error.to_string(), "Type mismatch in JSON: expected a string, found an integer");
+        assert!(matches!(bool::try_from(JsonValue::Null), Err(DeserializeError::TypeMismatch(_))));
+        assert!(matches!(f64::try_from(value["s"].clone()), Err(DeserializeError::TypeMismatch(_))));
+        assert!(matches!(Vec::<i64>::try_from(value["o"].clone()), Err(DeserializeError::TypeMismatch(_))));
+        assert!(matches!(BTreeMap::<String, i64>::try_from(value["a"].clone()), Err(DeserializeError::TypeMismatch(_))));
+
+        // An element that fails to convert fails the whole container
+        assert!(matches!(Vec::<u8>::try_from(JsonValue::from(vec![1, 256])), Err(DeserializeError::InvalidValue(_))));
+        assert!(matches!(BTreeMap::<String, i64>::try_from(value["o"].clone()), Err(DeserializeError::TypeMismatch(_))));
+
+        // Floats only fail when the number doesn't fit at all
+        assert!(matches!(f32::try_from(JsonValue::Float(1e300)), Err(DeserializeError::InvalidValue(_))));
+        assert_eq!(f32::try_from(JsonValue::Float(f64::INFINITY)).unwrap(), f32::INFINITY);
+        assert_eq!(f64::try_from(JsonValue::from(u128::MAX)).unwrap(), u128::MAX as f64);
+    }
+
+    #[test]
+    fn test_from_str() {
+        let value: JsonValue = r#"{"b": 1, "a": [true, null]}"#.parse().unwrap();
+        assert_eq!(value.to_string(), r#"{"b":1,"a":[true,null]}"#);
+
+        let sorted: GenericJsonValue<crate::types::Sorted> = r#"{"b": 1, "a": 2}"#.parse().unwrap();
+        assert_eq!(sorted.to_string(), r#"{"a":2,"b":1}"#);
+
+        assert!(matches!("[1,".parse::<JsonValue>(), Err(DeserializeError::InvalidJson(_))));
+        assert!(matches!("1 2".parse::<JsonValue>(), Err(DeserializeError::InvalidJson(_))));
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_checked_integers_from_decimals() {
+        use crate::types::Decimal;
+
+        // Decimals compare by value, so any whole number converts
+        let decimal = |literal: &str| JsonValue::Decimal(literal.parse::<Decimal>().unwrap());
+        assert_eq!(i32::try_from(&decimal("-12.00")).unwrap(), -12);
+        assert_eq!(u128::try_from(&decimal("3.40282366920938463463374607431768211455E+38")).unwrap(), u128::MAX);
+        assert!(matches!(u8::try_from(&decimal("2.56e2")), Err(DeserializeError::InvalidValue(msg)) if msg == "256 is out of range for u8"));
+        assert!(matches!(i64::try_from(&decimal("1e40")), Err(DeserializeError::InvalidValue(_))));
+        assert!(matches!(i64::try_from(&decimal("0.5")), Err(DeserializeError::TypeMismatch(_))));
+    }
+}
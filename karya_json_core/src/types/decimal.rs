@@ -0,0 +1,367 @@
+//! Exact decimal numbers, enabled by the `decimal` feature.
+//!
+//! `Decimal` stores a number as its decimal digits and a power of ten, with
+//! no limit on either, so values such as `0.1` are held exactly rather than
+//! as the nearest binary fraction. Arithmetic is out of scope; the type exists
+//! to carry amounts through parsing, comparison and printing untouched.
+
+use crate::types::error::{DeserializeError, ErrorCode};
+use crate::types::{JsonEvent, JsonReader, float};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// An arbitrary-precision decimal number.
+///
+/// `JsonParser` produces `JsonValue::Decimal` for every number when
+/// `ParserOptions::number_mode` is `NumberMode::Decimal`. The literal is
+/// converted straight from its digits, never through `f64`.
+///
+/// Comparison is by numeric value, so `1.10` equals `1.1`, while printing
+/// keeps the digits as written: trailing zeros in the fraction are kept, and
+/// very large or very small numbers are written with an exponent. Use
+/// `normalize` to drop the trailing zeros.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{Decimal, JsonParser, JsonValue, NumberMode, ParserOptions};
+///
+/// let options = ParserOptions { number_mode: NumberMode::Decimal, ..ParserOptions::default() };
+/// let input = r#"{"total": 123456789012345678.99, "rate": 0.10}"#;
+/// let value = JsonParser::from_str(input).with_options(options).parse().unwrap();
+///
+/// if let JsonValue::Obj(map) = &value {
+///     if let Some(JsonValue::Decimal(total)) = map.get("total") {
+///         assert_eq!(total.to_string(), "123456789012345678.99");
+///     }
+///     if let Some(JsonValue::Decimal(rate)) = map.get("rate") {
+///         assert_eq!(*rate, "0.1".parse::<Decimal>().unwrap());
+///         assert_eq!(rate.to_string(), "0.10");
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Decimal {
+    negative: bool,
+    /// The coefficient's ASCII digits without leading zeros; empty for zero
+    digits: String,
+    /// The power of ten the coefficient is multiplied by
+    exponent: i64,
+}
+
+impl Decimal {
+    // Converts a literal the reader has already validated, or returns `None`
+    // if its exponent is too large to represent
+    pub(crate) fn from_literal(literal: &str) -> Option<Self> {
+        let (negative, literal) = match literal.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, literal),
+        };
+        let (mantissa, exponent) = match literal.find(['e', 'E']) {
+            Some(at) => (&literal[..at], literal[at + 1..].parse::<i64>().ok()?),
+            None => (literal, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+        let digits = format!("{}{}", integer, fraction).trim_start_matches('0').to_owned();
+        let exponent = exponent.checked_sub(i64::try_from(fraction.len()).ok()?)?;
+        Some(Self { negative, digits, exponent })
+    }
+
+    /// Whether the number is zero, of either sign.
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// Whether the number is below zero.
+    pub fn is_negative(&self) -> bool {
+        self.negative && !self.is_zero()
+    }
+
+    /// Returns the same number with trailing zeros removed and zero made
+    /// positive, so numbers that are equal print the same way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::Decimal;
+    ///
+    /// let amount: Decimal = "12.3400".parse().unwrap();
+    /// assert_eq!(amount.normalize().to_string(), "12.34");
+    ///
+    /// let round: Decimal = "1500".parse().unwrap();
+    /// assert_eq!(round.normalize().to_string(), "1.5E+3");
+    /// ```
+    pub fn normalize(&self) -> Self {
+        let digits = self.digits.trim_end_matches('0');
+        let removed = self.digits.len() - digits.len();
+        Self {
+            negative: self.is_negative(),
+            digits: digits.to_owned(),
+            exponent: if digits.is_empty() { 0 } else { self.exponent.saturating_add(removed as i64) },
+        }
+    }
+
+    /// Whether the number is a whole number, however it was written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::Decimal;
+    ///
+    /// assert!("4.2e1".parse::<Decimal>().unwrap().is_integer());
+    /// assert!("-7.000".parse::<Decimal>().unwrap().is_integer());
+    /// assert!(!"0.5".parse::<Decimal>().unwrap().is_integer());
+    /// ```
+    pub fn is_integer(&self) -> bool {
+        self.normalize().exponent >= 0
+    }
+
+    /// Returns the number as an `i128`, if it is a whole number in range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::Decimal;
+    ///
+    /// assert_eq!("-42.000".parse::<Decimal>().unwrap().to_i128(), Some(-42));
+    /// assert_eq!("0.5".parse::<Decimal>().unwrap().to_i128(), None);
+    /// assert_eq!("1e40".parse::<Decimal>().unwrap().to_i128(), None);
+    /// ```
+    pub fn to_i128(&self) -> Option<i128> {
+        self.integer()
+    }
+
+    /// Returns the number as a `u128`, if it is a non-negative whole number
+    /// in range.
+    pub fn to_u128(&self) -> Option<u128> {
+        self.integer()
+    }
+
+    /// Returns the nearest `f64` to the number, which is infinite beyond the
+    /// range of `f64`.
+    pub fn to_f64(&self) -> f64 {
+        let sign = if self.negative { "-" } else { "" };
+        float::parse_f64(&format!("{}0{}e{}", sign, self.digits, self.exponent)).unwrap_or(f64::NAN)
+    }
+
+    // Converts a whole number by writing out its digits in full. Neither
+    // 128-bit type has more than 39 digits, which bounds the zeros written.
+    fn integer<T: FromStr>(&self) -> Option<T> {
+        let normal = self.normalize();
+        if normal.exponent < 0 || normal.exponent > 39 - normal.digits.len() as i64 {
+            return None;
+        }
+        let sign = if normal.negative { "-" } else { "" };
+        let zeros = "0".repeat(normal.exponent as usize);
+        format!("{}0{}{}", sign, normal.digits, zeros).parse().ok()
+    }
+
+    // The decimal point's position counted from the coefficient's first
+    // digit, less one: the exponent of the number in scientific notation
+    fn adjusted_exponent(&self) -> i128 {
+        i128::from(self.exponent) + self.digits.len() as i128 - 1
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    /// Orders numbers by value, regardless of how they were written.
+    fn cmp(&self, other: &Self) -> Ordering {
+        let sign = |d: &Decimal| if d.is_zero() { 0 } else if d.negative { -1 } else { 1 };
+        match (sign(self), sign(other)) {
+            (a, b) if a != b => return a.cmp(&b),
+            (0, _) => return Ordering::Equal,
+            _ => {}
+        }
+
+        // Same sign: compare magnitudes by their leading digit's place value
+        // first, then digit by digit, with the shorter padded with zeros
+        let magnitude = self.adjusted_exponent().cmp(&other.adjusted_exponent()).then_with(|| {
+            let len = self.digits.len().max(other.digits.len());
+            let padded = |d: &Decimal| d.digits.bytes().chain(std::iter::repeat(b'0')).take(len).collect::<Vec<_>>();
+            padded(self).cmp(&padded(other))
+        });
+        if self.negative { magnitude.reverse() } else { magnitude }
+    }
+}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Equal numbers have the same normal form
+        let normal = self.normalize();
+        normal.negative.hash(state);
+        normal.digits.hash(state);
+        normal.exponent.hash(state);
+    }
+}
+
+impl fmt::Display for Decimal {
+    /// Writes the number as valid JSON. Numbers are written out in full when
+    /// that takes at most six zeros after the decimal point and none before
+    /// it; others are written in scientific notation, as `1.5E+3`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            f.write_str("-")?;
+        }
+        let digits = if self.digits.is_empty() { "0" } else { &self.digits };
+        let adjusted = i128::from(self.exponent) + digits.len() as i128 - 1;
+
+        if self.exponent <= 0 && adjusted >= -6 {
+            let scale = self.exponent.unsigned_abs() as usize;
+            if scale == 0 {
+                f.write_str(digits)
+            } else if digits.len() > scale {
+                let (integer, fraction) = digits.split_at(digits.len() - scale);
+                write!(f, "{}.{}", integer, fraction)
+            } else {
+                write!(f, "0.{}{}", "0".repeat(scale - digits.len()), digits)
+            }
+        } else {
+            let (first, rest) = digits.split_at(1);
+            f.write_str(first)?;
+            if !rest.is_empty() {
+                write!(f, ".{}", rest)?;
+            }
+            write!(f, "E{}{}", if adjusted < 0 { "-" } else { "+" }, adjusted.unsigned_abs())
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = DeserializeError;
+
+    /// Parses a number literal, which must follow the JSON number grammar
+    /// with nothing around it.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut reader = JsonReader::from_str(s);
+        match reader.next_event()? {
+            Some(JsonEvent::Number(literal)) if literal.len() == s.len() && reader.next_event()?.is_none() => {
+                Decimal::from_literal(&literal)
+                    .ok_or_else(|| reader.token_error(ErrorCode::NumberOutOfRange(s.to_owned())))
+            }
+            _ => Err(reader.token_error(ErrorCode::InvalidNumber)),
+        }
+    }
+}
+
+impl Default for Decimal {
+    /// Returns zero.
+    fn default() -> Self {
+        Self::from(0i128)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(n: i64) -> Self {
+        Self::from(i128::from(n))
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(n: u64) -> Self {
+        Self::from(u128::from(n))
+    }
+}
+
+impl From<i128> for Decimal {
+    fn from(n: i128) -> Self {
+        Self { negative: n < 0, ..Self::from(n.unsigned_abs()) }
+    }
+}
+
+impl From<u128> for Decimal {
+    fn from(n: u128) -> Self {
+        let digits = if n == 0 { String::new() } else { n.to_string() };
+        Self { negative: false, digits, exponent: 0 }
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(literal: &str) -> Decimal {
+        literal.parse().unwrap()
+    }
+
+    #[test]
+    fn test_display() {
+        let cases = [
+            ("0", "0"),
+            ("-0", "-0"),
+            ("0.10", "0.10"),
+            ("123456789012345678.99", "123456789012345678.99"),
+            ("0.000001", "0.000001"),
+            ("0.0000001", "1E-7"),
+            ("1e2", "1E+2"),
+            ("12.5e-1", "1.25"),
+            ("-1.5E+400", "-1.5E+400"),
+            ("0e5", "0E+5"),
+        ];
+        for (literal, expected) in cases {
+            assert_eq!(decimal(literal).to_string(), expected, "literal: {}", literal);
+        }
+        assert_eq!(decimal("-0.000").normalize().to_string(), "0");
+    }
+
+    #[test]
+    fn test_comparison() {
+        assert_eq!(decimal("1.10"), decimal("1.1"));
+        assert_eq!(decimal("100"), decimal("1e2"));
+        assert_eq!(decimal("-0"), decimal("0.0"));
+        assert!(decimal("0.1") < decimal("0.10000000000000000001"));
+        assert!(decimal("-2") < decimal("-1.5"));
+        assert!(decimal("-1") < decimal("0"));
+        assert!(decimal("9e-1") < decimal("1"));
+        assert!(decimal("1e400") > decimal("9".repeat(399).as_str()));
+
+        let mut set = std::collections::HashSet::new();
+        set.insert(decimal("2.50"));
+        assert!(set.contains(&decimal("25e-1")));
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(decimal("170141183460469231731687303715884105727").to_i128(), Some(i128::MAX));
+        assert_eq!(decimal("170141183460469231731687303715884105728").to_i128(), None);
+        assert_eq!(decimal("1e39").to_i128(), None);
+        assert_eq!(decimal("-0.0").to_i128(), Some(0));
+        assert_eq!(decimal("3.40282366920938463463374607431768211455e38").to_u128(), Some(u128::MAX));
+        assert_eq!(decimal("-1").to_u128(), None);
+        assert_eq!(decimal("0.1").to_f64(), 0.1);
+        assert_eq!(decimal("-1e400").to_f64(), f64::NEG_INFINITY);
+
+        // Exponents at the ends of their range neither overflow nor allocate
+        for literal in ["1e9223372036854775807", "-12e9223372036854775807", "1e-9223372036854775808"] {
+            assert_eq!(decimal(literal).to_i128(), None);
+            assert_eq!(decimal(literal).to_u128(), None);
+        }
+        assert_eq!(decimal("1e9223372036854775807").to_f64(), f64::INFINITY);
+        assert_eq!(decimal("1e-9223372036854775808").to_f64(), 0.0);
+        assert_eq!(Decimal::from(-120i128).to_string(), "-120");
+        assert_eq!(Decimal::from(0i128), decimal("0"));
+
+        assert!(matches!(
+            "1e99999999999999999999".parse::<Decimal>().unwrap_err().code(),
+            Some(ErrorCode::NumberOutOfRange(_))
+        ));
+        assert!("1.".parse::<Decimal>().is_err());
+    }
+}
@@ -0,0 +1,320 @@
+//! Compiler-style rendering of parse errors.
+//!
+//! The functions in this module turn a `DeserializeError` into a readable report
+//! that quotes the offending line of the input, points at the problem with a
+//! caret and, where the mistake is a common one, suggests how to fix it. They
+//! are meant for showing errors to people editing JSON by hand.
+//!
+//! # Examples
+//!
+//! ```
+//! use karya_json::types::JsonParser;
+//! use karya_json::types::diagnostic;
+//!
+//! let input = "{\n  \"name\": \"Alice\",\n  \"age\": 30,\n}";
+//! let error = JsonParser::from_str(input).parse().unwrap_err();
+//!
+//! assert_eq!(
+//!     diagnostic::render(input, &error),
+//!     "error: Expected '\"', found '}'\n \
+//!       --> line 4, column 1\n  \
+//!        |\n\
+//!      4 | }\n  \
+//!        | ^\n  \
+//!        |\n  \
+//!        = hint: remove the trailing comma after the last member\n"
+//! );
+//! ```
+
+use crate::types::error::{DeserializeError, ErrorCode, SyntaxError};
+use std::fmt::Write;
+
+/// Renders an error as a plain-text diagnostic.
+///
+/// `input` must be the text that produced the error, so that the offending line
+/// can be quoted. Errors without a position, such as a missing field, are
+/// rendered as a single line.
+///
+/// # Arguments
+///
+/// * `input` - The JSON text that was being parsed
+/// * `error` - The error produced while parsing it
+///
+/// # Returns
+///
+/// The diagnostic as a multi-line string ending in a newline
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::JsonParser;
+/// use karya_json::types::diagnostic;
+///
+/// let input = r#"{"a": 1 "b": 2}"#;
+/// let error = JsonParser::from_str(input).parse().unwrap_err();
+/// let report = diagnostic::render(input, &error);
+///
+/// assert!(report.contains("1 | {\"a\": 1 \"b\": 2}"));
+/// assert!(report.contains("hint: add a comma between the members"));
+/// ```
+pub fn render(input: &str, error: &DeserializeError) -> String {
+    render_with(input, error, &Style::PLAIN)
+}
+
+/// Renders an error as a diagnostic colored with ANSI escape sequences.
+///
+/// The layout is identical to `render`; only terminal colors are added. Use it
+/// when writing to a terminal that supports them.
+///
+/// # Arguments
+///
+/// * `input` - The JSON text that was being parsed
+/// * `error` - The error produced while parsing it
+///
+/// # Returns
+///
+/// The colored diagnostic as a multi-line string ending in a newline
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::JsonParser;
+/// use karya_json::types::diagnostic;
+///
+/// let input = "[1, 2,]";
+/// let error = JsonParser::from_str(input).parse().unwrap_err();
+///
+/// eprint!("{}", diagnostic::render_colored(input, &error));
+/// ```
+pub fn render_colored(input: &str, error: &DeserializeError) -> String {
+    render_with(input, error, &Style::ANSI)
+}
+
+/// The escape sequences used to color each part of a diagnostic.
+struct Style {
+    error: &'static str,
+    gutter: &'static str,
+    caret: &'static str,
+    hint: &'static str,
+    reset: &'static str,
+}
+
+impl Style {
+    const PLAIN: Style = Style {
+        error: "",
+        gutter: "",
+        caret: "",
+        hint: "",
+        reset: "",
+    };
+
+    const ANSI: Style = Style {
+        error: "\x1b[1;31m",
+        gutter: "\x1b[1;34m",
+        caret: "\x1b[1;31m",
+        hint: "\x1b[1;36m",
+        reset: "\x1b[0m",
+    };
+}
+
+fn render_with(input: &str, error: &DeserializeError, style: &Style) -> String {
+    let mut out = String::new();
+    let Style {
+        error: red,
+        gutter: blue,
+        caret: caret_color,
+        hint: cyan,
+        reset,
+    } = style;
+
+    let DeserializeError::InvalidJson(syntax) = error else {
+        let _ = writeln!(out, "{red}error{reset}: {}", error);
+        return out;
+    };
+
+    let position = syntax.position();
+    let line_text = input
+        .split('\n')
+        .nth(position.line - 1)
+        .unwrap_or_default()
+        .trim_end_matches('\r');
+    let number = position.line.to_string();
+    let pad = " ".repeat(number.len());
+
+    // Mirror tabs in the caret line so the caret lines up under the column
+    let indent: String = line_text
+        .chars()
+        .take(position.column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    let _ = writeln!(out, "{red}error{reset}: {}", syntax.code());
+    let _ = writeln!(out, "{pad}{blue}-->{reset} {}", position);
+    let _ = writeln!(out, "{pad} {blue}|{reset}");
+    let _ = writeln!(out, "{blue}{number} |{reset} {line_text}");
+    let _ = writeln!(out, "{pad} {blue}|{reset} {indent}{caret_color}^{reset}");
+    let _ = writeln!(out, "{pad} {blue}|{reset}");
+    if !syntax.path().is_empty() {
+        let _ = writeln!(out, "{pad} {blue}={reset} in: {}", syntax.path());
+    }
+    if let Some(hint) = hint(input, syntax) {
+        let _ = writeln!(out, "{pad} {blue}={reset} {cyan}hint{reset}: {}", hint);
+    }
+    out
+}
+
+// Recognizes common hand-editing mistakes and suggests a fix
+fn hint(input: &str, error: &SyntaxError) -> Option<&'static str> {
+    let offset = error.position().offset;
+    // The input may not be the one the error came from, so don't trust the offset
+    let previous = input.get(..offset)?.trim_end().chars().next_back();
+
+    match error.code() {
+        // `[1, 2,]` and `{"a": 1,}`
+        ErrorCode::UnexpectedCharacter(']') if previous == Some(',') => {
+            Some("remove the trailing comma after the last element")
+        }
+        ErrorCode::ExpectedCharacter {
+            expected: '"',
+            found: Some('}'),
+        } if previous == Some(',') => Some("remove the trailing comma after the last member"),
+
+        // `{'a': 'b'}`
+        ErrorCode::UnexpectedCharacter('\'')
+        | ErrorCode::ExpectedCharacter {
+            expected: '"',
+            found: Some('\''),
+        } => Some("JSON strings must use double quotes (\") instead of single quotes (')"),
+
+        // `{a: 1}`
+        ErrorCode::ExpectedCharacter {
+            expected: '"',
+            found: Some(c),
+        } if c.is_alphabetic() || *c == '_' || *c == '$' => {
+            Some("object keys must be enclosed in double quotes, like \"key\"")
+        }
+
+        // `[1 2]` and `{"a": 1 "b": 2}`
+        ErrorCode::ExpectedCommaOrClose { close, found } if starts_value(*found) => {
+            if *close == ']' {
+                Some("add a comma between the elements")
+            } else {
+                Some("add a comma between the members")
+            }
+        }
+
+        // A no-break space or other Unicode whitespace pasted in between tokens
+        ErrorCode::UnexpectedCharacter(c)
+        | ErrorCode::ExpectedCharacter { found: Some(c), .. }
+        | ErrorCode::ExpectedCommaOrClose { found: c, .. }
+            if c.is_whitespace() && !matches!(c, ' ' | '\t' | '\n' | '\r') =>
+        {
+            Some("only spaces, tabs and line breaks may separate JSON tokens")
+        }
+
+        _ => None,
+    }
+}
+
+// Whether a character can begin a JSON value
+fn starts_value(c: char) -> bool {
+    matches!(c, '"' | '\'' | '{' | '[' | '-' | '0'..='9' | 't' | 'f' | 'n')
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::JsonParser;
+
+    fn render_input(input: &str) -> String {
+        render(input, &JsonParser::from_str(input).parse().unwrap_err())
+    }
+
+    #[test]
+    fn test_render_layout() {
+        let input = "{\n  \"a\": [1, 2],\n  \"b\": tru\n}";
+        assert_eq!(
+            render_input(input),
+            "error: Expected 'e', found '\\n'\n \
+              --> line 3, column 11\n  \
+               |\n\
+             3 |   \"b\": tru\n  \
+               |           ^\n  \
+               |\n  \
+               = in: /b\n"
+        );
+    }
+
+    #[test]
+    fn test_wide_gutter_and_tabs() {
+        let mut input = "[\n".repeat(9);
+        input.push_str("\t\t1 2");
+        let report = render_input(&input);
+        assert!(report.contains("\n10 | \t\t1 2\n"));
+        assert!(report.contains("\n   | \t\t  ^\n"));
+    }
+
+    #[test]
+    fn test_hints() {
+        let cases = [
+            ("[1, 2,]", "remove the trailing comma after the last element"),
+            ("{\"a\": 1,\n}", "remove the trailing comma after the last member"),
+            ("{'a': 1}", "double quotes (\") instead of single quotes"),
+            ("{\"a\": 'b'}", "double quotes (\") instead of single quotes"),
+            ("{a: 1}", "object keys must be enclosed in double quotes"),
+            ("{\"a\": 1, b: 2}", "object keys must be enclosed in double quotes"),
+            ("[1 2]", "add a comma between the elements"),
+            ("{\"a\": 1\n \"b\": 2}", "add a comma between the members"),
+            ("[1,\u{a0}2]", "only spaces, tabs and line breaks may separate JSON tokens"),
+            ("{\"a\":\u{3000}1}", "only spaces, tabs and line breaks may separate JSON tokens"),
+        ];
+
+        for (input, hint) in cases {
+            let report = render_input(input);
+            assert!(report.contains(hint), "input: {}\n{}", input, report);
+        }
+
+        // No hint is offered when the mistake isn't recognized
+        assert!(!render_input("[1, 2").contains("hint"));
+    }
+
+    #[test]
+    fn test_render_colored() {
+        let input = "[1 2]";
+        let error = JsonParser::from_str(input).parse().unwrap_err();
+        let colored = render_colored(input, &error);
+
+        assert!(colored.contains("\x1b[1;31merror\x1b[0m"));
+        assert!(colored.contains("\x1b[1;31m^\x1b[0m"));
+
+        // Stripping the escape sequences gives back the plain rendering
+        let mut stripped = String::new();
+        let mut rest = colored.as_str();
+        while let Some(start) = rest.find('\x1b') {
+            stripped.push_str(&rest[..start]);
+            rest = &rest[start..];
+            rest = &rest[rest.find('m').unwrap() + 1..];
+        }
+        stripped.push_str(rest);
+        assert_eq!(stripped, render(input, &error));
+    }
+
+    #[test]
+    fn test_error_without_position() {
+        let error = DeserializeError::MissingField("id".to_string());
+        assert_eq!(render("{}", &error), "error: Missing required field: id\n");
+    }
+
+    #[test]
+    fn test_error_from_other_input() {
+        // Rendering against the wrong input must not panic, only lose the hint
+        let error = JsonParser::from_str("[1, 2, 3,]").parse().unwrap_err();
+        let report = render("[1", &error);
+        assert!(report.starts_with("error: Unexpected character: ]\n"));
+        assert!(!report.contains("hint"));
+
+        let error = JsonParser::from_str("[1,]").parse().unwrap_err();
+        assert!(!render("[1é", &error).contains("hint"));
+    }
+}
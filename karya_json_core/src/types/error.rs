@@ -10,7 +10,7 @@
 //! use karya_json::types::{JsonParser, JsonValue};
 //! use karya_json::types::error::DeserializeError;
 //!
-//! let result = JsonParser::new("invalid json".to_string()).parse();
+//! let result = JsonParser::from_str("invalid json").parse();
 //!
 //! if let Err(error) = result {
 //!     match error {
@@ -20,69 +20,112 @@
 //! }
 //! ```
 
+use crate::types::DuplicateKeyPolicy;
+use std::convert::Infallible;
 use std::fmt;
 use std::error::Error;
+use std::io;
 
 /// Errors that can occur during JSON serialization.
 ///
 /// This enum represents the various errors that can occur when converting
 /// Rust data structures to JSON.
 ///
+/// New variants may be added in future releases, so matches on this enum need a
+/// wildcard arm.
+///
 /// # Examples
 ///
 /// ```
-/// use karya_json::types::error::SerializeError;
+/// use karya_json::types::{JsonValue, SerializeOptions};
+/// use karya_json::types::error::{SerializeError, SerializeErrorCode};
 ///
-/// fn handle_serialize_error(error: SerializeError) {
-///     match error {
-///         SerializeError::InvalidType(msg) => println!("Invalid type: {}", msg),
-///         SerializeError::InvalidValue(msg) => println!("Invalid value: {}", msg),
-///         SerializeError::InvalidStructure(msg) => println!("Invalid structure: {}", msg),
+/// let value = JsonValue::Arr(vec![JsonValue::Float(f64::NAN)].into());
+///
+/// match value.to_json_string(&SerializeOptions::default()) {
+///     Err(SerializeError::InvalidValue(SerializeErrorCode::NonFiniteNumber { literal, path })) => {
+///         assert_eq!((literal.as_str(), path.as_str()), ("NaN", "/0"));
 ///     }
+///     _ => panic!("Expected a non-finite number error"),
 /// }
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum SerializeError {
-    /// Indicates that a value has a type that cannot be serialized to JSON.
-    ///
-    /// The string contains a description of the type error.
-    InvalidType(String),
-
     /// Indicates that a value cannot be represented in JSON.
     ///
-    /// The string contains a description of the value error.
-    InvalidValue(String),
+    /// The `SerializeErrorCode` identifies the value and where it is.
+    InvalidValue(SerializeErrorCode),
+}
 
-    /// Indicates that the structure of the data is not valid for JSON serialization.
-    ///
-    /// The string contains a description of the structure error.
-    InvalidStructure(String),
+impl SerializeError {
+    /// Returns the kind of error, with the details of the offending value.
+    pub fn code(&self) -> Option<&SerializeErrorCode> {
+        match self {
+            SerializeError::InvalidValue(code) => Some(code),
+        }
+    }
 }
 
 impl fmt::Display for SerializeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            SerializeError::InvalidType(msg) => write!(f, "Invalid type for JSON serialization: {}", msg),
-            SerializeError::InvalidValue(msg) => write!(f, "Invalid value for JSON serialization: {}", msg),
-            SerializeError::InvalidStructure(msg) => write!(f, "Invalid structure for JSON serialization: {}", msg),
+            SerializeError::InvalidValue(code) => write!(f, "Invalid value for JSON serialization: {}", code),
         }
     }
 }
 
 impl Error for SerializeError {}
 
+/// Identifies a value that cannot be serialized.
+///
+/// Each variant carries the details relevant to it, including the
+/// [JSON Pointer](https://www.rfc-editor.org/rfc/rfc6901) path of the value,
+/// so callers can report it without matching on the message text.
+///
+/// New variants may be added in future releases, so matches on this enum need a
+/// wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SerializeErrorCode {
+    /// A number is infinite or NaN, which JSON cannot represent.
+    NonFiniteNumber {
+        /// The number as JSON5 writes it, such as `-Infinity` or `NaN`
+        literal: String,
+        /// The path of the number, empty if it is the whole value
+        path: String,
+    },
+}
+
+impl fmt::Display for SerializeErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeErrorCode::NonFiniteNumber { literal, path } => {
+                write!(f, "{} is not a finite number", literal)?;
+                if !path.is_empty() {
+                    write!(f, " in {}", path)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 /// Errors that can occur during JSON deserialization.
 ///
 /// This enum represents the various errors that can occur when parsing
 /// JSON into Rust data structures.
 ///
+/// New variants may be added in future releases, so matches on this enum need a
+/// wildcard arm.
+///
 /// # Examples
 ///
 /// ```
 /// use karya_json::types::JsonParser;
 /// use karya_json::types::error::DeserializeError;
 ///
-/// let result = JsonParser::new("invalid json".to_string()).parse();
+/// let result = JsonParser::from_str("invalid json").parse();
 ///
 /// if let Err(error) = result {
 ///     match error {
@@ -90,15 +133,18 @@ impl Error for SerializeError {}
 ///         DeserializeError::MissingField(field) => println!("Missing field: {}", field),
 ///         DeserializeError::TypeMismatch(msg) => println!("Type mismatch: {}", msg),
 ///         DeserializeError::InvalidValue(msg) => println!("Invalid value: {}", msg),
+///         DeserializeError::Io(error) => println!("I/O error: {}", error),
+///         _ => println!("Other error occurred"),
 ///     }
 /// }
 /// ```
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum DeserializeError {
     /// Indicates that the JSON string is not valid according to the JSON specification.
     ///
-    /// The string contains a description of the syntax error.
-    InvalidJson(String),
+    /// The `SyntaxError` describes the problem and where in the input it occurred.
+    InvalidJson(SyntaxError),
 
     /// Indicates that a required field is missing from a JSON object.
     ///
@@ -114,6 +160,11 @@ pub enum DeserializeError {
     ///
     /// The string contains a description of the value error.
     InvalidValue(String),
+
+    /// Indicates that reading the input failed.
+    ///
+    /// Only parsers created with `JsonParser::from_reader` can produce this error.
+    Io(io::Error),
 }
 
 impl fmt::Display for DeserializeError {
@@ -123,8 +174,460 @@ impl fmt::Display for DeserializeError {
             DeserializeError::MissingField(field) => write!(f, "Missing required field: {}", field),
             DeserializeError::TypeMismatch(msg) => write!(f, "Type mismatch in JSON: {}", msg),
             DeserializeError::InvalidValue(msg) => write!(f, "Invalid value in JSON: {}", msg),
+            DeserializeError::Io(error) => write!(f, "I/O error while reading JSON: {}", error),
+        }
+    }
+}
+
+impl Error for DeserializeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DeserializeError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DeserializeError {
+    fn from(error: io::Error) -> Self {
+        DeserializeError::Io(error)
+    }
+}
+
+/// Lets conversions that cannot fail, such as a `JsonValue` into itself, be
+/// used where a `DeserializeError` is expected.
+impl From<Infallible> for DeserializeError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+impl DeserializeError {
+    /// Returns where in the input the error occurred, if it is a syntax error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::JsonParser;
+    ///
+    /// let error = JsonParser::from_str("[1,\n 2,,]").parse().unwrap_err();
+    /// let position = error.position().unwrap();
+    ///
+    /// assert_eq!((position.line, position.column), (2, 4));
+    /// ```
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            DeserializeError::InvalidJson(error) => Some(error.position()),
+            _ => None,
+        }
+    }
+
+    /// Returns the kind of syntax error, if this is a syntax error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::JsonParser;
+    /// use karya_json::types::error::ErrorCode;
+    ///
+    /// let error = JsonParser::from_str("[1, 2] x").parse().unwrap_err();
+    /// assert_eq!(error.code(), Some(&ErrorCode::TrailingCharacters));
+    /// ```
+    pub fn code(&self) -> Option<&ErrorCode> {
+        match self {
+            DeserializeError::InvalidJson(error) => Some(error.code()),
+            _ => None,
         }
     }
 }
 
-impl Error for DeserializeError {}
+/// A location in the JSON input.
+///
+/// Lines and columns are 1-based. Columns count characters rather than bytes,
+/// so they line up with what a text editor shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// The byte offset from the start of the input
+    pub offset: usize,
+    /// The line number, starting at 1
+    pub line: usize,
+    /// The column number within the line, starting at 1
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// Identifies the kind of syntax error found while parsing JSON.
+///
+/// Each variant carries the details relevant to it, such as the offending
+/// character or key, so callers can tell errors apart without matching on the
+/// message text. The `Display` implementation produces a human-readable message.
+///
+/// New variants may be added in future releases, so matches on this enum need a
+/// wildcard arm.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::JsonParser;
+/// use karya_json::types::error::ErrorCode;
+///
+/// let error = JsonParser::from_str(r#"{"id": 1, "id": 2}"#).parse().unwrap_err();
+///
+/// match error.code() {
+///     Some(ErrorCode::DuplicateKey(key)) => assert_eq!(key, "id"),
+///     _ => panic!("Expected a duplicate key error"),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// The input ended where a value was expected.
+    UnexpectedEof,
+
+    /// A character that cannot start a JSON value was found.
+    UnexpectedCharacter(char),
+
+    /// A specific character was required, such as the `:` after an object key.
+    ///
+    /// `found` is `None` when the input ended instead.
+    ExpectedCharacter {
+        /// The character the parser required
+        expected: char,
+        /// The character that was found instead
+        found: Option<char>,
+    },
+
+    /// An array element or object member was followed by something other than
+    /// a comma or the closing bracket.
+    ExpectedCommaOrClose {
+        /// The closing bracket of the enclosing container, `]` or `}`
+        close: char,
+        /// The character that was found instead
+        found: char,
+    },
+
+    /// The input ended inside an array.
+    UnterminatedArray,
+
+    /// The input ended inside an object.
+    UnterminatedObject,
+
+    /// The input ended inside a string.
+    UnterminatedString,
+
+    /// A backslash was followed by a character that does not form a valid
+    /// escape sequence.
+    InvalidEscape(char),
+
+    /// A `\u` escape contained a character that is not a hexadecimal digit.
+    InvalidUnicodeEscape(char),
+
+    /// The input ended inside a `\u` escape.
+    UnterminatedUnicodeEscape,
+
+    /// A UTF-16 surrogate escape appeared without its other half.
+    ///
+    /// Holds the code unit of the unpaired surrogate.
+    LoneSurrogate(u32),
+
+    /// A high surrogate escape was followed by an escape that is not a low
+    /// surrogate.
+    ///
+    /// Holds the code unit that should have been a low surrogate.
+    InvalidLowSurrogate(u32),
+
+    /// A `\u` escape decoded to a value that is not a Unicode scalar value.
+    InvalidCodePoint(u32),
+
+    /// A string contained an unescaped control character (U+0000 through U+001F).
+    ///
+    /// Holds the byte value of the control character.
+    ControlCharacterInString(u8),
+
+    /// An object contained the same key more than once.
+    DuplicateKey(String),
+
+    /// The input ended inside a `/* */` comment.
+    UnterminatedComment,
+
+    /// A number literal is malformed, for example a lone `-`.
+    InvalidNumber,
+
+    /// A number literal has a decimal point that is not followed by digits.
+    MissingFractionDigits,
+
+    /// A number literal has an exponent marker that is not followed by digits.
+    MissingExponentDigits,
+
+    /// A number literal is too large or too small to be represented.
+    ///
+    /// Holds the literal as it appeared in the input.
+    NumberOutOfRange(String),
+
+    /// Containers are nested more deeply than the parser allows.
+    ///
+    /// Holds the maximum permitted depth.
+    DepthLimitExceeded(usize),
+
+    /// A string or object key is longer than the parser allows.
+    ///
+    /// Holds the maximum permitted length in bytes.
+    StringTooLong(usize),
+
+    /// An array has more elements than the parser allows.
+    ///
+    /// Holds the maximum permitted number of elements.
+    ArrayTooLong(usize),
+
+    /// An object has more members than the parser allows.
+    ///
+    /// Holds the maximum permitted number of members.
+    ObjectTooLong(usize),
+
+    /// A number literal is longer than the parser allows.
+    ///
+    /// Holds the maximum permitted length in bytes.
+    NumberTooLong(usize),
+
+    /// The input is longer than the parser allows.
+    ///
+    /// Holds the maximum permitted length in bytes.
+    InputTooLong(usize),
+
+    /// Non-whitespace characters follow the end of the JSON value.
+    TrailingCharacters,
+
+    /// The input is not valid UTF-8.
+    ///
+    /// Holds the byte offset of the first invalid byte.
+    InvalidUtf8(usize),
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorCode::UnexpectedEof => write!(f, "Unexpected end of input"),
+            ErrorCode::UnexpectedCharacter(c) => write!(f, "Unexpected character: {}", Printable(*c)),
+            ErrorCode::ExpectedCharacter { expected, found: Some(c) } => {
+                write!(f, "Expected '{}', found '{}'", expected, Printable(*c))
+            }
+            ErrorCode::ExpectedCharacter { expected, found: None } => {
+                write!(f, "Expected '{}', found end of input", expected)
+            }
+            ErrorCode::ExpectedCommaOrClose { close, found } => {
+                write!(f, "Expected ',' or '{}', found '{}'", close, Printable(*found))
+            }
+            ErrorCode::UnterminatedArray => write!(f, "Unterminated array"),
+            ErrorCode::UnterminatedObject => write!(f, "Unterminated object"),
+            ErrorCode::UnterminatedString => write!(f, "Unterminated string"),
+            ErrorCode::InvalidEscape(c) => write!(f, "Invalid escape sequence: \\{}", Printable(*c)),
+            ErrorCode::InvalidUnicodeEscape(c) => {
+                write!(f, "Invalid Unicode escape sequence: {}", Printable(*c))
+            }
+            ErrorCode::UnterminatedUnicodeEscape => write!(f, "Unexpected end of Unicode escape sequence"),
+            ErrorCode::LoneSurrogate(code_unit) if *code_unit < 0xDC00 => {
+                write!(f, "High surrogate U+{:04X} not followed by low surrogate", code_unit)
+            }
+            ErrorCode::LoneSurrogate(code_unit) => write!(f, "Unexpected low surrogate: U+{:04X}", code_unit),
+            ErrorCode::InvalidLowSurrogate(code_unit) => {
+                write!(f, "Invalid low surrogate in Unicode surrogate pair: U+{:04X}", code_unit)
+            }
+            ErrorCode::InvalidCodePoint(code_point) => write!(f, "Invalid Unicode code point: U+{:04X}", code_point),
+            ErrorCode::ControlCharacterInString(b) => {
+                write!(f, "Unescaped control character (0x{:02X}) in string", b)
+            }
+            ErrorCode::DuplicateKey(key) => write!(f, "Duplicate key '{}' in object", key),
+            ErrorCode::UnterminatedComment => write!(f, "Unterminated comment"),
+            ErrorCode::InvalidNumber => write!(f, "Invalid number format"),
+            ErrorCode::MissingFractionDigits => write!(f, "Expected digits after decimal point"),
+            ErrorCode::MissingExponentDigits => write!(f, "Expected digits in exponent"),
+            ErrorCode::NumberOutOfRange(literal) => write!(f, "Number out of range: {}", literal),
+            ErrorCode::DepthLimitExceeded(max) => write!(f, "Maximum nesting depth of {} exceeded", max),
+            ErrorCode::StringTooLong(max) => write!(f, "String longer than the maximum of {} bytes", max),
+            ErrorCode::ArrayTooLong(max) => write!(f, "Array longer than the maximum of {} elements", max),
+            ErrorCode::ObjectTooLong(max) => write!(f, "Object longer than the maximum of {} members", max),
+            ErrorCode::NumberTooLong(max) => write!(f, "Number longer than the maximum of {} bytes", max),
+            ErrorCode::InputTooLong(max) => write!(f, "Input longer than the maximum of {} bytes", max),
+            ErrorCode::TrailingCharacters => write!(f, "Unexpected trailing characters"),
+            ErrorCode::InvalidUtf8(offset) => write!(f, "Invalid UTF-8 at byte {}", offset),
+        }
+    }
+}
+
+/// Displays a character from the input, escaping it if it is a control
+/// character so that messages always fit on one line.
+struct Printable(char);
+
+impl fmt::Display for Printable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_control() {
+            write!(f, "{}", self.0.escape_debug())
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+/// A syntax error found while parsing JSON.
+///
+/// Carries the `ErrorCode` identifying the problem together with the position of the offending input
+/// and the [JSON Pointer](https://www.rfc-editor.org/rfc/rfc6901) path of the
+/// value that was being parsed when the error occurred.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::JsonParser;
+/// use karya_json::types::error::DeserializeError;
+///
+/// let input = r#"{"users": [{"name": "Alice"}, {"name": nul}]}"#;
+///
+/// if let Err(DeserializeError::InvalidJson(error)) = JsonParser::from_str(input).parse() {
+///     assert_eq!(error.path(), "/users/1/name");
+///     assert_eq!(error.position().offset, 42);
+///     assert_eq!(error.to_string(), "Expected 'l', found '}' at line 1, column 43 in /users/1/name");
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    code: ErrorCode,
+    position: Position,
+    path: String,
+}
+
+impl SyntaxError {
+    pub(crate) fn new(code: ErrorCode, position: Position, path: String) -> Self {
+        Self {
+            code,
+            position,
+            path,
+        }
+    }
+
+    /// Returns the kind of syntax error.
+    pub fn code(&self) -> &ErrorCode {
+        &self.code
+    }
+
+    /// Returns where in the input the error occurred.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Returns the JSON Pointer path of the value being parsed when the error
+    /// occurred.
+    ///
+    /// The path is empty for errors at the top level of the document.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.code, self.position)?;
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for SyntaxError {}
+
+/// Identifies something questionable in the input that the parser accepted.
+///
+/// New variants may be added in future releases, so matches on this enum need a
+/// wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WarningCode {
+    /// An object repeated a key, and the policy shown was applied to it.
+    DuplicateKey {
+        /// The key that appeared more than once
+        key: String,
+        /// The policy that decided which value was kept
+        policy: DuplicateKeyPolicy,
+    },
+}
+
+impl fmt::Display for WarningCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WarningCode::DuplicateKey { key, policy } => {
+                let kept = match policy {
+                    DuplicateKeyPolicy::FirstWins => "the first value",
+                    DuplicateKeyPolicy::LastWins => "the last value",
+                    _ => "every value",
+                };
+                write!(f, "Duplicate key '{}' in object, keeping {}", key, kept)
+            }
+        }
+    }
+}
+
+/// A warning about input the parser accepted, such as a repeated object key.
+///
+/// Located and displayed exactly like a `SyntaxError`, so offending documents
+/// can be logged the same way.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{DuplicateKeyPolicy, JsonParser, ParserOptions};
+/// use karya_json::types::error::WarningCode;
+///
+/// let options = ParserOptions { duplicate_keys: DuplicateKeyPolicy::FirstWins, ..ParserOptions::default() };
+/// let mut parser = JsonParser::from_str(r#"{"user": {"id": 1, "id": 2}}"#).with_options(options);
+/// parser.parse().unwrap();
+///
+/// let warning = &parser.warnings()[0];
+/// assert_eq!(warning.path(), "/user/id");
+/// assert!(matches!(warning.code(), WarningCode::DuplicateKey { key, .. } if key == "id"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    code: WarningCode,
+    position: Position,
+    path: String,
+}
+
+impl ParseWarning {
+    pub(crate) fn new(code: WarningCode, position: Position, path: String) -> Self {
+        Self {
+            code,
+            position,
+            path,
+        }
+    }
+
+    /// Returns the kind of warning.
+    pub fn code(&self) -> &WarningCode {
+        &self.code
+    }
+
+    /// Returns where in the input the warning applies.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Returns the JSON Pointer path of the value the warning applies to.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.code, self.position)?;
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+//! Writing `f64` as the shortest decimal text that reads back the same.
+//!
+//! The digits come from Ryu, which finds the shortest decimal in the interval
+//! of numbers that round to the float using fixed-size integer arithmetic and
+//! a table of powers of five. The layout then makes sure the text can only be
+//! read back as a float, never as an integer.
+
+use super::table::{POW5_INV_SPLIT, POW5_SPLIT};
+use std::fmt;
+
+/// The bits of an `f64` mantissa, not counting the implicit leading one
+const MANTISSA_BITS: u32 = 52;
+/// The bias of an `f64` exponent
+const EXPONENT_BIAS: i32 = 1023;
+/// The significant bits in each entry of `POW5_INV_SPLIT` and `POW5_SPLIT`
+const POW5_BITS: i32 = 125;
+
+/// Writes a finite `f64` as the shortest decimal that parses back to it.
+///
+/// The text always holds a decimal point or an exponent, so that `1.0` is
+/// written `1.0` and not `1`. Numbers from `1e-6` up to but not including
+/// `1e21` are written out in full, as `0.000123` or `250.0`; others use an
+/// exponent, as `1.5e-7` or `1e21`.
+pub(crate) fn write_f64<W: fmt::Write>(out: &mut W, n: f64) -> fmt::Result {
+    debug_assert!(n.is_finite());
+    if n.is_sign_negative() {
+        out.write_char('-')?;
+    }
+    if n == 0.0 {
+        return out.write_str("0.0");
+    }
+
+    let (mantissa, exponent) = shortest(n.to_bits());
+    let mut buffer = [0u8; 20];
+    let digits = write_digits(&mut buffer, mantissa);
+    let count = digits.len() as i32;
+    // Where the decimal point falls, counted in digits from the first
+    let point = count + exponent;
+
+    if exponent >= 0 && point <= 21 {
+        out.write_str(digits)?;
+        for _ in 0..exponent {
+            out.write_char('0')?;
+        }
+        out.write_str(".0")
+    } else if 0 < point && point <= 21 {
+        let (whole, fraction) = digits.split_at(point as usize);
+        write!(out, "{}.{}", whole, fraction)
+    } else if -6 < point && point <= 0 {
+        out.write_str("0.")?;
+        for _ in 0..-point {
+            out.write_char('0')?;
+        }
+        out.write_str(digits)
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.write_str(first)?;
+        if !rest.is_empty() {
+            write!(out, ".{}", rest)?;
+        }
+        write!(out, "e{}", point - 1)
+    }
+}
+
+// Writes the decimal digits of `n` to the end of `buffer`, returning them
+fn write_digits(buffer: &mut [u8; 20], mut n: u64) -> &str {
+    let mut start = buffer.len();
+    loop {
+        start -= 1;
+        buffer[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    // Only ASCII digits were written
+    std::str::from_utf8(&buffer[start..]).unwrap_or_default()
+}
+
+// Ryu: the shortest decimal `mantissa * 10^exponent` that lies within the
+// interval of real numbers rounding to the positive, nonzero float given by
+// `bits`, choosing the one closest to the float's exact value among several
+fn shortest(bits: u64) -> (u64, i32) {
+    let ieee_mantissa = bits & ((1 << MANTISSA_BITS) - 1);
+    let ieee_exponent = ((bits >> MANTISSA_BITS) & 0x7FF) as i32;
+
+    // The float is m2 * 2^e2; two extra bits leave room for the interval's
+    // bounds, which lie halfway to the neighbouring floats
+    let (m2, e2) = if ieee_exponent == 0 {
+        (ieee_mantissa, 1 - EXPONENT_BIAS - MANTISSA_BITS as i32 - 2)
+    } else {
+        (ieee_mantissa | (1 << MANTISSA_BITS), ieee_exponent - EXPONENT_BIAS - MANTISSA_BITS as i32 - 2)
+    };
+    // Round-to-even parsing reads the bounds themselves back as this float
+    // only when its mantissa is even
+    let accept_bounds = m2 & 1 == 0;
+
+    // The lower bound is closer when the mantissa is a power of two
+    let mv = 4 * m2;
+    let mm_shift = u64::from(ieee_mantissa != 0 || ieee_exponent <= 1);
+
+    // Convert the value and its bounds to decimal, noting whether the digits
+    // dropped along the way were all zeros
+    let (mut vr, mut vp, mut vm);
+    let e10;
+    let mut vm_trailing_zeros = false;
+    let mut vr_trailing_zeros = false;
+
+    if e2 >= 0 {
+        let q = log10_pow2(e2) - i32::from(e2 > 3);
+        e10 = q;
+        let k = POW5_BITS + pow5_bits(q) - 1;
+        let i = -e2 + q + k;
+        (vr, vp, vm) = mul_shift_all(m2, POW5_INV_SPLIT[q as usize], i, mm_shift);
+        if q <= 21 {
+            // Only one of mp, mv and mm can be a multiple of 5, if any
+            if mv % 5 == 0 {
+                vr_trailing_zeros = multiple_of_power_of_5(mv, q);
+            } else if accept_bounds {
+                vm_trailing_zeros = multiple_of_power_of_5(mv - 1 - mm_shift, q);
+            } else {
+                vp -= u64::from(multiple_of_power_of_5(mv + 2, q));
+            }
+        }
+    } else {
+        let q = log10_pow5(-e2) - i32::from(-e2 > 1);
+        e10 = q + e2;
+        let i = -e2 - q;
+        let k = pow5_bits(i) - POW5_BITS;
+        let j = q - k;
+        (vr, vp, vm) = mul_shift_all(m2, POW5_SPLIT[i as usize], j, mm_shift);
+        if q <= 1 {
+            // mv = 4 * m2 always has at least two trailing zero bits
+            vr_trailing_zeros = true;
+            if accept_bounds {
+                // mm = mv - 1 - mm_shift has one exactly when mm_shift is 1
+                vm_trailing_zeros = mm_shift == 1;
+            } else {
+                // mp = mv + 2 always has at least one
+                vp -= 1;
+            }
+        } else if q < 63 {
+            vr_trailing_zeros = multiple_of_power_of_2(mv, q);
+        }
+    }
+
+    // Remove digits for as long as the bounds stay apart, rounding the value
+    let mut removed = 0;
+    let output = if vm_trailing_zeros || vr_trailing_zeros {
+        // The general case, which is rare
+        let mut last_removed = 0;
+        while vp / 10 > vm / 10 {
+            vm_trailing_zeros &= vm % 10 == 0;
+            vr_trailing_zeros &= last_removed == 0;
+            last_removed = vr % 10;
+            vr /= 10;
+            vp /= 10;
+            vm /= 10;
+            removed += 1;
+        }
+        if vm_trailing_zeros {
+            while vm % 10 == 0 {
+                vr_trailing_zeros &= last_removed == 0;
+                last_removed = vr % 10;
+                vr /= 10;
+                vm /= 10;
+                removed += 1;
+            }
+        }
+        if vr_trailing_zeros && last_removed == 5 && vr % 2 == 0 {
+            // Exactly halfway: round to even
+            last_removed = 4;
+        }
+        let outside = vr == vm && (!accept_bounds || !vm_trailing_zeros);
+        vr + u64::from(outside || last_removed >= 5)
+    } else {
+        // The common case, where no trailing zeros need tracking
+        let mut round_up = false;
+        if vp / 100 > vm / 100 {
+            round_up = vr % 100 >= 50;
+            vr /= 100;
+            vp /= 100;
+            vm /= 100;
+            removed += 2;
+        }
+        while vp / 10 > vm / 10 {
+            round_up = vr % 10 >= 5;
+            vr /= 10;
+            vp /= 10;
+            vm /= 10;
+            removed += 1;
+        }
+        vr + u64::from(vr == vm || round_up)
+    };
+
+    (output, e10 + removed)
+}
+
+// Multiplies 4 * m, and the bounds either side of it, by a 125-bit power of
+// five and shifts the products right by `j` bits
+fn mul_shift_all(m: u64, multiplier: (u64, u64), j: i32, mm_shift: u64) -> (u64, u64, u64) {
+    (
+        mul_shift(4 * m, multiplier, j),
+        mul_shift(4 * m + 2, multiplier, j),
+        mul_shift(4 * m - 1 - mm_shift, multiplier, j),
+    )
+}
+
+fn mul_shift(m: u64, (low, high): (u64, u64), j: i32) -> u64 {
+    let low_product = u128::from(m) * u128::from(low);
+    let high_product = u128::from(m) * u128::from(high);
+    (((low_product >> 64) + high_product) >> (j - 64)) as u64
+}
+
+// The number of bits in 5^e, for e from 0 to 3528
+fn pow5_bits(e: i32) -> i32 {
+    ((e * 1_217_359) >> 19) + 1
+}
+
+// floor(log10(2^e)), for e from 0 to 1650
+fn log10_pow2(e: i32) -> i32 {
+    (e * 78_913) >> 18
+}
+
+// floor(log10(5^e)), for e from 0 to 2620
+fn log10_pow5(e: i32) -> i32 {
+    (e * 732_923) >> 20
+}
+
+fn multiple_of_power_of_5(mut value: u64, p: i32) -> bool {
+    let mut count = 0;
+    while value.is_multiple_of(5) {
+        value /= 5;
+        count += 1;
+    }
+    count >= p
+}
+
+fn multiple_of_power_of_2(value: u64, p: i32) -> bool {
+    value & ((1 << p) - 1) == 0
+}
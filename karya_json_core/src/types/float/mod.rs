@@ -0,0 +1,135 @@
+//! Conversions between `f64` and decimal text.
+//!
+//! Parsing reads a literal's digits straight from the input and formatting
+//! writes straight to the output, with no intermediate `String` either way.
+//! Together they round-trip: writing any finite float and parsing the text
+//! gives back the same float, bit for bit, and the text always reads as a
+//! float rather than an integer.
+
+mod format;
+mod parse;
+mod table;
+
+pub(crate) use format::write_f64;
+pub(crate) use parse::parse_f64;
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A xorshift generator, so the property tests are repeatable
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    fn format(n: f64) -> String {
+        let mut text = String::new();
+        write_f64(&mut text, n).unwrap();
+        text
+    }
+
+    // The shortest digits, as the standard library finds them
+    fn std_digits(n: f64) -> String {
+        let text = format!("{:e}", n.abs());
+        text[..text.find('e').unwrap()].replace('.', "")
+    }
+
+    #[test]
+    fn test_format() {
+        let cases = [
+            (0.0, "0.0"),
+            (-0.0, "-0.0"),
+            (1.0, "1.0"),
+            (-2.5, "-2.5"),
+            (0.1, "0.1"),
+            (100.0, "100.0"),
+            (1e20, "100000000000000000000.0"),
+            (1e21, "1e21"),
+            (1.5e300, "1.5e300"),
+            (0.000001, "0.000001"),
+            (1.5e-7, "1.5e-7"),
+            (f64::MAX, "1.7976931348623157e308"),
+            (f64::MIN_POSITIVE, "2.2250738585072014e-308"),
+            (5e-324, "5e-324"),
+            (123456789.125, "123456789.125"),
+        ];
+        for (n, expected) in cases {
+            assert_eq!(format(n), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let cases = [
+            ("0", 0.0),
+            ("-0.0", -0.0),
+            ("1.5", 1.5),
+            ("123e25", 123e25),
+            ("0.1", 0.1),
+            ("2.2250738585072011e-308", 2.225073858507201e-308),
+            ("4.9406564584124654e-324", 5e-324),
+            ("2.4703282292062328e-324", 5e-324),
+            ("2.4703282292062327e-324", 0.0),
+            ("1.7976931348623158e308", f64::MAX),
+            ("1.7976931348623159e308", f64::INFINITY),
+            ("9007199254740993", 9007199254740992.0),
+            ("12345678901234567890123456789e-10", 1234567890123456789.0123456789),
+            ("1e-99999999999999999999", 0.0),
+            ("0.000000000000000000000000000000000000000000001e45", 1.0),
+        ];
+        for (literal, expected) in cases {
+            let parsed = parse_f64(literal).unwrap();
+            assert_eq!(parsed.to_bits(), expected.to_bits(), "literal: {}", literal);
+        }
+        assert!(parse_f64("NaN").unwrap().is_nan());
+        assert_eq!(parse_f64("-Infinity"), Some(f64::NEG_INFINITY));
+        assert_eq!(parse_f64("1e"), None);
+    }
+
+    #[test]
+    fn test_round_trip_property() {
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+        for _ in 0..200_000 {
+            let n = f64::from_bits(rng.next());
+            if !n.is_finite() {
+                continue;
+            }
+            let text = format(n);
+            assert_eq!(parse_f64(&text).map(f64::to_bits), Some(n.to_bits()), "text: {}", text);
+            assert!(text.contains(['.', 'e']), "text: {}", text);
+
+            // Shortest means as few digits as the standard library needs. The
+            // digits themselves may differ only when two candidates are equally
+            // close, which Ryu breaks towards even and the standard library up.
+            let digits: String = text.split('e').next().unwrap().chars().filter(char::is_ascii_digit).collect();
+            assert_eq!(digits.trim_matches('0').len(), std_digits(n).trim_matches('0').len(), "text: {}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_property() {
+        // Random literals of every length parse as the standard library does
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..200_000 {
+            let digits = (rng.next() % 25 + 1) as usize;
+            let mut literal: String = (0..digits).map(|_| char::from(b'0' + (rng.next() % 10) as u8)).collect();
+            if rng.next().is_multiple_of(2) {
+                literal.insert(rng.next() as usize % digits, '.');
+                if literal.starts_with('.') {
+                    literal.insert(0, '0');
+                }
+            }
+            literal.push_str(&format!("e{}", rng.next() as i64 % 350));
+            let expected: f64 = literal.parse().unwrap();
+            assert_eq!(parse_f64(&literal).map(f64::to_bits), Some(expected.to_bits()), "literal: {}", literal);
+        }
+    }
+}
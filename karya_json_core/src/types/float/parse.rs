@@ -0,0 +1,222 @@
+//! Parsing number literals into `f64`.
+//!
+//! The literal's significant digits are gathered into a `u64` straight from
+//! its bytes, and the result computed from that and the power of ten. Exact
+//! cases use Clinger's fast path, plain floating-point arithmetic; the rest use
+//! the Eisel-Lemire algorithm, a 64 by 128-bit multiplication against a table
+//! of powers of five. The few literals neither can round correctly, those with
+//! more than 19 significant digits or that fall almost exactly halfway between
+//! two floats, go to the standard library's parser.
+
+use super::table::{LARGEST_POWER_OF_FIVE, POWERS_OF_FIVE, SMALLEST_POWER_OF_FIVE};
+
+/// The bits of an `f64` mantissa, not counting the implicit leading one
+const MANTISSA_BITS: u32 = 52;
+
+/// The powers of ten that are exact as `f64`s
+const EXACT_POWERS_OF_TEN: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16, 1e17, 1e18, 1e19,
+    1e20, 1e21, 1e22,
+];
+
+/// Parses a number literal the reader has already validated, returning the
+/// nearest `f64`, or `None` if it is not a number at all.
+///
+/// Besides the JSON number grammar this accepts whatever `str::parse` does,
+/// such as JSON5's `Infinity` and `NaN`.
+pub(crate) fn parse_f64(literal: &str) -> Option<f64> {
+    let Some((negative, mantissa, exponent, truncated)) = decompose(literal.as_bytes()) else {
+        return literal.parse().ok();
+    };
+
+    let magnitude = if mantissa == 0 {
+        Some(0.0)
+    } else if !truncated {
+        fast_path(mantissa, exponent).or_else(|| eisel_lemire(mantissa, exponent))
+    } else {
+        // The digits dropped lie between the truncated mantissa and the next
+        // one up, so if both round to the same float that is the answer
+        match (eisel_lemire(mantissa, exponent), eisel_lemire(mantissa + 1, exponent)) {
+            (Some(low), Some(high)) if low == high => Some(low),
+            _ => None,
+        }
+    };
+
+    match magnitude {
+        Some(n) if negative => Some(-n),
+        Some(n) => Some(n),
+        None => literal.parse().ok(),
+    }
+}
+
+// Splits a literal into its sign, its first 19 significant digits as an
+// integer, the power of ten to multiply them by, and whether any nonzero
+// digits beyond those were dropped. Returns `None` for anything but a plain
+// decimal literal.
+fn decompose(bytes: &[u8]) -> Option<(bool, u64, i64, bool)> {
+    let (negative, bytes) = match bytes.split_first() {
+        Some((b'-', rest)) => (true, rest),
+        _ => (false, bytes),
+    };
+
+    let mut mantissa = 0u64;
+    let mut kept = 0;
+    let mut exponent = 0i64;
+    let mut truncated = false;
+    let mut seen_digit = false;
+    let mut fraction = false;
+    let mut i = 0;
+
+    while let Some(&b) = bytes.get(i) {
+        match b {
+            b'0'..=b'9' => {
+                seen_digit = true;
+                let digit = u64::from(b - b'0');
+                if mantissa == 0 && digit == 0 {
+                    // A leading zero, which is not significant
+                    exponent -= i64::from(fraction);
+                } else if kept < 19 {
+                    mantissa = mantissa * 10 + digit;
+                    kept += 1;
+                    exponent -= i64::from(fraction);
+                } else {
+                    truncated |= digit != 0;
+                    exponent += i64::from(!fraction);
+                }
+            }
+            b'.' if !fraction => fraction = true,
+            b'e' | b'E' => break,
+            _ => return None,
+        }
+        i += 1;
+    }
+    if !seen_digit {
+        return None;
+    }
+
+    if let Some(b'e' | b'E') = bytes.get(i) {
+        let (negative_exponent, digits) = match bytes.get(i + 1) {
+            Some(b'-') => (true, &bytes[i + 2..]),
+            Some(b'+') => (false, &bytes[i + 2..]),
+            _ => (false, &bytes[i + 1..]),
+        };
+        if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        // Exponents too large for an i64 give zero or infinity whatever the
+        // digits, so saturating loses nothing
+        let explicit = digits
+            .iter()
+            .fold(0i64, |n, &b| n.saturating_mul(10).saturating_add(i64::from(b - b'0')));
+        exponent = exponent.saturating_add(if negative_exponent { -explicit } else { explicit });
+    }
+
+    Some((negative, mantissa, exponent, truncated))
+}
+
+// Clinger's fast path: when the mantissa and the power of ten are both exact
+// as floats, a single correctly rounded operation gives the answer
+fn fast_path(mut mantissa: u64, mut exponent: i64) -> Option<f64> {
+    const MAX_EXACT: u64 = 1 << (MANTISSA_BITS + 1);
+
+    // A mantissa with room to spare can absorb part of a larger exponent,
+    // as in `123e25`
+    if (23..=22 + 15).contains(&exponent) {
+        let shifted = mantissa.checked_mul(10u64.pow((exponent - 22) as u32))?;
+        mantissa = shifted;
+        exponent = 22;
+    }
+    if mantissa > MAX_EXACT || !(-22..=22).contains(&exponent) {
+        return None;
+    }
+
+    let value = mantissa as f64;
+    let power = EXACT_POWERS_OF_TEN[exponent.unsigned_abs() as usize];
+    Some(if exponent < 0 { value / power } else { value * power })
+}
+
+// The Eisel-Lemire algorithm, for `mantissa * 10^exponent` with a nonzero
+// mantissa. Returns `None` in the rare cases the product is too close to a
+// rounding boundary for 128 bits to decide.
+fn eisel_lemire(mut mantissa: u64, exponent: i64) -> Option<f64> {
+    if exponent < SMALLEST_POWER_OF_FIVE {
+        return Some(0.0);
+    }
+    if exponent > LARGEST_POWER_OF_FIVE {
+        return Some(f64::INFINITY);
+    }
+
+    // Normalize the mantissa so its top bit is set, and multiply it by the
+    // power of five, keeping the product's top bits
+    let leading_zeros = mantissa.leading_zeros();
+    mantissa <<= leading_zeros;
+    let (low, high) = product_approximation(exponent, mantissa, MANTISSA_BITS + 3);
+    if low == u64::MAX && !(-27..=55).contains(&exponent) {
+        // The truncated bits might have carried into the ones kept
+        return None;
+    }
+
+    let upper_bit = (high >> 63) as i32;
+    let shift = upper_bit + 64 - MANTISSA_BITS as i32 - 3;
+    let mut bits = high >> shift;
+    let mut power2 = binary_exponent(exponent) + upper_bit - leading_zeros as i32 + 1023;
+
+    if power2 <= 0 {
+        // A subnormal number, or zero
+        if -power2 + 1 >= 64 {
+            return Some(0.0);
+        }
+        bits >>= -power2 + 1;
+        bits += bits & 1;
+        bits >>= 1;
+        // Rounding up may have made it the smallest normal number
+        power2 = i32::from(bits >= 1 << MANTISSA_BITS);
+        return Some(f64::from_bits(bits | ((power2 as u64) << MANTISSA_BITS)));
+    }
+
+    // Exactly halfway between two floats, which only happens for small powers:
+    // round to even rather than up
+    if low <= 1 && (-4..=23).contains(&exponent) && bits & 3 == 1 && bits << shift == high {
+        bits &= !1;
+    }
+    bits += bits & 1;
+    bits >>= 1;
+    if bits >= 2 << MANTISSA_BITS {
+        bits = 1 << MANTISSA_BITS;
+        power2 += 1;
+    }
+    bits &= !(1 << MANTISSA_BITS);
+
+    if power2 >= 0x7FF {
+        return Some(f64::INFINITY);
+    }
+    Some(f64::from_bits(bits | ((power2 as u64) << MANTISSA_BITS)))
+}
+
+// The high 128 bits of the mantissa times the 128-bit power of five, as
+// `(low, high)` words, computing the second partial product only when the
+// first leaves the `precision` bits wanted in doubt
+fn product_approximation(exponent: i64, mantissa: u64, precision: u32) -> (u64, u64) {
+    let mask = u64::MAX >> precision;
+    let (high5, low5) = POWERS_OF_FIVE[(exponent - SMALLEST_POWER_OF_FIVE) as usize];
+
+    let (mut low, mut high) = full_multiplication(mantissa, high5);
+    if high & mask == mask {
+        let (_, carry) = full_multiplication(mantissa, low5);
+        low = low.wrapping_add(carry);
+        if carry > low {
+            high += 1;
+        }
+    }
+    (low, high)
+}
+
+// `floor(log2(10^exponent)) + 63`, for exponents within the table
+fn binary_exponent(exponent: i64) -> i32 {
+    (((exponent as i32) * (152_170 + 65_536)) >> 16) + 63
+}
+
+fn full_multiplication(a: u64, b: u64) -> (u64, u64) {
+    let product = u128::from(a) * u128::from(b);
+    (product as u64, (product >> 64) as u64)
+}
@@ -0,0 +1,452 @@
+//! The chunk-fed incremental parser.
+//!
+//! `IncrementalParser` accepts a document in pieces of any size, as they arrive
+//! from a socket or a framed transport, and parses as much as each piece allows.
+//! A chunk may end anywhere, even in the middle of a string, a number or a
+//! multi-byte character. The reader keeps its place in an unfinished token,
+//! including the part of a string decoded so far, and carries on from there
+//! when the next chunk arrives instead of scanning the token again.
+
+use crate::types::{JsonValue, ParserOptions};
+use crate::types::error::{DeserializeError, ErrorCode, ParseWarning, SyntaxError};
+use crate::types::parser::TreeBuilder;
+use crate::types::reader::{JsonReader, Step};
+use crate::types::source::FeedSource;
+use std::fmt;
+
+/// How far an `IncrementalParser` has got after a chunk was fed to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedStatus {
+    /// The document is not complete yet
+    NeedMoreInput,
+    /// The root value has been read completely. Only whitespace may follow,
+    /// and `finish` will return the value.
+    Complete,
+}
+
+/// A parser that is fed its input in chunks.
+///
+/// Call `feed` with each chunk as it arrives and `finish` once the input has
+/// ended. Everything that can be parsed is parsed as soon as it is fed, so
+/// syntax errors are reported by the `feed` call that supplies them, and only
+/// the partly built value and the unfinished token are kept between calls.
+///
+/// After an error, the parser is finished: later calls return the same error
+/// again.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{FeedStatus, IncrementalParser, JsonValue};
+///
+/// let mut parser = IncrementalParser::new();
+///
+/// // Chunks may split strings, numbers and literals anywhere
+/// assert_eq!(parser.feed(br#"{"name": "Al"#).unwrap(), FeedStatus::NeedMoreInput);
+/// assert_eq!(parser.feed(br#"ice", "age": 3"#).unwrap(), FeedStatus::NeedMoreInput);
+/// assert_eq!(parser.feed(b"0}").unwrap(), FeedStatus::Complete);
+///
+/// let value = parser.finish().unwrap();
+/// if let JsonValue::Obj(map) = &value {
+///     assert!(matches!(map.get("age"), Some(JsonValue::Int(30))));
+/// }
+/// ```
+pub struct IncrementalParser {
+    reader: JsonReader<'static, FeedSource>,
+    builder: TreeBuilder<'static, JsonValue>,
+    /// The root value, once it is complete
+    value: Option<JsonValue>,
+    /// The error that ended parsing. Input that is fed can only be invalid
+    /// JSON, so it is always a syntax error.
+    failure: Option<SyntaxError>,
+}
+
+impl IncrementalParser {
+    /// Creates a new parser with no input yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::IncrementalParser;
+    ///
+    /// let parser = IncrementalParser::new();
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            reader: JsonReader::feeding(),
+            builder: TreeBuilder::new(),
+            value: None,
+            failure: None,
+        }
+    }
+
+    /// Sets the options the parser uses, such as its resource limits.
+    ///
+    /// The limits are enforced as the input is fed, so a document that
+    /// exceeds one is rejected without the rest of it having to be buffered.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options to use in place of the defaults
+    ///
+    /// # Returns
+    ///
+    /// The parser, using the given options
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{IncrementalParser, ParserOptions};
+    /// use karya_json::types::error::ErrorCode;
+    ///
+    /// let options = ParserOptions { max_string_length: Some(8), ..ParserOptions::default() };
+    /// let mut parser = IncrementalParser::new().with_options(options);
+    ///
+    /// parser.feed(b"[\"01234").unwrap();
+    /// let error = parser.feed(b"56789").unwrap_err();
+    /// assert_eq!(error.code(), Some(&ErrorCode::StringTooLong(8)));
+    /// ```
+    pub fn with_options(mut self, options: ParserOptions) -> Self {
+        self.reader = self.reader.with_options(options);
+        self
+    }
+
+    /// Feeds the next chunk of input to the parser.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The next bytes of the UTF-8 encoded document; may be empty
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(FeedStatus::NeedMoreInput)` - If the document is not complete yet
+    /// * `Ok(FeedStatus::Complete)` - If the root value has been read completely
+    /// * `Err(DeserializeError)` - If the input fed so far is invalid JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `JsonParser::parse`, as soon as the input
+    /// that causes them has been fed, except for errors that depend on where
+    /// the input ends. Those are only reported by `finish`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{FeedStatus, IncrementalParser};
+    ///
+    /// let mut parser = IncrementalParser::new();
+    /// assert_eq!(parser.feed(b"[1, 2").unwrap(), FeedStatus::NeedMoreInput);
+    /// assert!(parser.feed(b" 3]").is_err());
+    /// ```
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<FeedStatus, DeserializeError> {
+        self.check_usable()?;
+        self.reader.source_mut().feed(chunk);
+        self.run()
+    }
+
+    /// Ends the input and returns the parsed value.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(JsonValue)` - If the input fed is a complete, valid JSON document
+    /// * `Err(DeserializeError)` - If it is invalid or incomplete JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `JsonParser::parse`. In particular, input
+    /// that ends before the document does is reported here as an unterminated
+    /// string, array or object, or an unexpected end of input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{IncrementalParser, JsonValue};
+    ///
+    /// // A number is only known to be complete when the input ends
+    /// let mut parser = IncrementalParser::new();
+    /// parser.feed(b"12").unwrap();
+    /// parser.feed(b"34").unwrap();
+    /// assert!(matches!(parser.finish().unwrap(), JsonValue::Int(1234)));
+    ///
+    /// let mut parser = IncrementalParser::new();
+    /// parser.feed(b"[true, ").unwrap();
+    /// assert!(parser.finish().is_err());
+    /// ```
+    pub fn finish(mut self) -> Result<JsonValue, DeserializeError> {
+        self.check_usable()?;
+        self.reader.source_mut().finish();
+        self.run()?;
+        match self.value {
+            Some(value) => Ok(value),
+            // The reader only reaches the end once the root value is complete
+            None => Err(self.reader.token_error(ErrorCode::UnexpectedEof)),
+        }
+    }
+
+    /// Returns the warnings about the input fed so far, in the order they
+    /// were found, as `JsonParser::warnings` does.
+    ///
+    /// Every warning is found by the `feed` call that supplies the input it
+    /// concerns, so all of them are available before `finish` is called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{DuplicateKeyPolicy, IncrementalParser, ParserOptions};
+    ///
+    /// let options = ParserOptions { duplicate_keys: DuplicateKeyPolicy::LastWins, ..ParserOptions::default() };
+    /// let mut parser = IncrementalParser::new().with_options(options);
+    /// parser.feed(br#"{"a": 1, "a""#).unwrap();
+    ///
+    /// assert_eq!(parser.warnings().len(), 1);
+    /// ```
+    pub fn warnings(&self) -> &[ParseWarning] {
+        self.builder.warnings()
+    }
+
+    // Parses as far as the input fed so far allows
+    fn run(&mut self) -> Result<FeedStatus, DeserializeError> {
+        let result = self.advance();
+        if let Err(DeserializeError::InvalidJson(error)) = &result {
+            self.failure = Some(error.clone());
+        }
+        result
+    }
+
+    fn advance(&mut self) -> Result<FeedStatus, DeserializeError> {
+        loop {
+            match self.reader.poll_event()? {
+                Step::Event(event) => {
+                    if let Some(value) = self.builder.push(event, &self.reader)? {
+                        self.value = Some(value);
+                    }
+                }
+                Step::NeedMoreInput | Step::End => return Ok(self.status()),
+            }
+        }
+    }
+
+    fn status(&self) -> FeedStatus {
+        if self.value.is_some() {
+            FeedStatus::Complete
+        } else {
+            FeedStatus::NeedMoreInput
+        }
+    }
+
+    fn check_usable(&self) -> Result<(), DeserializeError> {
+        match &self.failure {
+            Some(error) => Err(DeserializeError::InvalidJson(error.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+// The partly built value is left out, as it is only a stack of fragments
+impl fmt::Debug for IncrementalParser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IncrementalParser")
+            .field("reader", &self.reader)
+            .field("value", &self.value)
+            .field("failure", &self.failure)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for IncrementalParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Dialect, JsonParser};
+
+    const DOCUMENT: &str = r#" [{"kéy": "v\"al 日本 😀"}, -12.5e-3, 1234567, true, false, null, [], {}, "😀"] "#;
+
+    // Feeds the chunks and finishes, returning the value as JSON text
+    fn feed_all(chunks: &[&[u8]]) -> Result<String, DeserializeError> {
+        let mut parser = IncrementalParser::new();
+        for chunk in chunks {
+            parser.feed(chunk)?;
+        }
+        parser.finish().map(|value| value.to_string())
+    }
+
+    #[test]
+    fn test_any_split_matches_parse() {
+        let expected = JsonParser::from_str(DOCUMENT).parse().unwrap().to_string();
+        let bytes = DOCUMENT.as_bytes();
+
+        for split in 0..=bytes.len() {
+            let (a, b) = bytes.split_at(split);
+            assert_eq!(feed_all(&[a, b]).unwrap(), expected, "split at {}", split);
+        }
+
+        let bytes: Vec<&[u8]> = bytes.chunks(1).collect();
+        assert_eq!(feed_all(&bytes).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_json5_split_anywhere() {
+        let document = "// c\n[{a: [+.5, 0xff, 'x\\\ny\\x41\\\r\nz', 1e+10,]}, /* c ** */ NaN, \"\\uD83D\\uDE00\", ]";
+        let options = ParserOptions { dialect: Dialect::Json5, ..ParserOptions::default() };
+        let expected = JsonParser::from_str(document).with_options(options.clone()).parse().unwrap().to_string();
+        let bytes = document.as_bytes();
+
+        for split in 0..=bytes.len() {
+            let (a, b) = bytes.split_at(split);
+            let mut parser = IncrementalParser::new().with_options(options.clone());
+            parser.feed(a).unwrap();
+            parser.feed(b).unwrap();
+            assert_eq!(parser.finish().unwrap().to_string(), expected, "split at {}", split);
+        }
+
+        let mut parser = IncrementalParser::new().with_options(options);
+        for chunk in bytes.chunks(1) {
+            parser.feed(chunk).unwrap();
+        }
+        assert_eq!(parser.finish().unwrap().to_string(), expected);
+    }
+
+    #[test]
+    fn test_feed_status() {
+        let mut parser = IncrementalParser::new();
+        assert_eq!(parser.feed(b"").unwrap(), FeedStatus::NeedMoreInput);
+        assert_eq!(parser.feed(b"[\"a").unwrap(), FeedStatus::NeedMoreInput);
+        assert_eq!(parser.feed(b"\"]").unwrap(), FeedStatus::Complete);
+        assert_eq!(parser.feed(b" \n").unwrap(), FeedStatus::Complete);
+        assert_eq!(parser.finish().unwrap().to_string(), r#"["a"]"#);
+
+        // A literal split across chunks
+        assert_eq!(feed_all(&[b"[tr", b"u", b"e, nu", b"ll]"]).unwrap(), "[true,null]");
+    }
+
+    #[test]
+    fn test_long_string_in_many_chunks() {
+        let mut parser = IncrementalParser::new();
+        parser.feed(b"[\"").unwrap();
+        for _ in 0..1000 {
+            assert_eq!(parser.feed(b"0123456789").unwrap(), FeedStatus::NeedMoreInput);
+        }
+        assert_eq!(parser.feed(b"\"]").unwrap(), FeedStatus::Complete);
+        match &parser.finish().unwrap() {
+            JsonValue::Arr(items) => {
+                assert!(matches!(&items[0], JsonValue::Str(s) if s.len() == 10_000));
+            }
+            other => panic!("Expected an array, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_long_tokens_are_scanned_once() {
+        // Feeds the document in small chunks, returning how many bytes the
+        // reader looked at
+        fn bytes_read(document: &str, options: ParserOptions) -> usize {
+            let mut parser = IncrementalParser::new().with_options(options);
+            for chunk in document.as_bytes().chunks(7) {
+                parser.feed(chunk).unwrap();
+            }
+            let bytes_read = parser.reader.bytes_read;
+            parser.finish().unwrap();
+            bytes_read
+        }
+
+        let escaped = "ab\\n\\u00e9\\uD83D\\uDE00".repeat(100_000);
+        let digits = "1".repeat(200_000);
+        let json5 = ParserOptions { dialect: Dialect::Json5, ..ParserOptions::default() };
+        let limited = ParserOptions {
+            max_string_length: Some(usize::MAX / 2),
+            max_input_length: Some(usize::MAX / 2),
+            ..ParserOptions::default()
+        };
+        let cases = [
+            (format!("[\"{}\"]", escaped), ParserOptions::default()),
+            (format!("[\"{}\"]", escaped), limited.clone()),
+            (format!("{{\"{}\": 1}}", escaped), limited),
+            (format!("['{}']", escaped), json5.clone()),
+            (format!("[{}.{}e-{}]", digits, digits, digits.len()), json5.clone()),
+            (format!("[0x{}]", "f".repeat(16)), json5.clone()),
+            (format!("[/* {} */ 1]", "* /".repeat(100_000)), json5.clone()),
+            (format!("[1 // {}\n]", digits), json5.clone()),
+            (format!("{{{}: 1}}", "k".repeat(200_000)), json5),
+        ];
+        for (document, options) in cases {
+            // Each byte is looked at a few times at most, rather than again for
+            // every chunk that follows it in the same token. A number is read
+            // again from its start when its point or exponent arrives.
+            let read = bytes_read(&document, options);
+            assert!(read < 5 * document.len(), "read {} of {} bytes: {:.40}", read, document.len(), document);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        // Errors are reported by the chunk that causes them, located in the
+        // input as a whole
+        let mut parser = IncrementalParser::new();
+        parser.feed(b"{\"a\": [1,\n").unwrap();
+        let error = parser.feed(b" 2 x").unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::ExpectedCommaOrClose { close: ']', found: 'x' }));
+        let position = error.position().unwrap();
+        assert_eq!((position.offset, position.line, position.column), (13, 2, 4));
+        assert_eq!(error.to_string(), "Invalid JSON: Expected ',' or ']', found 'x' at line 2, column 4 in /a/1");
+
+        // The parser can't be used after an error, and keeps reporting it
+        assert_eq!(parser.feed(b"]}").unwrap_err().to_string(), error.to_string());
+        assert_eq!(parser.finish().unwrap_err().to_string(), error.to_string());
+
+        // Input that ends too soon is only an error once it has ended
+        let cases: [(&[u8], ErrorCode); 5] = [
+            (b"", ErrorCode::UnexpectedEof),
+            (b"[1, 2", ErrorCode::UnterminatedArray),
+            (b"{\"a\": \"b", ErrorCode::UnterminatedString),
+            (b"1e", ErrorCode::MissingExponentDigits),
+            (b"\"\xE6\x97", ErrorCode::InvalidUtf8(1)),
+        ];
+        for (input, code) in cases {
+            let mut parser = IncrementalParser::new();
+            assert_eq!(parser.feed(input).unwrap(), FeedStatus::NeedMoreInput);
+            assert_eq!(parser.finish().unwrap_err().code(), Some(&code));
+        }
+
+        // Trailing characters and invalid UTF-8 are caught as they are fed
+        let mut parser = IncrementalParser::new();
+        parser.feed(b"{}").unwrap();
+        assert!(parser.feed(b" {}").is_err());
+        let mut parser = IncrementalParser::new();
+        let error = parser.feed(b"[\"\xFF\"]").unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::InvalidUtf8(2)));
+    }
+
+    #[test]
+    fn test_errors_inside_unfinished_strings() {
+        // Strings are checked as they arrive, not only once they end
+        let cases: [(&[&[u8]], ErrorCode); 4] = [
+            (&[b"[\"abc", b"\x01def"], ErrorCode::ControlCharacterInString(1)),
+            (&[b"[\"abc", b"def\\q"], ErrorCode::InvalidEscape('q')),
+            (&[b"[\"abc\\", b"q"], ErrorCode::InvalidEscape('q')),
+            (&[b"[\"abc\\u12", b"xy"], ErrorCode::InvalidUnicodeEscape('x')),
+        ];
+        for (chunks, code) in cases {
+            let mut parser = IncrementalParser::new();
+            let (last, first) = chunks.split_last().unwrap();
+            for chunk in first {
+                parser.feed(chunk).unwrap();
+            }
+            assert_eq!(parser.feed(last).unwrap_err().code(), Some(&code), "{:?}", chunks);
+        }
+
+        // Complete escapes don't stop later chunks being skipped over
+        let mut parser = IncrementalParser::new();
+        parser.feed(b"[\"\\u00e9\\n").unwrap();
+        for _ in 0..10 {
+            assert_eq!(parser.feed(b"0123456789").unwrap(), FeedStatus::NeedMoreInput);
+        }
+        assert!(format!("{:?}", parser).starts_with("IncrementalParser { reader: "));
+        assert_eq!(parser.feed(b"\"]").unwrap(), FeedStatus::Complete);
+    }
+}
@@ -0,0 +1,641 @@
+//! The maps that hold object members.
+//!
+//! `JsonMap`, the default, stores its members in a `Vec`, so iterating over a
+//! parsed object visits the members in the order they appear in the source,
+//! and writing it out reproduces that order. Small maps are searched by
+//! scanning the `Vec`; past `LINEAR_LIMIT` members a hash table of positions
+//! in the `Vec` is kept alongside it for lookups.
+//!
+//! Other workloads suit other maps, so `GenericJsonValue` takes a
+//! `MapBackend` naming the map its objects use. Any map implementing
+//! `ObjectMap` can serve; `HashMap`, `BTreeMap` and `VecMap` come ready.
+
+use crate::types::JsonValue;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{BuildHasher, Hash, RandomState};
+
+/// The most members a map holds before it starts keeping a hash index
+const LINEAR_LIMIT: usize = 8;
+/// Marks a free slot in the hash index
+const EMPTY: usize = usize::MAX;
+
+/// A map from string keys to values that remembers the order in which keys
+/// were first inserted.
+///
+/// This is the map held by `JsonValue::Obj` and, with `Cow` keys, by
+/// `JsonValueRef::Obj`. Its methods mirror those of `HashMap`, but iteration
+/// follows insertion order: the parser inserts members in source order, so
+/// parsing and writing a document keeps its members where they were.
+///
+/// Replacing the value of an existing key keeps the key's position. Removing
+/// a key shifts the members after it down, which takes time proportional to
+/// the length of the map.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{JsonMap, JsonValue};
+///
+/// let mut map = JsonMap::new();
+/// map.insert("zebra".to_string(), JsonValue::Int(1));
+/// map.insert("apple".to_string(), JsonValue::Int(2));
+///
+/// let keys: Vec<&str> = map.keys().map(String::as_str).collect();
+/// assert_eq!(keys, ["zebra", "apple"]);
+/// assert_eq!(JsonValue::Obj(map.into()).to_string(), r#"{"zebra":1,"apple":2}"#);
+/// ```
+#[derive(Clone)]
+pub struct JsonMap<K = String, V = JsonValue> {
+    entries: Vec<(K, V)>,
+    /// An open-addressed table of positions in `entries`, with `EMPTY` for
+    /// free slots, or empty while the map is small enough to scan
+    index: Vec<usize>,
+    hasher: RandomState,
+}
+
+impl<K, V> JsonMap<K, V> {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates an empty map with room for at least `capacity` members.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            index: Vec::new(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Returns the number of members in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the map has no members.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all members.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    /// Returns an iterator over the members, in order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.entries.iter())
+    }
+
+    /// Returns an iterator over the members, in order, with mutable values.
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut(self.entries.iter_mut())
+    }
+
+    /// Returns an iterator over the keys, in order.
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + ExactSizeIterator {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    /// Returns an iterator over the values, in order.
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator {
+        self.entries.iter().map(|(_, val)| val)
+    }
+
+    /// Returns an iterator over mutable references to the values, in order.
+    pub fn values_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut V> + ExactSizeIterator {
+        self.entries.iter_mut().map(|(_, val)| val)
+    }
+
+    /// Removes all members, returning them in order.
+    pub fn drain(&mut self) -> std::vec::Drain<'_, (K, V)> {
+        self.index.clear();
+        self.entries.drain(..)
+    }
+}
+
+impl<K: Borrow<str>, V> JsonMap<K, V> {
+    /// Returns the value for `key`, if present.
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.position(key).map(|i| &self.entries[i].1)
+    }
+
+    /// Returns a mutable reference to the value for `key`, if present.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.position(key).map(|i| &mut self.entries[i].1)
+    }
+
+    /// Returns `true` if the map has a member with this key.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    /// Inserts a member, returning the previous value for the key if there
+    /// was one. A new key goes at the end; an existing key keeps its place
+    /// and only its value is replaced.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(i) = self.position(key.borrow()) {
+            return Some(std::mem::replace(&mut self.entries[i].1, value));
+        }
+        self.entries.push((key, value));
+        if self.index.len() < 2 * self.entries.len() {
+            self.reindex();
+        } else {
+            self.place(self.entries.len() - 1);
+        }
+        None
+    }
+
+    /// Removes the member with this key, returning its value if present. The
+    /// members after it move down, keeping their order.
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let i = self.position(key)?;
+        let (_, value) = self.entries.remove(i);
+        self.reindex();
+        Some(value)
+    }
+
+    // The position of `key` in `entries`
+    fn position(&self, key: &str) -> Option<usize> {
+        if self.index.is_empty() {
+            return self.entries.iter().position(|(k, _)| k.borrow() == key);
+        }
+        let mask = self.index.len() - 1;
+        let mut slot = self.hasher.hash_one(key) as usize & mask;
+        loop {
+            match self.index[slot] {
+                EMPTY => return None,
+                i if self.entries[i].0.borrow() == key => return Some(i),
+                _ => slot = (slot + 1) & mask,
+            }
+        }
+    }
+
+    // Rebuilds the index from scratch, at most half full, or drops it if the
+    // map is small enough to scan
+    fn reindex(&mut self) {
+        self.index.clear();
+        if self.entries.len() <= LINEAR_LIMIT {
+            return;
+        }
+        self.index.resize((2 * self.entries.len()).next_power_of_two(), EMPTY);
+        for i in 0..self.entries.len() {
+            self.place(i);
+        }
+    }
+
+    // Records the position of `entries[i]` in the first free slot from its hash
+    fn place(&mut self, i: usize) {
+        let mask = self.index.len() - 1;
+        let mut slot = self.hasher.hash_one(self.entries[i].0.borrow()) as usize & mask;
+        while self.index[slot] != EMPTY {
+            slot = (slot + 1) & mask;
+        }
+        self.index[slot] = i;
+    }
+}
+
+impl<K, V> Default for JsonMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for JsonMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Borrow<str>, V> FromIterator<(K, V)> for JsonMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = JsonMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Borrow<str>, V> Extend<(K, V)> for JsonMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V> IntoIterator for JsonMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'m, K, V> IntoIterator for &'m JsonMap<K, V> {
+    type Item = (&'m K, &'m V);
+    type IntoIter = Iter<'m, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'m, K, V> IntoIterator for &'m mut JsonMap<K, V> {
+    type Item = (&'m K, &'m mut V);
+    type IntoIter = IterMut<'m, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// An iterator over the members of a `JsonMap`, in order.
+#[derive(Debug, Clone)]
+pub struct Iter<'m, K, V>(std::slice::Iter<'m, (K, V)>);
+
+impl<'m, K, V> Iterator for Iter<'m, K, V> {
+    type Item = (&'m K, &'m V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, val)| (key, val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(key, val)| (key, val))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+/// An iterator over the members of a `JsonMap`, in order, with mutable values.
+#[derive(Debug)]
+pub struct IterMut<'m, K, V>(std::slice::IterMut<'m, (K, V)>);
+
+impl<'m, K, V> Iterator for IterMut<'m, K, V> {
+    type Item = (&'m K, &'m mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, val)| (&*key, val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IterMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(key, val)| (&*key, val))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+/// A map of object members, as used by the parser and serializer.
+///
+/// Keys are looked up by `&str`, whatever their own type. Implement this,
+/// together with a `MapBackend` naming the map, to store objects in a map of
+/// your own.
+pub trait ObjectMap<K, V>: Default + IntoIterator<Item = (K, V)> + FromIterator<(K, V)> {
+    /// The iterator returned by `iter`
+    type Iter<'m>: Iterator<Item = (&'m K, &'m V)>
+    where
+        Self: 'm,
+        K: 'm,
+        V: 'm;
+
+    /// Returns the number of members in the map.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the map has no members.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the value for `key`, if present.
+    fn get(&self, key: &str) -> Option<&V>;
+
+    /// Returns a mutable reference to the value for `key`, if present.
+    fn get_mut(&mut self, key: &str) -> Option<&mut V>;
+
+    /// Returns `true` if the map has a member with this key.
+    fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Inserts a member, returning the previous value for the key if there
+    /// was one.
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+
+    /// Removes the member with this key, returning its value if present.
+    fn remove(&mut self, key: &str) -> Option<V>;
+
+    /// Returns an iterator over the members, in the map's own order.
+    fn iter(&self) -> Self::Iter<'_>;
+}
+
+impl<K: Borrow<str>, V> ObjectMap<K, V> for JsonMap<K, V> {
+    type Iter<'m>
+        = Iter<'m, K, V>
+    where
+        Self: 'm;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+    fn get(&self, key: &str) -> Option<&V> {
+        self.get(key)
+    }
+    fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.get_mut(key)
+    }
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+    fn remove(&mut self, key: &str) -> Option<V> {
+        self.remove(key)
+    }
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+}
+
+impl<K: Borrow<str> + Eq + Hash, V> ObjectMap<K, V> for HashMap<K, V> {
+    type Iter<'m>
+        = std::collections::hash_map::Iter<'m, K, V>
+    where
+        Self: 'm;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+    fn get(&self, key: &str) -> Option<&V> {
+        self.get(key)
+    }
+    fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.get_mut(key)
+    }
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+    fn remove(&mut self, key: &str) -> Option<V> {
+        self.remove(key)
+    }
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+}
+
+impl<K: Borrow<str> + Ord, V> ObjectMap<K, V> for BTreeMap<K, V> {
+    type Iter<'m>
+        = std::collections::btree_map::Iter<'m, K, V>
+    where
+        Self: 'm;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+    fn get(&self, key: &str) -> Option<&V> {
+        self.get(key)
+    }
+    fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.get_mut(key)
+    }
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+    fn remove(&mut self, key: &str) -> Option<V> {
+        self.remove(key)
+    }
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+}
+
+/// A map kept as a plain `Vec` of members in insertion order, searched from
+/// the start for every lookup.
+///
+/// With no hash table to build, this is the smallest and quickest map for
+/// objects of a handful of members, and the slowest for large ones. Its
+/// methods are those of `ObjectMap`.
+#[derive(Debug, Clone)]
+pub struct VecMap<K = String, V = JsonValue>(Vec<(K, V)>);
+
+impl<K, V> Default for VecMap<K, V> {
+    fn default() -> Self {
+        VecMap(Vec::new())
+    }
+}
+
+impl<K: Borrow<str>, V> ObjectMap<K, V> for VecMap<K, V> {
+    type Iter<'m>
+        = Iter<'m, K, V>
+    where
+        Self: 'm;
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn get(&self, key: &str) -> Option<&V> {
+        self.0.iter().find(|(k, _)| k.borrow() == key).map(|(_, val)| val)
+    }
+    fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.0.iter_mut().find(|(k, _)| k.borrow() == key).map(|(_, val)| val)
+    }
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.get_mut(key.borrow()) {
+            Some(existing) => Some(std::mem::replace(existing, value)),
+            None => {
+                self.0.push((key, value));
+                None
+            }
+        }
+    }
+    fn remove(&mut self, key: &str) -> Option<V> {
+        let i = self.0.iter().position(|(k, _)| k.borrow() == key)?;
+        Some(self.0.remove(i).1)
+    }
+    fn iter(&self) -> Self::Iter<'_> {
+        Iter(self.0.iter())
+    }
+}
+
+impl<K: Borrow<str>, V> FromIterator<(K, V)> for VecMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = VecMap::default();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl<K, V> IntoIterator for VecMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// Chooses the map that objects are stored in.
+///
+/// `GenericJsonValue<B>` holds its objects in a `B::Map`, and
+/// `JsonParser::parse_with_maps` builds them. The backends provided are
+/// uninhabited types, used only to name a map, and like them a backend must
+/// not borrow anything:
+///
+/// - `Ordered`: `JsonMap`, in insertion order. The default, used by `JsonValue`
+/// - `Sorted`: `BTreeMap`, in key order, for output sorted by key
+/// - `Hashed`: `HashMap`, in no particular order, for lookups in large objects
+/// - `Small`: `VecMap`, in insertion order, for many tiny objects
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{GenericJsonValue, JsonParser, Sorted};
+///
+/// let value: GenericJsonValue<Sorted> = JsonParser::from_str(r#"{"b": 1, "a": 2}"#).parse_with_maps().unwrap();
+/// assert_eq!(value.to_string(), r#"{"a":2,"b":1}"#);
+/// ```
+pub trait MapBackend: 'static {
+    /// The map, for objects whose values are `V`
+    type Map<V>: ObjectMap<String, V>;
+}
+
+/// Stores objects in a `JsonMap`, keeping insertion order.
+#[derive(Debug)]
+pub enum Ordered {}
+
+impl MapBackend for Ordered {
+    type Map<V> = JsonMap<String, V>;
+}
+
+/// Stores objects in a `BTreeMap`, sorted by key.
+#[derive(Debug)]
+pub enum Sorted {}
+
+impl MapBackend for Sorted {
+    type Map<V> = BTreeMap<String, V>;
+}
+
+/// Stores objects in a `HashMap`, in no particular order.
+#[derive(Debug)]
+pub enum Hashed {}
+
+impl MapBackend for Hashed {
+    type Map<V> = HashMap<String, V>;
+}
+
+/// Stores objects in a `VecMap`, keeping insertion order.
+#[derive(Debug)]
+pub enum Small {}
+
+impl MapBackend for Small {
+    type Map<V> = VecMap<String, V>;
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DuplicateKeyPolicy, JsonParser, ParserOptions};
+
+    #[test]
+    fn test_insertion_order() {
+        let mut map: JsonMap<String, i32> = JsonMap::new();
+        for (i, key) in ["c", "a", "b"].iter().enumerate() {
+            assert_eq!(map.insert(key.to_string(), i as i32), None);
+        }
+        // Replacing a value keeps the key where it was
+        assert_eq!(map.insert("c".to_string(), 10), Some(0));
+
+        let members: Vec<(&str, i32)> = map.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(members, [("c", 10), ("a", 1), ("b", 2)]);
+
+        assert_eq!(map.remove("a"), Some(1));
+        assert_eq!(map.remove("a"), None);
+        let keys: Vec<&str> = map.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["c", "b"]);
+    }
+
+    #[test]
+    fn test_large_maps() {
+        // Past the linear limit lookups go through the index, which must
+        // follow every insertion and removal
+        let mut map: JsonMap<String, usize> = (0..1000).map(|i| (i.to_string(), i)).collect();
+        assert_eq!(map.len(), 1000);
+        for i in (0..1000).step_by(3) {
+            assert_eq!(map.remove(&i.to_string()), Some(i));
+        }
+        for i in 0..1000 {
+            let expected = if i % 3 == 0 { None } else { Some(&i) };
+            assert_eq!(map.get(&i.to_string()), expected);
+        }
+        assert!(map.values().copied().eq((0..1000).filter(|i| i % 3 != 0)));
+
+        // Shrinking below the limit goes back to scanning
+        let keys: Vec<String> = map.keys().skip(4).cloned().collect();
+        for key in keys {
+            map.remove(&key);
+        }
+        assert_eq!(map.get("1"), Some(&1));
+        assert_eq!(map.get("7"), None);
+        *map.get_mut("5").unwrap() = 50;
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), [1, 2, 4, 50]);
+    }
+
+    // Parses with the backend's maps and writes the result back out
+    fn round_trip<B: MapBackend>(input: &str) -> String {
+        let value = JsonParser::from_str(input).parse_with_maps::<B>().unwrap();
+        value.to_string()
+    }
+
+    #[test]
+    fn test_backends() {
+        let input = r#"{"b":1,"c":{"z":[],"y":null},"a":"x"}"#;
+        assert_eq!(round_trip::<Ordered>(input), input);
+        assert_eq!(round_trip::<Small>(input), input);
+        assert_eq!(round_trip::<Sorted>(input), r#"{"a":"x","b":1,"c":{"y":null,"z":[]}}"#);
+        assert_eq!(round_trip::<Hashed>(input).len(), input.len());
+
+        // Duplicate keys follow the policy whatever the map
+        let options = ParserOptions { duplicate_keys: DuplicateKeyPolicy::CollectAll, ..ParserOptions::default() };
+        let value = JsonParser::from_str(r#"{"k": 1, "j": 0, "k": 2}"#)
+            .with_options(options)
+            .parse_with_maps::<Small>()
+            .unwrap();
+        assert_eq!(value.to_string(), r#"{"k":[1,2],"j":0}"#);
+        assert_eq!(format!("{:?}", value), r#"Obj({"k": Arr([Int(1), Int(2)]), "j": Int(0)})"#);
+
+        // Deeply nested objects still drop without recursion
+        let depth = 100_000;
+        let input = r#"{"a":"#.repeat(depth) + "null" + &"}".repeat(depth);
+        let value = JsonParser::from_str(&input).parse_with_maps::<Sorted>().unwrap();
+        drop(value);
+    }
+
+    #[test]
+    fn test_vec_map() {
+        let mut map: VecMap<String, i32> = [("b".to_string(), 1), ("a".to_string(), 2)].into_iter().collect();
+        assert_eq!(map.insert("b".to_string(), 3), Some(1));
+        assert_eq!(map.insert("c".to_string(), 4), None);
+        assert_eq!(map.remove("a"), Some(2));
+        assert!(!map.contains_key("a"));
+        assert_eq!(ObjectMap::len(&map), 2);
+        let members: Vec<(String, i32)> = map.into_iter().collect();
+        assert_eq!(members, [("b".to_string(), 3), ("c".to_string(), 4)]);
+    }
+}
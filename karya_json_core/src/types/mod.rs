@@ -2,7 +2,19 @@
 //!
 //! This module provides the fundamental types and functions for working with JSON data:
 //! - `JsonValue`: An enum representing all possible JSON value types
+//! - `GenericJsonValue`: The same enum, with objects held in a map of your choosing
+//! - `JsonValueRef`: A borrowed counterpart to `JsonValue` that avoids copying strings
+//! - `JsonArray` and `JsonObject`: The containers holding an array's elements and an object's members
+//! - `JsonMap`: The map of an object's members, which keeps them in source order
+//! - `JsonNumber`: A number kept exactly as written, for lossless parsing
+//! - `Decimal`: An exact decimal number, with the `decimal` feature
 //! - `JsonParser`: A parser for converting JSON strings into `JsonValue` instances
+//! - `JsonReader`: A pull-based reader that yields a document as a sequence of `JsonEvent`s
+//! - `IncrementalParser`: A parser fed with chunks of input as they arrive
+//! - `ParserOptions`: Resource limits and other settings shared by the parsers
+//! - `Dialect`: The variants of JSON the parsers accept, such as JSON5
+//! - `SerializeOptions`: Settings for `JsonValue::to_json_string`, such as how to write non-finite numbers
+//! - `JsonVisitor`: Callbacks driven by `JsonParser::parse_with`, for processing a document without building it
 //!
 //! # Examples
 //!
@@ -11,64 +23,135 @@
 //!
 //! // Parse a JSON string
 //! let json_str = r#"{"name": "Alice", "age": 30}"#;
-//! let mut parser = JsonParser::new(json_str.to_string());
+//! let mut parser = JsonParser::from_str(json_str);
 //! let value = parser.parse().expect("Failed to parse JSON");
 //!
 //! // Work with the parsed data
-//! if let JsonValue::Obj(map) = value {
-//!     if let Some(JsonValue::Str(name)) = map.get("name") {
-//!         assert_eq!(name, "Alice");
-//!     }
-//!     if let Some(JsonValue::Int(age)) = map.get("age") {
-//!         assert_eq!(*age, 30);
-//!     }
+//! assert_eq!(value["name"].as_str(), Some("Alice"));
+//! assert_eq!(value["age"].as_i64(), Some(30));
+//!
+//! // Or match on it
+//! if let JsonValue::Obj(map) = &value {
+//!     assert!(matches!(map.get("age"), Some(JsonValue::Int(30))));
 //! }
 //! ```
 
+/// Compiler-style rendering of parse errors
+pub mod diagnostic;
 /// Error types for serialization and deserialization
 pub mod error;
-
-use crate::types::error::DeserializeError;
-use std::collections::HashMap;
-
-/// Represents a JSON value.
+mod access;
+mod compare;
+mod container;
+mod convert;
+#[cfg(feature = "decimal")]
+mod decimal;
+mod float;
+mod incremental;
+/// The maps that hold object members
+pub mod map;
+mod number;
+mod options;
+mod parser;
+mod reader;
+mod serialize;
+/// Input sources the parser can read from
+pub mod source;
+mod value_ref;
+mod visitor;
+
+#[cfg(feature = "decimal")]
+pub use decimal::Decimal;
+pub use container::{JsonArray, JsonObject, Nested};
+pub use incremental::{FeedStatus, IncrementalParser};
+pub use map::{Hashed, JsonMap, MapBackend, ObjectMap, Ordered, Small, Sorted, VecMap};
+pub use number::JsonNumber;
+pub use options::{Dialect, DuplicateKeyPolicy, NumberMode, ParserOptions};
+pub use parser::JsonParser;
+pub use reader::{JsonEvent, JsonReader};
+pub use serialize::{NonFinitePolicy, SerializeOptions};
+pub use value_ref::JsonValueRef;
+pub use visitor::JsonVisitor;
+
+/// Represents a JSON value, with objects held in `JsonMap`s.
+///
+/// This is `GenericJsonValue` with the default `Ordered` backend, and what
+/// `JsonParser::parse` returns.
 ///
 /// This enum can represent any valid JSON data type:
 /// - `Int`: A 64-bit signed integer
+/// - `UInt`, `Int128`, `UInt128`: Integers beyond the range of `i64`
 /// - `Float`: A 64-bit floating point number
+/// - `Number`: A number kept exactly as written, from lossless parsing
+/// - `Decimal`: An exact decimal number, with the `decimal` feature
 /// - `Bool`: A boolean value (true or false)
 /// - `Str`: A UTF-8 encoded string
 /// - `Arr`: An ordered array of JSON values
-/// - `Obj`: A key-value map where keys are strings and values are JSON values
+/// - `Obj`: A key-value map where keys are strings and values are JSON values,
+///   kept in insertion order
 /// - `Null`: The JSON null value
 ///
 /// # Examples
 ///
 /// Creating a JSON object:
 /// ```
-/// use karya_json::types::JsonValue;
-/// use std::collections::HashMap;
+/// use karya_json::types::{JsonMap, JsonValue};
 ///
-/// let mut map = HashMap::new();
+/// let mut map = JsonMap::new();
 /// map.insert("name".to_string(), JsonValue::Str("Alice".to_string()));
 /// map.insert("age".to_string(), JsonValue::Int(30));
 ///
-/// let json_obj = JsonValue::Obj(map);
+/// let json_obj = JsonValue::Obj(map.into());
 /// ```
-#[derive(Debug)]
-pub enum JsonValue {
+pub type JsonValue = GenericJsonValue<Ordered>;
+
+/// Represents a JSON value, with objects held in the map `B` chooses.
+///
+/// `JsonValue` is the usual form of this, with insertion-ordered objects;
+/// see `MapBackend` for the other maps available.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{GenericJsonValue, Hashed, ObjectMap};
+/// use std::collections::HashMap;
+///
+/// let mut map = HashMap::new();
+/// map.insert("id".to_string(), GenericJsonValue::<Hashed>::Int(7));
+/// let value = GenericJsonValue::<Hashed>::Obj(map.into());
+///
+/// assert_eq!(value.to_string(), r#"{"id":7}"#);
+/// ```
+pub enum GenericJsonValue<B: MapBackend> {
     /// A 64-bit signed integer
     Int(i64),
+    /// A 64-bit unsigned integer. The parser uses it only for integers
+    /// above `i64::MAX`.
+    UInt(u64),
+    /// A 128-bit signed integer. The parser uses it only for integers beyond
+    /// the range of both `i64` and `u64`.
+    Int128(i128),
+    /// A 128-bit unsigned integer. The parser uses it only for integers above
+    /// `i128::MAX`.
+    UInt128(u128),
     /// A 64-bit floating point number
     Float(f64),
+    /// A number kept exactly as written, produced in place of `Int` and
+    /// `Float` by `NumberMode::Lossless`
+    Number(JsonNumber),
+    /// An exact decimal number, produced in place of `Int` and `Float` by
+    /// `NumberMode::Decimal`
+    #[cfg(feature = "decimal")]
+    Decimal(Decimal),
     /// A boolean value (true or false)
     Bool(bool),
     /// A UTF-8 encoded string
     Str(String),
     /// An ordered array of JSON values
-    Arr(Vec<JsonValue>),
-    /// A key-value map where keys are strings and values are JSON values
-    Obj(HashMap<String, JsonValue>),
+    Arr(JsonArray<GenericJsonValue<B>>),
+    /// A key-value map where keys are strings and values are JSON values, in
+    /// the map the backend chooses
+    Obj(JsonObject<GenericJsonValue<B>>),
     /// The JSON null value
     Null,
 }
@@ -95,7 +178,7 @@ use std::fmt;
 /// assert_eq!(format!("{}", json_value), "\"Hello, world!\"");
 /// ```
 /// Helper function to write an escaped JSON string to a formatter
-fn escape_json_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+fn escape_json_string<W: fmt::Write>(f: &mut W, s: &str) -> fmt::Result {
     write!(f, "\"")?;
     for c in s.chars() {
         match c {
@@ -116,561 +199,121 @@ fn escape_json_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
     write!(f, "\"")
 }
 
-impl fmt::Display for JsonValue {
-    /// Formats the JsonValue as a JSON string.
-    ///
-    /// This method is automatically called when using string formatting macros
-    /// like `format!`, `println!`, etc. with a JsonValue.
+impl<B: MapBackend> fmt::Debug for GenericJsonValue<B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            JsonValue::Int(n) => write!(f, "{}", n),
-            JsonValue::Float(n) => {
-                // Handle special float values according to JSON spec
-                if n.is_nan() || n.is_infinite() {
-                    write!(f, "null") // JSON doesn't support NaN or Infinity
-                } else {
-                    write!(f, "{}", n)
-                }
-            },
-            JsonValue::Bool(b) => write!(f, "{}", b),
-            JsonValue::Str(s) => escape_json_string(f, s),
-            JsonValue::Arr(arr) => {
-                write!(f, "[")?;
-                for (i, val) in arr.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ",")?;
-                    }
-                    write!(f, "{}", val)?;
-                }
-                write!(f, "]")
-            }
-            JsonValue::Obj(map) => {
-                write!(f, "{{")?;
-                for (i, (key, val)) in map.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ",")?;
-                    }
-                    // Properly escape the key
-                    escape_json_string(f, key)?;
-                    write!(f, ":{}", val)?;
-                }
-                write!(f, "}}")
-            }
-            JsonValue::Null => write!(f, "null"),
+            GenericJsonValue::Int(n) => f.debug_tuple("Int").field(n).finish(),
+            GenericJsonValue::UInt(n) => f.debug_tuple("UInt").field(n).finish(),
+            GenericJsonValue::Int128(n) => f.debug_tuple("Int128").field(n).finish(),
+            GenericJsonValue::UInt128(n) => f.debug_tuple("UInt128").field(n).finish(),
+            GenericJsonValue::Float(n) => f.debug_tuple("Float").field(n).finish(),
+            GenericJsonValue::Number(n) => f.debug_tuple("Number").field(n).finish(),
+            #[cfg(feature = "decimal")]
+            GenericJsonValue::Decimal(n) => f.debug_tuple("Decimal").field(n).finish(),
+            GenericJsonValue::Bool(b) => f.debug_tuple("Bool").field(b).finish(),
+            GenericJsonValue::Str(s) => f.debug_tuple("Str").field(s).finish(),
+            GenericJsonValue::Arr(items) => f.debug_tuple("Arr").field(items).finish(),
+            GenericJsonValue::Obj(map) => f.debug_tuple("Obj").field(map).finish(),
+            GenericJsonValue::Null => f.write_str("Null"),
         }
     }
 }
 
-/// A parser for converting JSON strings into `JsonValue` instances.
-///
-/// The `JsonParser` provides methods for parsing JSON strings according to the
-/// JSON specification. It handles all valid JSON data types and provides detailed
-/// error messages for invalid JSON.
-///
-/// # Examples
-///
-/// ```
-/// use karya_json::types::{JsonParser, JsonValue};
-///
-/// let json_str = r#"{"name": "Alice", "age": 30, "is_active": true}"#;
-/// let mut parser = JsonParser::new(json_str.to_string());
-/// let result = parser.parse();
-///
-/// assert!(result.is_ok());
-/// ```
-#[derive(Debug, Clone)]
-pub struct JsonParser {
-    /// The input JSON string as a vector of characters
-    input: Vec<char>,
-    /// The current position in the input
-    position: usize,
-}
-
-impl JsonParser {
-    /// Creates a new JSON parser for the given input string.
-    ///
-    /// # Arguments
-    ///
-    /// * `input` - The JSON string to parse
-    ///
-    /// # Returns
-    ///
-    /// A new `JsonParser` instance initialized with the input string
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use karya_json::types::JsonParser;
-    ///
-    /// let parser = JsonParser::new(r#"{"key": "value"}"#.to_string());
-    /// ```
-    pub fn new(input: String) -> Self {
-        Self {
-            input: input.chars().collect(),
-            position: 0,
-        }
-    }
-
-    /// Parses the input JSON string into a `JsonValue`.
-    ///
-    /// This is the main entry point for parsing JSON. It parses the entire input
-    /// string and returns a `JsonValue` representing the parsed JSON data.
-    ///
-    /// # Returns
-    ///
-    /// * `Ok(JsonValue)` - If the input is valid JSON
-    /// * `Err(DeserializeError)` - If the input is invalid JSON
-    ///
-    /// # Errors
-    ///
-    /// Returns a `DeserializeError` if:
-    /// * The input is not valid JSON
-    /// * There are unexpected trailing characters after the JSON value
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use karya_json::types::{JsonParser, JsonValue};
-    ///
-    /// let mut parser = JsonParser::new(r#"{"name": "Alice"}"#.to_string());
-    /// let result = parser.parse();
+impl<B: MapBackend> fmt::Display for GenericJsonValue<B> {
+    /// Formats the JsonValue as a JSON string.
     ///
-    /// assert!(result.is_ok());
-    /// ```
-    pub fn parse(&mut self) -> Result<JsonValue, DeserializeError> {
-        self.skip_whitespace();
-        let value = self.parse_value()?;
-        self.skip_whitespace();
-
-        // Ensure we've consumed all inputs
-        if self.position < self.input.len() {
-            return Err(DeserializeError::InvalidJson(
-                "Unexpected trailing characters".to_string(),
-            ));
-        }
-        Ok(value)
-    }
-
-    // Core parsing methods
-    fn parse_value(&mut self) -> Result<JsonValue, DeserializeError> {
-        self.skip_whitespace();
-
-        match self.peek_char() {
-            Some('"') => self.parse_string().map(JsonValue::Str),
-            Some('-') | Some('0'..='9') => self.parse_number(),
-            Some('t') | Some('f') => self.parse_boolean().map(JsonValue::Bool),
-            Some('n') => self.parse_null().map(|_| JsonValue::Null),
-            Some('[') => self.parse_array().map(JsonValue::Arr),
-            Some('{') => self.parse_object().map(JsonValue::Obj),
-            Some(c) => Err(DeserializeError::InvalidJson(format!(
-                "Unexpected character: {}",
-                c
-            ))),
-            None => Err(DeserializeError::InvalidJson(
-                "Unexpected end of input".to_string(),
-            )),
-        }
-    }
-
-    // String parsing according to RFC 8259
-    fn parse_string(&mut self) -> Result<String, DeserializeError> {
-        self.expect_char('"')?;
-        let mut result = String::new();
-        let mut is_escaped = false;
-    
-        while let Some(c) = self.next_char() {
-            match (is_escaped, c) {
-                (true, '"') => {
-                    result.push('"');
-                    is_escaped = false;
-                }
-                (true, '\\') => {
-                    result.push('\\');
-                    is_escaped = false;
-                }
-                (true, '/') => {
-                    result.push('/');
-                    is_escaped = false;
-                }
-                (true, 'b') => {
-                    result.push('\u{0008}');
-                    is_escaped = false;
-                }
-                (true, 'f') => {
-                    result.push('\u{000C}');
-                    is_escaped = false;
-                }
-                (true, 'n') => {
-                    result.push('\n');
-                    is_escaped = false;
-                }
-                (true, 'r') => {
-                    result.push('\r');
-                    is_escaped = false;
-                }
-                (true, 't') => {
-                    result.push('\t');
-                    is_escaped = false;
-                }
-                (true, 'u') => {
-                    result.push(self.parse_unicode_escape()?);
-                    is_escaped = false;
-                }
-                (true, c) => {
-                    // RFC 8259 only allows specific escape sequences
-                    return Err(DeserializeError::InvalidJson(format!(
-                        "Invalid escape sequence: \\{}",
-                        c
-                    )));
-                }
-                (false, '"') => {
-                    return Ok(result);
-                }
-                (false, '\\') => {
-                    is_escaped = true;
-                }
-                (false, c) if c.is_ascii_control() => {
-                    // RFC 8259 prohibits unescaped control characters (U+0000 through U+001F)
-                    return Err(DeserializeError::InvalidJson(format!(
-                        "Unescaped control character (0x{:02X}) in string",
-                        c as u32
-                    )));
-                }
-                (false, c) => {
-                    result.push(c);
-                }
-            }
-        }
-
-        Err(DeserializeError::InvalidJson(
-            "Unterminated string".to_string(),
-        ))
-    }
-
-    // Number parsing
-    fn parse_number(&mut self) -> Result<JsonValue, DeserializeError> {
-        let mut number_str = String::new();
-        let mut has_decimal = false;
-        let mut has_exponent = false;
-    
-        // Handle negative numbers
-        if self.peek_char() == Some('-') {
-            number_str.push(self.next_char().unwrap());
-        }
-
-        // Parse integer part
-        match self.peek_char() {
-            Some('0') => {
-                number_str.push(self.next_char().unwrap());
-            }
-            Some('1'..='9') => {
-                while let Some(c) = self.peek_char() {
-                    if !c.is_ascii_digit() {
-                        break;
-                    }
-                    number_str.push(self.next_char().unwrap());
-                }
-            }
-            _ => {
-                return Err(DeserializeError::InvalidJson(
-                    "Invalid number format".to_string(),
-                ));
-            }
-        }
-
-        // Parse decimal part
-        if self.peek_char() == Some('.') {
-            has_decimal = true;
-            number_str.push(self.next_char().unwrap());
-
-            let mut has_digits = false;
-            while let Some(c) = self.peek_char() {
-                if !c.is_ascii_digit() {
-                    break;
-                }
-                has_digits = true;
-                number_str.push(self.next_char().unwrap());
-            }
-
-            if !has_digits {
-                return Err(DeserializeError::InvalidJson(
-                    "Expected digits after decimal point".to_string(),
-                ));
-            }
-        }
-
-        // Parse exponent
-        if let Some('e' | 'E') = self.peek_char() {
-            has_exponent = true;
-            number_str.push(self.next_char().unwrap());
-
-            // Handle exponent sign
-            match self.peek_char() {
-                Some('+' | '-') => number_str.push(self.next_char().unwrap()),
-                _ => {}
-            }
-
-            let mut has_digits = false;
-            while let Some(c) = self.peek_char() {
-                if !c.is_ascii_digit() {
-                    break;
-                }
-                has_digits = true;
-                number_str.push(self.next_char().unwrap());
-            }
-
-            if !has_digits {
-                return Err(DeserializeError::InvalidJson(
-                    "Expected digits in exponent".to_string(),
-                ));
-            }
-        }
-
-        // If it's an integer with no decimal or exponent, parse as i64
-        if !has_decimal && !has_exponent {
-            match number_str.parse::<i64>() {
-                Ok(int_value) => Ok(JsonValue::Int(int_value)),
-                Err(_) => {
-                    // If i64 parsing fails, try f64 as fallback
-                    number_str
-                        .parse::<f64>()
-                        .map(JsonValue::Float)
-                        .map_err(|_| DeserializeError::InvalidJson(format!("Invalid number: {}", number_str)))
-                }
-            }
-        } else {
-            // Parse as float for decimal or exponent numbers
-            number_str
-                .parse::<f64>()
-                .map(JsonValue::Float)
-                .map_err(|_| DeserializeError::InvalidJson(format!("Invalid number: {}", number_str)))
-        }
-    }
-
-    // Boolean parsing
-    fn parse_boolean(&mut self) -> Result<bool, DeserializeError> {
-        match self.peek_char() {
-            Some('t') => {
-                self.expect_literal("true")?;
-                Ok(true)
-            }
-            Some('f') => {
-                self.expect_literal("false")?;
-                Ok(false)
-            }
-            _ => Err(DeserializeError::InvalidJson(
-                "Expected boolean value".to_string(),
-            )),
-        }
-    }
-
-    // Null parsing
-    fn parse_null(&mut self) -> Result<(), DeserializeError> {
-        self.expect_literal("null")
+    /// This method is automatically called when using string formatting macros
+    /// like `format!`, `println!`, etc. with a JsonValue.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Infinities and NaN are written as `null`, so only the formatter can fail
+        let options = SerializeOptions { non_finite: NonFinitePolicy::Null };
+        serialize::write_value(f, self, &options).map_err(|_| fmt::Error)
     }
+}
 
-    // Array parsing
-    fn parse_array(&mut self) -> Result<Vec<JsonValue>, DeserializeError> {
-        self.expect_char('[')?;
-        let mut array = Vec::new();
-        self.skip_whitespace();
-
-        if self.peek_char() == Some(']') {
-            self.next_char();
-            return Ok(array);
-        }
-
-        loop {
-            self.skip_whitespace();
-            array.push(self.parse_value()?);
-            self.skip_whitespace();
-
-            match self.next_char() {
-                Some(',') => continue,
-                Some(']') => break,
-                Some(c) => {
-                    return Err(DeserializeError::InvalidJson(format!(
-                        "Expected ',' or ']', found '{}'",
-                        c
-                    )));
-                }
-                None => {
-                    return Err(DeserializeError::InvalidJson(
-                        "Unterminated array".to_string(),
-                    ));
-                }
-            }
-        }
-
-        Ok(array)
-    }
+/// Copies nested arrays and objects one at a time rather than recursively,
+/// as their containers do when dropping them, so that values nested to any
+/// depth can be cloned.
+impl<B: MapBackend> Clone for GenericJsonValue<B> {
+    fn clone(&self) -> Self {
+        // The containers being copied, with the members left to copy and
+        // those already copied, are kept on an explicit stack
+        let mut stack: Vec<Cloning<'_, B>> = Vec::new();
+        let mut next = self;
 
-    // Object parsing
-    fn parse_object(&mut self) -> Result<HashMap<String, JsonValue>, DeserializeError> {
-        self.expect_char('{')?;
-        let mut object = HashMap::new();
-        self.skip_whitespace();
-    
-        if self.peek_char() == Some('}') {
-            self.next_char();
-            return Ok(object);
-        }
-    
         loop {
-            self.skip_whitespace();
-            let key = self.parse_string()?;
-            self.skip_whitespace();
-    
-            // Check for duplicate keys - RFC 8259 recommends implementations
-            // should either prevent or report duplicate names
-            if object.contains_key(&key) {
-                return Err(DeserializeError::InvalidJson(format!(
-                    "Duplicate key '{}' in object",
-                    key
-                )));
-            }
-
-            self.expect_char(':')?;
-            self.skip_whitespace();
-
-            let value = self.parse_value()?;
-            object.insert(key, value);
-            self.skip_whitespace();
-
-            match self.next_char() {
-                Some(',') => continue,
-                Some('}') => break,
-                Some(c) => {
-                    return Err(DeserializeError::InvalidJson(format!(
-                        "Expected ',' or '}}', found '{}'",
-                        c
-                    )));
+            let mut value = match next {
+                GenericJsonValue::Int(n) => Some(GenericJsonValue::Int(*n)),
+                GenericJsonValue::UInt(n) => Some(GenericJsonValue::UInt(*n)),
+                GenericJsonValue::Int128(n) => Some(GenericJsonValue::Int128(*n)),
+                GenericJsonValue::UInt128(n) => Some(GenericJsonValue::UInt128(*n)),
+                GenericJsonValue::Float(n) => Some(GenericJsonValue::Float(*n)),
+                GenericJsonValue::Number(n) => Some(GenericJsonValue::Number(n.clone())),
+                #[cfg(feature = "decimal")]
+                GenericJsonValue::Decimal(n) => Some(GenericJsonValue::Decimal(n.clone())),
+                GenericJsonValue::Bool(b) => Some(GenericJsonValue::Bool(*b)),
+                GenericJsonValue::Str(s) => Some(GenericJsonValue::Str(s.clone())),
+                GenericJsonValue::Arr(items) => {
+                    stack.push(Cloning::Arr(items.iter(), Vec::with_capacity(items.len())));
+                    None
                 }
-                None => {
-                    return Err(DeserializeError::InvalidJson(
-                        "Unterminated object".to_string(),
-                    ));
+                GenericJsonValue::Obj(map) => {
+                    stack.push(Cloning::Obj(map.iter(), B::Map::default(), String::new()));
+                    None
                 }
-            }
-        }
-
-        Ok(object)
-    }
-
-    // Helper methods
-    fn parse_four_hex_digits(&mut self) -> Result<u32, DeserializeError> {
-        let mut code_point = 0u32;
-        for _ in 0..4 {
-            code_point = code_point * 16
-                + match self.next_char() {
-                    Some(c) => c.to_digit(16).ok_or_else(|| {
-                        DeserializeError::InvalidJson(format!(
-                            "Invalid Unicode escape sequence: {}",
-                            c
-                        ))
-                    })?,
-                    None => {
-                        return Err(DeserializeError::InvalidJson(
-                            "Unexpected end of Unicode escape sequence".to_string(),
-                        ));
-                    }
+                GenericJsonValue::Null => Some(GenericJsonValue::Null),
+            };
+
+            // Store the copy in its container and move on to the next value,
+            // completing the containers that have run out
+            next = loop {
+                let Some(level) = stack.last_mut() else {
+                    // Only reached once the root value has been copied
+                    return value.unwrap_or(GenericJsonValue::Null);
                 };
-        }
-        Ok(code_point)
-    }
-    
-    fn parse_unicode_escape(&mut self) -> Result<char, DeserializeError> {
-        let code_point = self.parse_four_hex_digits()?;
-        
-        // Handle surrogate pairs according to RFC 8259
-        if (0xD800..=0xDBFF).contains(&code_point) {
-            // High surrogate, must be followed by low surrogate
-            if self.peek_char() == Some('\\') {
-                self.next_char(); // Consume the backslash
-                if self.peek_char() == Some('u') {
-                    self.next_char(); // Consume the 'u'
-                    let low_surrogate = self.parse_four_hex_digits()?;
-                    return if (0xDC00..=0xDFFF).contains(&low_surrogate) {
-                        // Calculate the combined code point
-                        let combined = 0x10000 + (((code_point - 0xD800) << 10) | (low_surrogate - 0xDC00));
-                        char::from_u32(combined).ok_or_else(|| {
-                            DeserializeError::InvalidJson(format!("Invalid Unicode surrogate pair: U+{:04X} U+{:04X}", code_point, low_surrogate))
-                        })
-                    } else {
-                        Err(DeserializeError::InvalidJson(
-                            format!("Invalid low surrogate in Unicode surrogate pair: U+{:04X}", low_surrogate)
-                        ))
+                let done = match level {
+                    Cloning::Arr(rest, copied) => {
+                        copied.extend(value.take());
+                        match rest.next() {
+                            Some(val) => break val,
+                            None => GenericJsonValue::Arr(std::mem::take(copied).into()),
+                        }
                     }
-                }
-            }
-            return Err(DeserializeError::InvalidJson(
-                format!("High surrogate U+{:04X} not followed by low surrogate", code_point)
-            ));
-        } else if (0xDC00..=0xDFFF).contains(&code_point) {
-            return Err(DeserializeError::InvalidJson(
-                format!("Unexpected low surrogate: U+{:04X}", code_point)
-            ));
-        }
-        
-        char::from_u32(code_point).ok_or_else(|| {
-            DeserializeError::InvalidJson(format!("Invalid Unicode code point: U+{:04X}", code_point))
-        })
-    }
-
-    fn expect_char(&mut self, expected: char) -> Result<(), DeserializeError> {
-        match self.next_char() {
-            Some(c) if c == expected => Ok(()),
-            Some(c) => Err(DeserializeError::InvalidJson(format!(
-                "Expected '{}', found '{}'",
-                expected, c
-            ))),
-            None => Err(DeserializeError::InvalidJson(format!(
-                "Expected '{}', found end of input",
-                expected
-            ))),
-        }
-    }
-
-    fn expect_literal(&mut self, literal: &str) -> Result<(), DeserializeError> {
-        for expected in literal.chars() {
-            match self.next_char() {
-                Some(c) if c == expected => continue,
-                Some(c) => {
-                    return Err(DeserializeError::InvalidJson(format!(
-                        "Expected '{}', found '{}'",
-                        expected, c
-                    )));
-                }
-                None => {
-                    return Err(DeserializeError::InvalidJson(format!(
-                        "Expected '{}', found end of input",
-                        expected
-                    )));
-                }
-            }
+                    Cloning::Obj(rest, copied, key) => {
+                        if let Some(val) = value.take() {
+                            copied.insert(std::mem::take(key), val);
+                        }
+                        match rest.next() {
+                            Some((k, val)) => {
+                                key.clone_from(k);
+                                break val;
+                            }
+                            None => GenericJsonValue::Obj(JsonObject::from_map(std::mem::take(copied))),
+                        }
+                    }
+                };
+                value = Some(done);
+                stack.pop();
+            };
         }
-        Ok(())
     }
+}
 
-    fn peek_char(&self) -> Option<char> {
-        self.input.get(self.position).copied()
-    }
+/// A container that `clone` is copying: the members still to copy, those
+/// copied so far and, for objects, the key of the current member.
+enum Cloning<'v, B: MapBackend> {
+    Arr(std::slice::Iter<'v, GenericJsonValue<B>>, Vec<GenericJsonValue<B>>),
+    Obj(
+        <B::Map<GenericJsonValue<B>> as ObjectMap<String, GenericJsonValue<B>>>::Iter<'v>,
+        B::Map<GenericJsonValue<B>>,
+        String,
+    ),
+}
 
-    fn next_char(&mut self) -> Option<char> {
-        let c = self.peek_char();
-        if c.is_some() {
-            self.position += 1;
-        }
-        c
-    }
-
-    fn skip_whitespace(&mut self) {
-        while let Some(c) = self.peek_char() {
-            if !c.is_whitespace() {
-                break;
-            }
-            self.position += 1;
-        }
+/// The default value is `Null`.
+impl<B: MapBackend> Default for GenericJsonValue<B> {
+    fn default() -> Self {
+        GenericJsonValue::Null
     }
 }
 
@@ -679,108 +322,6 @@ impl JsonParser {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_parse_string() {
-        let mut parser = JsonParser::new(r#""Hello, World!""#.to_string());
-        assert_eq!(parser.parse_string().unwrap(), "Hello, World!");
-    }
-
-    #[test]
-    fn test_parse_number() {
-        // Test integer parsing
-        let mut parser = JsonParser::new("123".to_string());
-        match parser.parse_number().unwrap() {
-            JsonValue::Int(n) => assert_eq!(n, 123),
-            _ => panic!("Expected JsonValue::Int"),
-        }
-
-        // Test float parsing
-        let mut parser = JsonParser::new("123.456".to_string());
-        match parser.parse_number().unwrap() {
-            JsonValue::Float(n) => assert_eq!(n, 123.456),
-            _ => panic!("Expected JsonValue::Float"),
-        }
-
-        // Test negative float with exponent
-        let mut parser = JsonParser::new("-123.456e-10".to_string());
-        match parser.parse_number().unwrap() {
-            JsonValue::Float(n) => assert_eq!(n, -123.456e-10),
-            _ => panic!("Expected JsonValue::Float"),
-        }
-
-        // Test integer with exponent (should be a float)
-        let mut parser = JsonParser::new("123e2".to_string());
-        match parser.parse_number().unwrap() {
-            JsonValue::Float(n) => assert_eq!(n, 12300f64),
-            _ => panic!("Expected JsonValue::Float"),
-        }
-    }
-
-    #[test]
-    fn test_parse_boolean() {
-        let mut parser = JsonParser::new("true".to_string());
-        assert_eq!(parser.parse_boolean().unwrap(), true);
-
-        let mut parser = JsonParser::new("false".to_string());
-        assert_eq!(parser.parse_boolean().unwrap(), false);
-    }
-
-    #[test]
-    fn test_parse_null() {
-        let mut parser = JsonParser::new("null".to_string());
-        assert!(parser.parse_null().is_ok());
-    }
-
-    #[test]
-    fn test_parse_array() {
-        let mut parser = JsonParser::new("[1, 2, 3]".to_string());
-        let array = parser.parse_array().unwrap();
-        assert_eq!(array.len(), 3);
-    }
-
-    #[test]
-    fn test_parse_object() {
-        let mut parser = JsonParser::new(r#"{"name": "John", "age": 30}"#.to_string());
-        let object = parser.parse_object().unwrap();
-        assert_eq!(object.len(), 2);
-    }
-
-    #[test]
-    fn test_duplicate_keys() {
-        let mut parser = JsonParser::new(r#"{"name": "John", "name": "Jane"}"#.to_string());
-        let result = parser.parse();
-        assert!(result.is_err(), "Parsing duplicate keys should fail");
-        if let Err(e) = result {
-            assert!(e.to_string().contains("Duplicate key"));
-        }
-    }
-
-    #[test]
-    fn test_unicode_escape_sequences() {
-        // Basic Unicode escape
-        let mut parser = JsonParser::new(r#""\u0041\u0042\u0043""#.to_string());
-        assert_eq!(parser.parse_string().unwrap(), "ABC");
-
-        // Surrogate pair (represents 😀 emoji, U+1F600)
-        let mut parser = JsonParser::new(r#""\uD83D\uDE00""#.to_string());
-        assert_eq!(parser.parse_string().unwrap(), "😀");
-    }
-
-    #[test]
-    fn test_invalid_surrogate_pairs() {
-        // High surrogate without low surrogate
-        let mut parser = JsonParser::new(r#""\uD83D""#.to_string());
-        assert!(parser.parse_string().is_err());
-
-        // Low surrogate without high surrogate
-        let mut parser = JsonParser::new(r#""\uDE00""#.to_string());
-        assert!(parser.parse_string().is_err());
-
-        // High surrogate followed by something that's not a low surrogate
-        let mut parser = JsonParser::new(r#""\uD83Dz""#.to_string());
-        assert!(parser.parse_string().is_err());
-    }
-
     #[test]
     fn test_string_escaping_display() {
         // Test basic escaping
@@ -793,66 +334,154 @@ mod tests {
     }
 
     #[test]
-    fn test_complex_json() {
-        let json = r#"
-        {
-            "name": "John Doe",
-            "age": 30,
-            "is_student": false,
-            "grades": [85, 90, 92],
-            "address": {
-                "street": "123 Main St",
-                "city": "Anytown"
-            },
-            "phone": null,
-            "score": 98.6,
-            "exp": 1.23e4,
-            "credit_score": -120,
-            "total_bank_balance": -20.012
-        }"#;
+    fn test_deeply_nested_values() {
+        // Parsing, printing and dropping all work without recursion
+        const DEPTH: usize = 1_000_000;
+
+        let input = "[".repeat(DEPTH) + &"]".repeat(DEPTH);
+        let value = JsonParser::from_str(&input).parse().unwrap();
+        assert_eq!(value.to_string(), input);
+        drop(value);
+
+        // Objects, and borrowed values, take the same paths
+        let input = r#"{"a":"#.repeat(DEPTH / 10) + "[1,{}]" + &"}".repeat(DEPTH / 10);
+        let value = JsonParser::from_str(&input).parse_borrowed().unwrap();
+        assert_eq!(value.to_string(), input);
+        let value = value.into_owned();
+        assert_eq!(value.to_string(), input);
+    }
+
+    #[test]
+    fn test_move_payloads_out() {
+        // The enums have no `Drop`, so a `match` can move any payload out
+        let value = JsonParser::from_str(r#"["a", [1, 2], {"k": null}]"#).parse().unwrap();
+        let JsonValue::Arr(items) = value else {
+            panic!("Expected JsonValue::Arr");
+        };
+        let mut moved = Vec::new();
+        for item in items {
+            match item {
+                JsonValue::Str(s) => moved.push(s),
+                JsonValue::Arr(items) => moved.push(JsonValue::Arr(items).to_string()),
+                JsonValue::Obj(map) => moved.extend(map.into_iter().map(|(key, _)| key)),
+                other => panic!("Unexpected value {:?}", other),
+            }
+        }
+        assert_eq!(moved, ["a", "[1,2]", "k"]);
+
+        let value = JsonParser::from_str(r#"{"s": "b", "a": [true], "o": {}}"#).parse_borrowed().unwrap();
+        let JsonValueRef::Obj(map) = value else {
+            panic!("Expected JsonValueRef::Obj");
+        };
+        for (key, val) in map {
+            match (key.as_ref(), val) {
+                ("s", JsonValueRef::Str(s)) => assert_eq!(s, "b"),
+                ("a", JsonValueRef::Arr(items)) => assert!(matches!(items.into_vec()[..], [JsonValueRef::Bool(true)])),
+                ("o", JsonValueRef::Obj(map)) => assert!(map.into_map().is_empty()),
+                _ => panic!("Unexpected member {:?}", key),
+            }
+        }
 
-        let mut parser = JsonParser::new(json.to_string());
-        let result = parser.parse();
-        assert!(result.is_ok());
+        // A container moved out still drops without recursing
+        const DEPTH: usize = 1_000_000;
+        let input = "[".repeat(DEPTH) + &"]".repeat(DEPTH);
+        if let JsonValue::Arr(items) = JsonParser::from_str(&input).parse().unwrap() {
+            drop(items.into_vec());
+        }
+    }
 
-        // Verify that the parsed result contains correctly typed numbers
-        if let Ok(JsonValue::Obj(obj)) = result {
-            // Verify integer
-            if let Some(JsonValue::Int(age)) = obj.get("age") {
-                assert_eq!(*age, 30);
-            } else {
-                panic!("Expected age to be an integer");
+    #[test]
+    fn test_display_round_trip_property() {
+        // A xorshift generator, so the test is repeatable
+        let mut state = 0x853C_49E6_748F_EA9Bu64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        // Builds a random number literal, with any sign, fraction and exponent
+        fn literal(next: &mut dyn FnMut() -> u64) -> String {
+            let mut literal = ((next() as i64) >> (next() % 64)).to_string();
+            if next().is_multiple_of(2) {
+                literal += &format!(".{:03}", next() % 1000);
+            }
+            if next().is_multiple_of(3) {
+                literal += &format!("e{}", next() as i8);
             }
+            literal
+        }
 
-            // Verify float
-            if let Some(JsonValue::Float(score)) = obj.get("score") {
-                assert_eq!(*score, 98.6);
-            } else {
-                panic!("Expected score to be a float");
+        // Builds a random number of a kind the number mode parses to
+        fn number(next: &mut dyn FnMut() -> u64, mode: NumberMode) -> JsonValue {
+            match mode {
+                NumberMode::Lossless => JsonValue::Number(literal(next).parse().unwrap()),
+                #[cfg(feature = "decimal")]
+                NumberMode::Decimal => JsonValue::Decimal(literal(next).parse().unwrap()),
+                _ => match next() % 6 {
+                    0 => JsonValue::Int(next() as i64),
+                    1 => JsonValue::UInt(next() | 1 << 63),
+                    2 => JsonValue::Int128(-(1 << 64) - i128::from(next())),
+                    3 => JsonValue::UInt128(u128::from(next()) << 64 | u128::from(next()) | 1 << 127),
+                    // Any finite float; JSON cannot hold infinities or NaN
+                    4 => JsonValue::Float(Some(f64::from_bits(next())).filter(|n| n.is_finite()).unwrap_or(0.5)),
+                    _ => JsonValue::Float((next() % 2000) as f64 - 1000.0),
+                },
             }
+        }
 
-            // Verify exponent
-            if let Some(JsonValue::Float(exp)) = obj.get("exp") {
-                assert_eq!(*exp, 12300.0);
-            } else {
-                panic!("Expected exp to be a float");
+        // Builds a random value, with containers only above the given depth
+        fn random(next: &mut dyn FnMut() -> u64, mode: NumberMode, depth: u32) -> JsonValue {
+            let kinds = if depth == 0 { 8 } else { 10 };
+            match next() % kinds {
+                0..5 => number(next, mode),
+                5 => JsonValue::Bool(next().is_multiple_of(2)),
+                6 => JsonValue::Str((0..next() % 8).filter_map(|_| char::from_u32((next() % 0x800) as u32)).collect()),
+                7 => JsonValue::Null,
+                8 => JsonValue::Arr((0..next() % 5).map(|_| random(next, mode, depth - 1)).collect()),
+                _ => JsonValue::Obj((0..next() % 5).map(|i| (i.to_string(), random(next, mode, depth - 1))).collect()),
             }
+        }
 
-            // Verify negative int
-            if let Some(JsonValue::Int(negative_num)) = obj.get("credit_score") {
-                assert_eq!(*negative_num, -120);
-            } else {
-                panic!("Expected credit_score to be a -ve integer");
+        // Whether two values are the same, variant for variant, with floats
+        // compared bit for bit and lossless numbers digit for digit
+        fn same(a: &JsonValue, b: &JsonValue) -> bool {
+            match (a, b) {
+                (JsonValue::Int(a), JsonValue::Int(b)) => a == b,
+                (JsonValue::UInt(a), JsonValue::UInt(b)) => a == b,
+                (JsonValue::Int128(a), JsonValue::Int128(b)) => a == b,
+                (JsonValue::UInt128(a), JsonValue::UInt128(b)) => a == b,
+                (JsonValue::Float(a), JsonValue::Float(b)) => a.to_bits() == b.to_bits(),
+                (JsonValue::Number(a), JsonValue::Number(b)) => a.as_str() == b.as_str(),
+                #[cfg(feature = "decimal")]
+                (JsonValue::Decimal(a), JsonValue::Decimal(b)) => a == b,
+                (JsonValue::Bool(a), JsonValue::Bool(b)) => a == b,
+                (JsonValue::Str(a), JsonValue::Str(b)) => a == b,
+                (JsonValue::Arr(a), JsonValue::Arr(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b)),
+                (JsonValue::Obj(a), JsonValue::Obj(b)) => {
+                    a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| same(a, b)))
+                }
+                (JsonValue::Null, JsonValue::Null) => true,
+                _ => false,
             }
+        }
 
-            // Verify negative float
-            if let Some(JsonValue::Float(negative_num)) = obj.get("total_bank_balance") {
-                assert_eq!(*negative_num, -20.012);
-            } else {
-                panic!("Expected total_bank_balance to be a -ve float");
+        // Each number mode parses the numbers it is given back to the same kind
+        let modes = [
+            NumberMode::IntOrFloat,
+            NumberMode::Lossless,
+            #[cfg(feature = "decimal")]
+            NumberMode::Decimal,
+        ];
+        for mode in modes {
+            let options = ParserOptions { number_mode: mode, ..ParserOptions::default() };
+            for _ in 0..5_000 {
+                let value = random(&mut next, mode, 3);
+                let text = value.to_string();
+                let parsed = JsonParser::from_str(&text).with_options(options.clone()).parse().unwrap();
+                assert!(same(&parsed, &value), "text: {}", text);
             }
-        } else {
-            panic!("Failed to parse complex JSON");
         }
     }
 }
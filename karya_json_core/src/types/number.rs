@@ -0,0 +1,243 @@
+//! Numbers kept exactly as they were written.
+//!
+//! `JsonNumber` holds the literal text of a number rather than a converted
+//! `i64` or `f64`, so integers of any size, negative zero and trailing zeros in
+//! a fraction all survive a parse and print unchanged. Conversions to Rust
+//! numeric types happen only when asked for.
+
+use crate::types::error::{DeserializeError, ErrorCode};
+use crate::types::{JsonEvent, JsonReader, float};
+use std::fmt;
+use std::str::FromStr;
+
+/// A JSON number, stored as the text of its literal.
+///
+/// `JsonParser` produces `JsonValue::Number` in place of `Int` and `Float`
+/// when `ParserOptions::number_mode` is `NumberMode::Lossless`. Printing the
+/// value writes the literal back out byte for byte.
+///
+/// Equality compares the literals, so `1.0` and `1.00` are different numbers.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{JsonParser, JsonValue, NumberMode, ParserOptions};
+///
+/// let options = ParserOptions { number_mode: NumberMode::Lossless, ..ParserOptions::default() };
+/// let input = r#"{"id": 18446744073709551615, "price": 1.10}"#;
+/// let value = JsonParser::from_str(input).with_options(options).parse().unwrap();
+///
+/// if let JsonValue::Obj(map) = &value {
+///     if let Some(JsonValue::Number(id)) = map.get("id") {
+///         assert_eq!(id.as_u64(), Some(u64::MAX));
+///         assert_eq!(id.as_i64(), None);
+///     }
+///     if let Some(JsonValue::Number(price)) = map.get("price") {
+///         assert_eq!(price.as_str(), "1.10");
+///         assert_eq!(price.as_f64(), 1.1);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JsonNumber {
+    /// The literal, which follows the JSON number grammar, or is `Infinity`,
+    /// `-Infinity` or `NaN` when parsed from JSON5
+    literal: String,
+}
+
+impl JsonNumber {
+    // Wraps a literal the reader has already validated
+    pub(crate) fn from_literal(literal: String) -> Self {
+        Self { literal }
+    }
+
+    /// Returns the literal text of the number.
+    pub fn as_str(&self) -> &str {
+        &self.literal
+    }
+
+    /// Whether the literal is an integer, with no fraction or exponent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::JsonNumber;
+    ///
+    /// assert!("-12".parse::<JsonNumber>().unwrap().is_integer());
+    /// assert!(!"1.0".parse::<JsonNumber>().unwrap().is_integer());
+    /// assert!(!"1e3".parse::<JsonNumber>().unwrap().is_integer());
+    /// ```
+    pub fn is_integer(&self) -> bool {
+        self.literal.bytes().all(|b| b == b'-' || b.is_ascii_digit())
+    }
+
+    /// Returns the number as an `i64`, if it is an integer in range.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.integer()
+    }
+
+    /// Returns the number as a `u64`, if it is a non-negative integer in range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::JsonNumber;
+    ///
+    /// let id: JsonNumber = "18446744073709551615".parse().unwrap();
+    /// assert_eq!(id.as_u64(), Some(u64::MAX));
+    /// assert_eq!("-1".parse::<JsonNumber>().unwrap().as_u64(), None);
+    /// ```
+    pub fn as_u64(&self) -> Option<u64> {
+        self.integer()
+    }
+
+    /// Returns the number as an `i128`, if it is an integer in range.
+    pub fn as_i128(&self) -> Option<i128> {
+        self.integer()
+    }
+
+    /// Returns the number as a `u128`, if it is a non-negative integer in range.
+    pub fn as_u128(&self) -> Option<u128> {
+        self.integer()
+    }
+
+    /// Returns the number as the nearest `f64`.
+    ///
+    /// Every number has one, though it may be infinite for a literal beyond
+    /// the range of `f64`, or zero for one too small for it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::JsonNumber;
+    ///
+    /// assert_eq!("2.5e-3".parse::<JsonNumber>().unwrap().as_f64(), 0.0025);
+    /// assert_eq!("1e400".parse::<JsonNumber>().unwrap().as_f64(), f64::INFINITY);
+    /// ```
+    pub fn as_f64(&self) -> f64 {
+        float::parse_f64(&self.literal).unwrap_or(f64::NAN)
+    }
+
+    // Whether the number is finite, which all but JSON5's `Infinity`,
+    // `-Infinity` and `NaN` are
+    pub(crate) fn is_finite(&self) -> bool {
+        !matches!(self.literal.as_str(), "Infinity" | "-Infinity" | "NaN")
+    }
+
+    // Converts an integer literal, treating `-0` as zero for every type
+    fn integer<T: FromStr + Default>(&self) -> Option<T> {
+        if !self.is_integer() {
+            return None;
+        }
+        match self.literal.as_str() {
+            "-0" => Some(T::default()),
+            literal => literal.parse().ok(),
+        }
+    }
+}
+
+impl Default for JsonNumber {
+    /// Returns zero.
+    fn default() -> Self {
+        Self::from_literal(String::from("0"))
+    }
+}
+
+impl fmt::Display for JsonNumber {
+    /// Writes the literal exactly as it was parsed.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.literal)
+    }
+}
+
+impl FromStr for JsonNumber {
+    type Err = DeserializeError;
+
+    /// Parses a number literal, which must follow the JSON number grammar
+    /// with nothing around it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::JsonNumber;
+    ///
+    /// let amount: JsonNumber = "-0.50".parse().unwrap();
+    /// assert_eq!(amount.to_string(), "-0.50");
+    ///
+    /// assert!("01".parse::<JsonNumber>().is_err());
+    /// assert!(" 1".parse::<JsonNumber>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut reader = JsonReader::from_str(s);
+        match reader.next_event()? {
+            Some(JsonEvent::Number(literal)) if literal.len() == s.len() && reader.next_event()?.is_none() => {
+                Ok(Self::from_literal(literal.into_owned()))
+            }
+            _ => Err(reader.token_error(ErrorCode::InvalidNumber)),
+        }
+    }
+}
+
+impl From<i64> for JsonNumber {
+    fn from(n: i64) -> Self {
+        Self::from_literal(n.to_string())
+    }
+}
+
+impl From<u64> for JsonNumber {
+    fn from(n: u64) -> Self {
+        Self::from_literal(n.to_string())
+    }
+}
+
+impl From<i128> for JsonNumber {
+    fn from(n: i128) -> Self {
+        Self::from_literal(n.to_string())
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(literal: &str) -> JsonNumber {
+        literal.parse().unwrap()
+    }
+
+    #[test]
+    fn test_integer_accessors() {
+        let big = number("170141183460469231731687303715884105727");
+        assert_eq!(big.as_i128(), Some(i128::MAX));
+        assert_eq!(big.as_u64(), None);
+        assert_eq!(big.as_i64(), None);
+
+        let negative = number("-9223372036854775808");
+        assert_eq!(negative.as_i64(), Some(i64::MIN));
+        assert_eq!(negative.as_u64(), None);
+
+        // Negative zero is zero for every integer type
+        assert_eq!(number("-0").as_u64(), Some(0));
+        assert_eq!(number("-0").as_f64().to_bits(), (-0.0f64).to_bits());
+
+        // Fractions and exponents are never integers, even when whole
+        assert_eq!(number("1.0").as_i64(), None);
+        assert_eq!(number("1e2").as_u64(), None);
+        assert_eq!(number("1e2").as_f64(), 100.0);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for literal in ["0", "-0", "1.10", "1E+2", "-0.0e-0", "12345678901234567890123"] {
+            assert_eq!(number(literal).to_string(), literal);
+            assert_eq!(number(literal).as_str(), literal);
+        }
+        assert_ne!(number("1.0"), number("1.00"));
+        assert_eq!(JsonNumber::from(u64::MAX), number("18446744073709551615"));
+        assert_eq!(JsonNumber::from(-5i64).as_i64(), Some(-5));
+
+        for invalid in ["", "-", "1.", ".5", "+1", "0x10", "1 ", "[1]", "\"1\"", "NaN"] {
+            assert!(invalid.parse::<JsonNumber>().is_err(), "input: {:?}", invalid);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Options controlling how JSON is parsed.
+//!
+//! `ParserOptions` is shared by `JsonParser`, `JsonReader` and
+//! `IncrementalParser`. Its resource limits bound how much work and memory a
+//! single document can cost, which matters when parsing untrusted input, and
+//! its switches relax strict RFC 8259 parsing where that is wanted, up to
+//! accepting a different `Dialect` altogether.
+
+/// Options for `JsonParser`, `JsonReader` and `IncrementalParser`.
+///
+/// Each limit is an `Option`, with `None` meaning no limit. Nothing is
+/// limited by default; set the limits when parsing input from untrusted
+/// sources. A document that exceeds a limit is rejected with a syntax
+/// error carrying a dedicated `ErrorCode`, located where the limit was crossed,
+/// and parsing stops there without reading the rest of the input.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{JsonParser, ParserOptions};
+/// use karya_json::types::error::ErrorCode;
+///
+/// let options = ParserOptions {
+///     max_depth: Some(2),
+///     max_string_length: Some(16),
+///     ..ParserOptions::default()
+/// };
+///
+/// let error = JsonParser::from_str("[[[1]]]").with_options(options.clone()).parse().unwrap_err();
+/// assert_eq!(error.code(), Some(&ErrorCode::DepthLimitExceeded(2)));
+///
+/// let error = JsonParser::from_str(r#"["a very long string indeed"]"#)
+///     .with_options(options)
+///     .parse()
+///     .unwrap_err();
+/// assert_eq!(error.code(), Some(&ErrorCode::StringTooLong(16)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// The deepest that arrays and objects may be nested. The root container
+    /// is at depth 1. Unlimited by default.
+    ///
+    /// Parsing, printing and dropping values never recurse, so without this
+    /// limit depth is bounded only by memory, and deeply nested documents are
+    /// not an error.
+    pub max_depth: Option<usize>,
+
+    /// The longest a string or object key may be, in bytes of UTF-8 after
+    /// escape sequences are decoded.
+    pub max_string_length: Option<usize>,
+
+    /// The most elements a single array may have.
+    pub max_array_length: Option<usize>,
+
+    /// The most members a single object may have.
+    pub max_object_length: Option<usize>,
+
+    /// The longest a number literal may be, in bytes. Very long literals are
+    /// costly to convert, and are mostly of use to denial-of-service attacks.
+    pub max_number_length: Option<usize>,
+
+    /// The most input that will be read, in bytes, including whitespace.
+    pub max_input_length: Option<usize>,
+
+    /// Whether to accept any Unicode whitespace between tokens, such as the
+    /// vertical tab, form feed, U+00A0 NO-BREAK SPACE and U+2028 LINE
+    /// SEPARATOR. By default only the space, tab, line feed and carriage
+    /// return that RFC 8259 allows are accepted.
+    pub lenient_whitespace: bool,
+
+    /// The variant of JSON to accept. Defaults to strict RFC 8259 JSON.
+    pub dialect: Dialect,
+
+    /// What to do when an object has the same key more than once. Defaults
+    /// to rejecting the document.
+    pub duplicate_keys: DuplicateKeyPolicy,
+
+    /// How numbers are represented in parsed values. Defaults to converting
+    /// them to `Int` or `Float`.
+    pub number_mode: NumberMode,
+
+    /// Whether to reject a number too large for an `f64`, such as `1e400`,
+    /// with `ErrorCode::NumberOutOfRange`. By default it becomes an infinite
+    /// `Float`, which `Display` writes as `null`. JSON5's `Infinity` is not
+    /// rejected, and numbers kept exactly by `NumberMode::Lossless` never
+    /// overflow.
+    pub reject_number_overflow: bool,
+}
+
+/// How `JsonParser` represents the numbers in the values it builds.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{JsonParser, JsonValue, NumberMode, ParserOptions};
+///
+/// let value = JsonParser::from_str("[-0, 1.10]").parse().unwrap();
+/// assert_eq!(value.to_string(), "[0,1.1]");
+///
+/// let options = ParserOptions { number_mode: NumberMode::Lossless, ..ParserOptions::default() };
+/// let value = JsonParser::from_str("[-0, 1.10]").with_options(options).parse().unwrap();
+/// assert_eq!(value.to_string(), "[-0,1.10]");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum NumberMode {
+    /// Integers become `Int`, or `UInt`, `Int128` or `UInt128` if they don't
+    /// fit in an `i64`, and every other number becomes `Float`, which may
+    /// lose precision
+    #[default]
+    IntOrFloat,
+    /// Every number becomes a `JsonNumber` holding its literal, so none is
+    /// ever rounded and each prints exactly as it was written
+    Lossless,
+    /// Every number becomes an exact `Decimal`, converted from its digits
+    /// without passing through `f64`. JSON5's `Infinity` and `NaN` have no
+    /// decimal form and become `Float`.
+    #[cfg(feature = "decimal")]
+    Decimal,
+}
+
+/// What the parser does with an object that repeats a key.
+///
+/// RFC 8259 says the keys of an object SHOULD be unique, but leaves the
+/// handling of duplicates to the implementation. Every policy but `Error`
+/// accepts the document and records a `ParseWarning` for each repeated key,
+/// available from `JsonParser::warnings` once parsing is done.
+///
+/// Only `JsonParser` and `IncrementalParser` apply the policy. `JsonReader`
+/// and `JsonParser::parse_with` report every member as it comes.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{DuplicateKeyPolicy, JsonParser, JsonValue, ParserOptions};
+///
+/// let options = ParserOptions { duplicate_keys: DuplicateKeyPolicy::LastWins, ..ParserOptions::default() };
+/// let mut parser = JsonParser::from_str(r#"{"id": 1, "id": 2}"#).with_options(options);
+/// let value = parser.parse().unwrap();
+///
+/// if let JsonValue::Obj(map) = &value {
+///     assert!(matches!(map.get("id"), Some(JsonValue::Int(2))));
+/// }
+/// assert_eq!(parser.warnings()[0].to_string(), "Duplicate key 'id' in object, keeping the last value at line 1, column 11 in /id");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum DuplicateKeyPolicy {
+    /// Reject the document with `ErrorCode::DuplicateKey`
+    #[default]
+    Error,
+    /// Keep the value of the first member with the key
+    FirstWins,
+    /// Keep the value of the last member with the key
+    LastWins,
+    /// Keep every value: a repeated key maps to an array of all its values,
+    /// in the order they appear. A key that is not repeated maps to its
+    /// value as usual, so only the warnings tell the two kinds of array
+    /// apart.
+    CollectAll,
+}
+
+/// The variants of JSON the parser understands.
+///
+/// New dialects may be added in future releases, so matches on this enum need
+/// a wildcard arm.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{Dialect, JsonParser, JsonValue, ParserOptions};
+///
+/// let config = "{
+///     // Hand-written configuration
+///     name: 'server',
+///     port: 0x1F90,
+///     ratio: .5,
+/// }";
+///
+/// let options = ParserOptions { dialect: Dialect::Json5, ..ParserOptions::default() };
+/// let value = JsonParser::from_str(config).with_options(options).parse().unwrap();
+///
+/// if let JsonValue::Obj(map) = &value {
+///     assert!(matches!(map.get("port"), Some(JsonValue::Int(8080))));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Dialect {
+    /// Strict JSON, as specified by RFC 8259
+    #[default]
+    Json,
+
+    /// [JSON5](https://spec.json5.org), the JSON superset for hand-written
+    /// files. On top of JSON it accepts:
+    ///
+    /// - `//` and `/* */` comments, and any Unicode whitespace between tokens
+    /// - Object keys written as identifiers, without quotes
+    /// - Strings in single quotes, the escapes `\'`, `\v`, `\0` and `\xHH`,
+    ///   other characters escaped as themselves, lines continued by escaping
+    ///   the line break, and unescaped control characters other than line
+    ///   breaks
+    /// - Hexadecimal numbers, leading and trailing decimal points, a leading
+    ///   `+`, and `Infinity` and `NaN`
+    /// - A trailing comma after the last array element or object member
+    ///
+    /// Identifier keys may not contain `\u` escapes.
+    Json5,
+
+    /// JSON with comments, as used by editor settings and `tsconfig.json`.
+    /// On top of JSON it accepts `//` and `/* */` comments between tokens and
+    /// a trailing comma after the last array element or object member; strings,
+    /// numbers and whitespace follow RFC 8259 as strictly as for `Json`.
+    Jsonc,
+}
+
+impl Dialect {
+    // Whether comments may appear between tokens
+    pub(crate) fn allows_comments(self) -> bool {
+        matches!(self, Dialect::Json5 | Dialect::Jsonc)
+    }
+
+    // Whether the last element or member may be followed by a comma
+    pub(crate) fn allows_trailing_commas(self) -> bool {
+        matches!(self, Dialect::Json5 | Dialect::Jsonc)
+    }
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            max_string_length: None,
+            max_array_length: None,
+            max_object_length: None,
+            max_number_length: None,
+            max_input_length: None,
+            lenient_whitespace: false,
+            dialect: Dialect::Json,
+            duplicate_keys: DuplicateKeyPolicy::Error,
+            number_mode: NumberMode::IntOrFloat,
+            reject_number_overflow: false,
+        }
+    }
+}
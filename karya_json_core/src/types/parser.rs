@@ -0,0 +1,1384 @@
+//! The JSON parser.
+//!
+//! `JsonParser` works directly over the bytes of a borrowed `&str` or `&[u8]`.
+//! UTF-8 validation happens exactly once, when the parser is constructed, so the
+//! input is never copied or re-encoded before parsing starts.
+//!
+//! It can also read from any `std::io::Read`, in which case the input is
+//! buffered a chunk at a time and validated as it arrives.
+//!
+//! The syntax itself is handled by `JsonReader`; the parser assembles the
+//! reader's events into a value tree, using a stack of partly built containers
+//! rather than recursion.
+
+use crate::types::error::{DeserializeError, ErrorCode, ParseWarning, WarningCode};
+use crate::types::reader::{JsonEvent, JsonReader};
+use crate::types::source::{ReaderSource, Source, StrSource};
+#[cfg(feature = "decimal")]
+use crate::types::Decimal;
+use crate::types::map::ObjectMap;
+use crate::types::{DuplicateKeyPolicy, GenericJsonValue, JsonMap, JsonNumber, JsonObject, JsonValue, MapBackend, JsonValueRef, JsonVisitor, NumberMode, ParserOptions, float};
+use std::borrow::{Borrow, Cow};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::Read;
+
+/// A parser for converting JSON strings into `JsonValue` instances.
+///
+/// The `JsonParser` provides methods for parsing JSON strings according to the
+/// JSON specification. It handles all valid JSON data types and provides detailed
+/// error messages for invalid JSON.
+///
+/// The parser borrows its input rather than taking ownership of it, and works on
+/// the raw UTF-8 bytes without expanding them into characters first. Parsers
+/// created with `from_reader` read their input incrementally instead; the
+/// second type parameter names the source the parser reads from.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{JsonParser, JsonValue};
+///
+/// let json_str = r#"{"name": "Alice", "age": 30, "is_active": true}"#;
+/// let mut parser = JsonParser::from_str(json_str);
+/// let result = parser.parse();
+///
+/// assert!(result.is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct JsonParser<'a, S = StrSource<'a>> {
+    /// The reader producing the document's events
+    reader: JsonReader<'a, S>,
+    /// The warnings from the last document parsed
+    warnings: Vec<ParseWarning>,
+}
+
+impl<'a> JsonParser<'a> {
+    /// Creates a new JSON parser over the given string slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The JSON string to parse
+    ///
+    /// # Returns
+    ///
+    /// A new `JsonParser` instance borrowing the input string
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::JsonParser;
+    ///
+    /// let parser = JsonParser::from_str(r#"{"key": "value"}"#);
+    /// ```
+    // `FromStr` cannot be implemented here because the parser borrows its input.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'a str) -> Self {
+        Self {
+            reader: JsonReader::from_str(input),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Creates a new JSON parser over the given byte slice.
+    ///
+    /// The bytes are validated as UTF-8 once, up front. RFC 8259 requires JSON
+    /// exchanged between systems to be encoded as UTF-8.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The UTF-8 encoded JSON bytes to parse
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(JsonParser)` - If the input is valid UTF-8
+    /// * `Err(DeserializeError)` - If the input is not valid UTF-8
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::JsonParser;
+    ///
+    /// let parser = JsonParser::from_slice(br#"{"key": "value"}"#);
+    /// assert!(parser.is_ok());
+    ///
+    /// let parser = JsonParser::from_slice(b"\"\xFF\"");
+    /// assert!(parser.is_err());
+    /// ```
+    pub fn from_slice(input: &'a [u8]) -> Result<Self, DeserializeError> {
+        JsonReader::from_slice(input).map(|reader| Self {
+            reader,
+            warnings: Vec::new(),
+        })
+    }
+}
+
+impl<R: Read> JsonParser<'static, ReaderSource<R>> {
+    /// Creates a new JSON parser that reads its input from `reader`.
+    ///
+    /// The input is read in chunks as parsing proceeds and text that has been
+    /// parsed is released, so memory use depends on the size of the resulting
+    /// value rather than on the size of the input. The reader does not need to
+    /// be buffered. Strings are always copied out of the internal buffer, so
+    /// `parse_borrowed` returns only owned strings.
+    ///
+    /// An I/O error, or input that is not valid UTF-8, is reported in place of
+    /// whatever syntax error the early end of the input would have caused.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source of the JSON text, such as a `File` or `TcpStream`
+    ///
+    /// # Returns
+    ///
+    /// A new `JsonParser` that owns the reader
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{JsonParser, JsonValue};
+    ///
+    /// let bytes: &[u8] = br#"{"name": "Alice", "scores": [90, 85]}"#;
+    /// let value = JsonParser::from_reader(bytes).parse().unwrap();
+    ///
+    /// assert!(matches!(value, JsonValue::Obj(ref map) if map.len() == 2));
+    /// ```
+    ///
+    /// Reading a file without loading it into a `String` first:
+    ///
+    /// ```no_run
+    /// use karya_json::types::JsonParser;
+    /// use std::fs::File;
+    ///
+    /// let file = File::open("export.json").expect("Failed to open file");
+    /// let value = JsonParser::from_reader(file).parse();
+    /// ```
+    pub fn from_reader(reader: R) -> Self {
+        Self {
+            reader: JsonReader::from_reader(reader),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl<'a, S: Source<'a>> JsonParser<'a, S> {
+    /// Sets the options the parser uses, such as its resource limits.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options to use in place of the defaults
+    ///
+    /// # Returns
+    ///
+    /// The parser, using the given options
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{JsonParser, ParserOptions};
+    /// use karya_json::types::error::ErrorCode;
+    ///
+    /// let options = ParserOptions { max_input_length: Some(1024), ..ParserOptions::default() };
+    /// let input = format!("[{}]", "1, ".repeat(1000) + "1");
+    ///
+    /// let error = JsonParser::from_str(&input).with_options(options).parse().unwrap_err();
+    /// assert_eq!(error.code(), Some(&ErrorCode::InputTooLong(1024)));
+    /// ```
+    pub fn with_options(mut self, options: ParserOptions) -> Self {
+        self.reader = self.reader.with_options(options);
+        self
+    }
+
+    /// Parses the input JSON string into a `JsonValue`.
+    ///
+    /// This is the main entry point for parsing JSON. It parses the entire input
+    /// string and returns a `JsonValue` representing the parsed JSON data.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(JsonValue)` - If the input is valid JSON
+    /// * `Err(DeserializeError)` - If the input is invalid JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns a `DeserializeError` if:
+    /// * The input is not valid JSON
+    /// * There are unexpected trailing characters after the JSON value
+    /// * The input exceeds one of the parser's limits, such as its maximum
+    ///   nesting depth (see `ParserOptions`)
+    /// * An object repeats a key, unless `ParserOptions::duplicate_keys` says
+    ///   otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{JsonParser, JsonValue};
+    ///
+    /// let mut parser = JsonParser::from_str(r#"{"name": "Alice"}"#);
+    /// let result = parser.parse();
+    ///
+    /// assert!(result.is_ok());
+    /// ```
+    pub fn parse(&mut self) -> Result<JsonValue, DeserializeError> {
+        self.parse_document()
+    }
+
+    /// Parses the input JSON string into a `GenericJsonValue` whose objects
+    /// are held in the maps the backend `B` chooses.
+    ///
+    /// Members are inserted in the order they appear, so the map decides
+    /// whether that order is kept. Otherwise this is the same as `parse`,
+    /// which uses the `Ordered` backend.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `parse`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{GenericJsonValue, Hashed, JsonParser, ObjectMap};
+    ///
+    /// let value = JsonParser::from_str(r#"{"id": 7}"#).parse_with_maps::<Hashed>().unwrap();
+    ///
+    /// if let GenericJsonValue::Obj(map) = &value {
+    ///     assert!(matches!(map.get("id"), Some(GenericJsonValue::Int(7))));
+    /// }
+    /// ```
+    pub fn parse_with_maps<B: MapBackend>(&mut self) -> Result<GenericJsonValue<B>, DeserializeError> {
+        self.parse_document()
+    }
+
+    /// Parses the input JSON string into a `JsonValueRef` that borrows from the input.
+    ///
+    /// Strings and object keys without escape sequences are returned as
+    /// `Cow::Borrowed` slices of the input; only strings containing escapes are
+    /// decoded into new allocations. Call `JsonValueRef::into_owned` to detach
+    /// the result from the input.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(JsonValueRef)` - If the input is valid JSON
+    /// * `Err(DeserializeError)` - If the input is invalid JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `parse`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{JsonParser, JsonValueRef};
+    ///
+    /// let input = r#"{"id": 7, "name": "Alice"}"#;
+    /// let value = JsonParser::from_str(input).parse_borrowed().unwrap();
+    ///
+    /// if let JsonValueRef::Obj(map) = &value {
+    ///     assert!(matches!(map.get("name"), Some(JsonValueRef::Str(name)) if name == "Alice"));
+    /// }
+    /// ```
+    pub fn parse_borrowed(&mut self) -> Result<JsonValueRef<'a>, DeserializeError> {
+        self.parse_document()
+    }
+
+    /// Returns the warnings about the document read by the last call to
+    /// `parse` or `parse_borrowed`, in the order they were found.
+    ///
+    /// The parser warns about input it accepted but that may not mean what
+    /// its author intended, currently only object keys repeated under a
+    /// `DuplicateKeyPolicy` other than `Error`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{DuplicateKeyPolicy, JsonParser, ParserOptions};
+    ///
+    /// let options = ParserOptions { duplicate_keys: DuplicateKeyPolicy::FirstWins, ..ParserOptions::default() };
+    /// let mut parser = JsonParser::from_str(r#"[{"a": 1, "a": 2}, {"b": 1, "b": 2}]"#).with_options(options);
+    /// parser.parse().unwrap();
+    ///
+    /// for warning in parser.warnings() {
+    ///     eprintln!("warning: {}", warning);
+    /// }
+    /// assert_eq!(parser.warnings().len(), 2);
+    /// ```
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    /// Parses the input, reporting each part of it to a visitor instead of
+    /// building a value.
+    ///
+    /// The visitor's callbacks are made in document order as the input is
+    /// read. Nothing is retained between them, so duplicate keys are not
+    /// detected.
+    ///
+    /// # Arguments
+    ///
+    /// * `visitor` - The callbacks to drive
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the input is valid JSON and the visitor accepted all of it
+    /// * `Err(DeserializeError)` - If the input is invalid JSON, or the error a
+    ///   callback returned
+    ///
+    /// # Errors
+    ///
+    /// Returns the same syntax errors as `parse`, apart from duplicate keys.
+    /// An error returned by the visitor stops parsing at once and is returned
+    /// unchanged; the rest of the input is not read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{JsonParser, JsonVisitor};
+    /// use karya_json::types::error::DeserializeError;
+    ///
+    /// // Counts the records in a top-level array
+    /// struct Records {
+    ///     depth: usize,
+    ///     count: usize,
+    /// }
+    ///
+    /// impl JsonVisitor for Records {
+    ///     fn on_object_start(&mut self) -> Result<(), DeserializeError> {
+    ///         if self.depth == 1 {
+    ///             self.count += 1;
+    ///         }
+    ///         self.depth += 1;
+    ///         Ok(())
+    ///     }
+    ///     fn on_object_end(&mut self) -> Result<(), DeserializeError> {
+    ///         self.depth -= 1;
+    ///         Ok(())
+    ///     }
+    ///     fn on_array_start(&mut self) -> Result<(), DeserializeError> {
+    ///         self.depth += 1;
+    ///         Ok(())
+    ///     }
+    ///     fn on_array_end(&mut self) -> Result<(), DeserializeError> {
+    ///         self.depth -= 1;
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut records = Records { depth: 0, count: 0 };
+    /// let input = r#"[{"id": 1, "tags": [{}]}, {"id": 2}]"#;
+    /// JsonParser::from_str(input).parse_with(&mut records).unwrap();
+    ///
+    /// assert_eq!(records.count, 2);
+    /// ```
+    pub fn parse_with<V: JsonVisitor + ?Sized>(
+        &mut self,
+        visitor: &mut V,
+    ) -> Result<(), DeserializeError> {
+        while let Some(event) = self.reader.next_event()? {
+            match event {
+                JsonEvent::StartObject => visitor.on_object_start()?,
+                JsonEvent::Key(key) => visitor.on_key(&key)?,
+                JsonEvent::EndObject => visitor.on_object_end()?,
+                JsonEvent::StartArray => visitor.on_array_start()?,
+                JsonEvent::EndArray => visitor.on_array_end()?,
+                JsonEvent::String(s) => visitor.on_string(&s)?,
+                JsonEvent::Number(literal) => visitor.on_number(&literal)?,
+                JsonEvent::Bool(b) => visitor.on_bool(b)?,
+                JsonEvent::Null => visitor.on_null()?,
+            }
+        }
+        Ok(())
+    }
+
+    // Parses a complete document into either kind of value tree
+    fn parse_document<V: ParsedValue<'a>>(&mut self) -> Result<V, DeserializeError> {
+        let mut builder = TreeBuilder::new();
+        let result = self.build(&mut builder);
+        self.warnings = builder.warnings;
+        result
+    }
+
+    fn build<V: ParsedValue<'a>>(&mut self, builder: &mut TreeBuilder<'a, V>) -> Result<V, DeserializeError> {
+        loop {
+            let Some(event) = self.reader.next_event()? else {
+                // The reader only finishes once the root value is complete
+                return Err(self.reader.token_error(ErrorCode::UnexpectedEof));
+            };
+            if let Some(value) = builder.push(event, &self.reader)? {
+                // Ensure nothing but whitespace follows the root value
+                self.reader.next_event()?;
+                return Ok(value);
+            }
+        }
+    }
+}
+
+/// Assembles a value tree from a reader's events, one event at a time.
+///
+/// Containers being filled are kept on an explicit stack instead of the call
+/// stack, so nesting depth is limited only by memory, and building can stop
+/// after any event and pick up again later.
+pub(crate) struct TreeBuilder<'a, V: ParsedValue<'a>> {
+    stack: Vec<Container<'a, V>>,
+    warnings: Vec<ParseWarning>,
+}
+
+impl<'a, V: ParsedValue<'a>> TreeBuilder<'a, V> {
+    pub(crate) fn new() -> Self {
+        Self {
+            stack: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    pub(crate) fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    // Adds the next event to the tree, returning the root value once it is
+    // complete. Errors are located using the reader that produced the event.
+    pub(crate) fn push<S: Source<'a>>(
+        &mut self,
+        event: JsonEvent<'a>,
+        reader: &JsonReader<'a, S>,
+    ) -> Result<Option<V>, DeserializeError> {
+        let value = match event {
+            JsonEvent::StartArray => {
+                self.stack.push(Container::Array(Vec::new()));
+                return Ok(None);
+            }
+            JsonEvent::StartObject => {
+                self.stack.push(Container::Object(V::Object::default(), None, HashMap::new()));
+                return Ok(None);
+            }
+            JsonEvent::Key(key) => {
+                if let Some(Container::Object(object, pending, collected)) = self.stack.last_mut() {
+                    // Check for duplicate keys - RFC 8259 recommends implementations
+                    // should either prevent or report duplicate names
+                    if object.contains_key(key.as_ref()) || collected.contains_key(key.as_ref()) {
+                        let policy = reader.options().duplicate_keys;
+                        if policy == DuplicateKeyPolicy::Error {
+                            return Err(reader.token_error(ErrorCode::DuplicateKey(key.into_owned())));
+                        }
+                        let key = key.to_string();
+                        self.warnings.push(reader.token_warning(WarningCode::DuplicateKey { key, policy }));
+                    }
+                    *pending = Some(V::key(key));
+                }
+                return Ok(None);
+            }
+            JsonEvent::EndArray | JsonEvent::EndObject => match self.stack.pop() {
+                Some(container) => container.into_value(),
+                None => unreachable!("the reader only ends containers it started"),
+            },
+            JsonEvent::String(s) => V::string(s),
+            JsonEvent::Number(literal) => parse_number(&literal, reader)?,
+            JsonEvent::Bool(b) => V::bool(b),
+            JsonEvent::Null => V::null(),
+        };
+
+        match self.stack.last_mut() {
+            Some(Container::Array(items)) => items.push(value),
+            Some(Container::Object(object, key, collected)) => {
+                if let Some(key) = key.take() {
+                    insert_member(object, collected, key, value, reader.options().duplicate_keys);
+                }
+            }
+            None => return Ok(Some(value)),
+        }
+        Ok(None)
+    }
+}
+
+// Adds a member to an object, applying the policy if the key is already
+// there. The values of keys repeated under `CollectAll` move to `collected`
+// until the object is complete, leaving a placeholder to hold the key's place.
+fn insert_member<'a, V: ParsedValue<'a>>(
+    object: &mut V::Object,
+    collected: &mut HashMap<V::Key, Vec<V>>,
+    key: V::Key,
+    value: V,
+    policy: DuplicateKeyPolicy,
+) {
+    match policy {
+        DuplicateKeyPolicy::FirstWins => {
+            if !object.contains_key(key.borrow()) {
+                object.insert(key, value);
+            }
+        }
+        DuplicateKeyPolicy::CollectAll => {
+            if let Some(values) = collected.get_mut(key.borrow()) {
+                values.push(value);
+            } else if let Some(first) = object.get_mut(key.borrow()) {
+                let first = std::mem::replace(first, V::null());
+                collected.insert(key, vec![first, value]);
+            } else {
+                object.insert(key, value);
+            }
+        }
+        DuplicateKeyPolicy::Error | DuplicateKeyPolicy::LastWins => {
+            object.insert(key, value);
+        }
+    }
+}
+
+// Number conversion, from a literal the reader has already validated
+fn parse_number<'a, V: ParsedValue<'a>, S: Source<'a>>(
+    literal: &str,
+    reader: &JsonReader<'a, S>,
+) -> Result<V, DeserializeError> {
+    if reader.options().number_mode == NumberMode::Lossless {
+        return Ok(V::number(JsonNumber::from_literal(literal.to_owned())));
+    }
+
+    // JSON5's `Infinity` and `NaN` have no decimal form, and stay floats
+    #[cfg(feature = "decimal")]
+    if reader.options().number_mode == NumberMode::Decimal && literal.bytes().any(|b| b.is_ascii_digit()) {
+        return Decimal::from_literal(literal)
+            .map(V::decimal)
+            .ok_or_else(|| reader.token_error(ErrorCode::NumberOutOfRange(literal.to_owned())));
+    }
+
+    // If it's an integer with no decimal or exponent, parse it as the
+    // narrowest of i64, u64, i128 and u128 that holds it
+    if !literal.contains(['.', 'e', 'E']) {
+        if let Ok(n) = literal.parse::<i64>() {
+            return Ok(V::int(n));
+        }
+        if let Ok(n) = literal.parse::<u64>() {
+            return Ok(V::uint(n));
+        }
+        if let Ok(n) = literal.parse::<i128>() {
+            return Ok(V::int128(n));
+        }
+        if let Ok(n) = literal.parse::<u128>() {
+            return Ok(V::uint128(n));
+        }
+    }
+
+    // Parse as float for decimal or exponent numbers, and as a fallback
+    // for integers outside the range of u128
+    let n = float::parse_f64(literal).ok_or_else(|| reader.token_error(ErrorCode::InvalidNumber))?;
+
+    // Only a literal with digits can overflow; JSON5's `Infinity` is meant
+    if n.is_infinite() && reader.options().reject_number_overflow && literal.bytes().any(|b| b.is_ascii_digit()) {
+        return Err(reader.token_error(ErrorCode::NumberOutOfRange(literal.to_owned())));
+    }
+    Ok(V::float(n))
+}
+
+/// A container whose contents are still being parsed.
+enum Container<'a, V: ParsedValue<'a>> {
+    Array(Vec<V>),
+    /// An object, the key of the member whose value is being parsed, and the
+    /// values of keys repeated under `DuplicateKeyPolicy::CollectAll`
+    Object(V::Object, Option<V::Key>, HashMap<V::Key, Vec<V>>),
+}
+
+impl<'a, V: ParsedValue<'a>> Container<'a, V> {
+    fn into_value(self) -> V {
+        match self {
+            Container::Array(items) => V::array(items),
+            Container::Object(mut members, _, collected) => {
+                // Each replaces its placeholder, keeping the key's place
+                for (key, values) in collected {
+                    members.insert(key, V::array(values));
+                }
+                V::object(members)
+            }
+        }
+    }
+}
+
+/// The value trees the parser can build.
+///
+/// Implemented for `GenericJsonValue` and `JsonValueRef` so that the same
+/// tree builder serves `parse`, `parse_with_maps` and `parse_borrowed`.
+pub(crate) trait ParsedValue<'a>: Sized {
+    /// The object key type
+    type Key: Eq + Hash + Borrow<str>;
+    /// The object map type
+    type Object: ObjectMap<Self::Key, Self>;
+
+    fn key(key: Cow<'a, str>) -> Self::Key;
+    fn string(s: Cow<'a, str>) -> Self;
+    fn int(n: i64) -> Self;
+    fn uint(n: u64) -> Self;
+    fn int128(n: i128) -> Self;
+    fn uint128(n: u128) -> Self;
+    fn float(n: f64) -> Self;
+    fn number(n: JsonNumber) -> Self;
+    #[cfg(feature = "decimal")]
+    fn decimal(n: Decimal) -> Self;
+    fn bool(b: bool) -> Self;
+    fn null() -> Self;
+    fn array(items: Vec<Self>) -> Self;
+    fn object(members: Self::Object) -> Self;
+}
+
+impl<'a, B: MapBackend> ParsedValue<'a> for GenericJsonValue<B> {
+    type Key = String;
+    type Object = B::Map<Self>;
+
+    fn key(key: Cow<'a, str>) -> String {
+        key.into_owned()
+    }
+    fn string(s: Cow<'a, str>) -> Self {
+        GenericJsonValue::Str(s.into_owned())
+    }
+    fn int(n: i64) -> Self {
+        GenericJsonValue::Int(n)
+    }
+    fn uint(n: u64) -> Self {
+        GenericJsonValue::UInt(n)
+    }
+    fn int128(n: i128) -> Self {
+        GenericJsonValue::Int128(n)
+    }
+    fn uint128(n: u128) -> Self {
+        GenericJsonValue::UInt128(n)
+    }
+    fn float(n: f64) -> Self {
+        GenericJsonValue::Float(n)
+    }
+    fn number(n: JsonNumber) -> Self {
+        GenericJsonValue::Number(n)
+    }
+    #[cfg(feature = "decimal")]
+    fn decimal(n: Decimal) -> Self {
+        GenericJsonValue::Decimal(n)
+    }
+    fn bool(b: bool) -> Self {
+        GenericJsonValue::Bool(b)
+    }
+    fn null() -> Self {
+        GenericJsonValue::Null
+    }
+    fn array(items: Vec<Self>) -> Self {
+        GenericJsonValue::Arr(items.into())
+    }
+    fn object(members: B::Map<Self>) -> Self {
+        GenericJsonValue::Obj(JsonObject::from_map(members))
+    }
+}
+
+impl<'a> ParsedValue<'a> for JsonValueRef<'a> {
+    type Key = Cow<'a, str>;
+    type Object = JsonMap<Cow<'a, str>, Self>;
+
+    fn key(key: Cow<'a, str>) -> Cow<'a, str> {
+        key
+    }
+    fn string(s: Cow<'a, str>) -> Self {
+        JsonValueRef::Str(s)
+    }
+    fn int(n: i64) -> Self {
+        JsonValueRef::Int(n)
+    }
+    fn uint(n: u64) -> Self {
+        JsonValueRef::UInt(n)
+    }
+    fn int128(n: i128) -> Self {
+        JsonValueRef::Int128(n)
+    }
+    fn uint128(n: u128) -> Self {
+        JsonValueRef::UInt128(n)
+    }
+    fn float(n: f64) -> Self {
+        JsonValueRef::Float(n)
+    }
+    fn number(n: JsonNumber) -> Self {
+        JsonValueRef::Number(n)
+    }
+    #[cfg(feature = "decimal")]
+    fn decimal(n: Decimal) -> Self {
+        JsonValueRef::Decimal(n)
+    }
+    fn bool(b: bool) -> Self {
+        JsonValueRef::Bool(b)
+    }
+    fn null() -> Self {
+        JsonValueRef::Null
+    }
+    fn array(items: Vec<Self>) -> Self {
+        JsonValueRef::Arr(items.into())
+    }
+    fn object(members: JsonMap<Cow<'a, str>, Self>) -> Self {
+        JsonValueRef::Obj(members.into())
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::error::{Position, SyntaxError};
+    use crate::types::Dialect;
+    use crate::types::error::WarningCode;
+
+    #[test]
+    fn test_parse_number() {
+        // Test integer parsing
+        let mut parser = JsonParser::from_str("123");
+        match parser.parse().unwrap() {
+            JsonValue::Int(n) => assert_eq!(n, 123),
+            _ => panic!("Expected JsonValue::Int"),
+        }
+
+        // Test float parsing
+        let mut parser = JsonParser::from_str("123.456");
+        match parser.parse().unwrap() {
+            JsonValue::Float(n) => assert_eq!(n, 123.456),
+            _ => panic!("Expected JsonValue::Float"),
+        }
+
+        // Test negative float with exponent
+        let mut parser = JsonParser::from_str("-123.456e-10");
+        match parser.parse().unwrap() {
+            JsonValue::Float(n) => assert_eq!(n, -123.456e-10),
+            _ => panic!("Expected JsonValue::Float"),
+        }
+
+        // Test integer with exponent (should be a float)
+        let mut parser = JsonParser::from_str("123e2");
+        match parser.parse().unwrap() {
+            JsonValue::Float(n) => assert_eq!(n, 12300f64),
+            _ => panic!("Expected JsonValue::Float"),
+        }
+
+        // Integers beyond i64 keep every digit in the narrowest type that holds them
+        let input = "[9223372036854775807,9223372036854775808,18446744073709551615,\
+                     18446744073709551616,-9223372036854775809,\
+                     340282366920938463463374607431768211455,340282366920938463463374607431768211456]";
+        let value = JsonParser::from_str(input).parse().unwrap();
+        let JsonValue::Arr(items) = &value else {
+            panic!("Expected JsonValue::Arr");
+        };
+        assert!(matches!(items[0], JsonValue::Int(i64::MAX)));
+        assert!(matches!(items[1], JsonValue::UInt(9223372036854775808)));
+        assert!(matches!(items[2], JsonValue::UInt(u64::MAX)));
+        assert!(matches!(items[3], JsonValue::Int128(18446744073709551616)));
+        assert!(matches!(items[4], JsonValue::Int128(-9223372036854775809)));
+        assert!(matches!(items[5], JsonValue::UInt128(u128::MAX)));
+        assert!(matches!(items[6], JsonValue::Float(n) if n == 2f64.powi(128)));
+        let exact = &input[..input.rfind(',').unwrap()];
+        assert!(value.to_string().starts_with(exact));
+    }
+
+    #[test]
+    fn test_number_overflow() {
+        // By default an overflowing literal becomes an infinite float
+        let value = JsonParser::from_str("[1e400]").parse().unwrap();
+        assert!(matches!(&value, JsonValue::Arr(items) if matches!(items[0], JsonValue::Float(n) if n == f64::INFINITY)));
+
+        let options = ParserOptions { reject_number_overflow: true, ..ParserOptions::default() };
+        let error = JsonParser::from_str(r#"{"price": [1, -1.5e400]}"#).with_options(options.clone()).parse().unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::NumberOutOfRange("-1.5e400".to_string())));
+        assert!(matches!(error, DeserializeError::InvalidJson(error) if error.path() == "/price/1"));
+
+        // Underflow, JSON5's Infinity and lossless numbers are all accepted
+        let value = JsonParser::from_str("1e-400").with_options(options.clone()).parse().unwrap();
+        assert!(matches!(value, JsonValue::Float(n) if n == 0.0));
+        let json5 = ParserOptions { dialect: Dialect::Json5, ..options.clone() };
+        assert!(JsonParser::from_str("-Infinity").with_options(json5).parse().is_ok());
+        let lossless = ParserOptions { number_mode: NumberMode::Lossless, ..options };
+        assert!(JsonParser::from_str("1e400").with_options(lossless).parse().is_ok());
+    }
+
+    #[test]
+    fn test_lossless_numbers() {
+        let options = ParserOptions { number_mode: NumberMode::Lossless, ..ParserOptions::default() };
+        let input = "[18446744073709551615,-0,1.10,-12.5E+03,123456789012345678901234567890]";
+        let value = JsonParser::from_str(input).with_options(options.clone()).parse().unwrap();
+        assert_eq!(value.to_string(), input);
+
+        let JsonValue::Arr(items) = &value else {
+            panic!("Expected JsonValue::Arr");
+        };
+        assert!(matches!(&items[0], JsonValue::Number(n) if n.as_u64() == Some(u64::MAX)));
+        assert!(matches!(&items[1], JsonValue::Number(n) if n.as_i64() == Some(0) && n.as_str() == "-0"));
+        assert!(matches!(&items[3], JsonValue::Number(n) if n.as_f64() == -12500.0));
+
+        // Borrowed values, and JSON5 literals, keep their numbers the same way
+        let value = JsonParser::from_str(input).with_options(options.clone()).parse_borrowed().unwrap();
+        assert_eq!(value.to_string(), input);
+        assert_eq!(value.into_owned().to_string(), input);
+        let json5 = ParserOptions { dialect: Dialect::Json5, ..options };
+        let value = JsonParser::from_str("[0x10, +1.50, Infinity]").with_options(json5).parse().unwrap();
+        assert_eq!(value.to_string(), "[16,1.50,null]");
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_decimal_numbers() {
+        let options = ParserOptions { number_mode: NumberMode::Decimal, ..ParserOptions::default() };
+        let input = "[0.1,123456789012345678.99,-0,1.10,1E+400]";
+        let value = JsonParser::from_str(input).with_options(options.clone()).parse().unwrap();
+        assert_eq!(value.to_string(), input);
+
+        let JsonValue::Arr(items) = &value else {
+            panic!("Expected JsonValue::Arr");
+        };
+        let decimal = |literal: &str| literal.parse::<Decimal>().unwrap();
+        assert!(matches!(&items[0], JsonValue::Decimal(n) if *n == decimal("1e-1")));
+        assert!(matches!(&items[1], JsonValue::Decimal(n) if n.to_i128().is_none()));
+        assert!(matches!(&items[3], JsonValue::Decimal(n) if *n == decimal("1.1")));
+        let huge = JsonParser::from_str("1e9223372036854775807").with_options(options.clone()).parse().unwrap();
+        assert_eq!(huge.as_i64(), None);
+
+        // Borrowed values match, and JSON5 infinities stay floats
+        let value = JsonParser::from_str(input).with_options(options.clone()).parse_borrowed().unwrap();
+        assert_eq!(value.into_owned().to_string(), input);
+        let json5 = ParserOptions { dialect: Dialect::Json5, ..options.clone() };
+        let value = JsonParser::from_str("[0x10, .5, -Infinity]").with_options(json5).parse().unwrap();
+        assert_eq!(value.to_string(), "[16,0.5,null]");
+
+        let error = JsonParser::from_str("1e99999999999999999999").with_options(options).parse().unwrap_err();
+        assert!(matches!(error.code(), Some(ErrorCode::NumberOutOfRange(_))));
+    }
+
+    #[test]
+    fn test_parse_array() {
+        let mut parser = JsonParser::from_str("[1, 2, 3]");
+        match &parser.parse().unwrap() {
+            JsonValue::Arr(array) => assert_eq!(array.len(), 3),
+            _ => panic!("Expected JsonValue::Arr"),
+        }
+    }
+
+    #[test]
+    fn test_parse_object() {
+        let mut parser = JsonParser::from_str(r#"{"name": "John", "age": 30}"#);
+        match &parser.parse().unwrap() {
+            JsonValue::Obj(object) => assert_eq!(object.len(), 2),
+            _ => panic!("Expected JsonValue::Obj"),
+        }
+    }
+
+    #[test]
+    fn test_object_order() {
+        // Members keep their source order through parsing and writing, for
+        // objects small enough to scan and those large enough to index
+        let small = r#"{"zebra":1,"apple":{"y":true,"x":null},"mango":"m"}"#;
+        let large = format!("{{{}}}", (0..100).rev().map(|i| format!(r#""k{}":{}"#, i, i)).collect::<Vec<_>>().join(","));
+        for input in [small, large.as_str()] {
+            assert_eq!(JsonParser::from_str(input).parse().unwrap().to_string(), input);
+            assert_eq!(JsonParser::from_str(input).parse_borrowed().unwrap().to_string(), input);
+            assert_eq!(JsonParser::from_str(input).parse_borrowed().unwrap().into_owned().to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let mut parser = JsonParser::from_str(r#"{"name": "John", "name": "Jane"}"#);
+        let result = parser.parse();
+        assert!(result.is_err(), "Parsing duplicate keys should fail");
+        if let Err(e) = result {
+            assert_eq!(e.code(), Some(&ErrorCode::DuplicateKey("name".to_string())));
+            assert!(e.to_string().contains("Duplicate key 'name' in object"));
+        }
+    }
+
+    #[test]
+    fn test_duplicate_key_policies() {
+        let input = r#"{"k": 1, "k": [2], "other": [0], "k": 3}"#;
+        let parse = |policy| {
+            let options = ParserOptions { duplicate_keys: policy, ..ParserOptions::default() };
+            let mut parser = JsonParser::from_str(input).with_options(options);
+            let value = parser.parse().unwrap();
+            (value.to_string(), parser.warnings().to_vec())
+        };
+
+        // The key stays where it first appeared, whichever value it keeps
+        assert_eq!(parse(DuplicateKeyPolicy::FirstWins).0, r#"{"k":1,"other":[0]}"#);
+        assert_eq!(parse(DuplicateKeyPolicy::LastWins).0, r#"{"k":3,"other":[0]}"#);
+        let (k, warnings) = parse(DuplicateKeyPolicy::CollectAll);
+        assert_eq!(k, r#"{"k":[1,[2],3],"other":[0]}"#);
+
+        // Every repeat is reported, with the policy applied to it
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(
+            warnings[0].code(),
+            &WarningCode::DuplicateKey { key: "k".to_string(), policy: DuplicateKeyPolicy::CollectAll }
+        );
+        assert_eq!(warnings[0].position().offset, 9);
+        assert_eq!(warnings[1].position().offset, 33);
+        assert_eq!(
+            warnings[1].to_string(),
+            "Duplicate key 'k' in object, keeping every value at line 1, column 34 in /k"
+        );
+
+        // Borrowed values, and each new document, start afresh
+        let options = ParserOptions { duplicate_keys: DuplicateKeyPolicy::LastWins, ..ParserOptions::default() };
+        let mut parser = JsonParser::from_str(r#"[{"a": "x", "a": "y"}]"#).with_options(options);
+        let value = parser.parse_borrowed().unwrap();
+        assert_eq!(value.to_string(), r#"[{"a":"y"}]"#);
+        assert_eq!(parser.warnings().len(), 1);
+        assert_eq!(parser.warnings()[0].path(), "/0/a");
+        assert!(parser.parse().is_err());
+        assert!(parser.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_from_slice() {
+        let mut parser = JsonParser::from_slice("{\"greeting\": \"héllo\"}".as_bytes()).unwrap();
+        match &parser.parse().unwrap() {
+            JsonValue::Obj(map) => match map.get("greeting") {
+                Some(JsonValue::Str(s)) => assert_eq!(s, "h\u{e9}llo"),
+                _ => panic!("Expected greeting to be a string"),
+            },
+            _ => panic!("Expected JsonValue::Obj"),
+        }
+
+        // Invalid UTF-8 is rejected before parsing starts
+        assert!(JsonParser::from_slice(b"[\"\xC3\x28\"]").is_err());
+    }
+
+    #[test]
+    fn test_multibyte_characters() {
+        // Raw multi-byte characters are copied through unchanged
+        let mut parser = JsonParser::from_str("\"café 日本 😀\"");
+        assert!(matches!(&parser.parse().unwrap(), JsonValue::Str(s) if s == "café 日本 😀"));
+
+        // Non-ASCII characters are reported whole in error messages
+        let mut parser = JsonParser::from_str("[1 é]");
+        let error = parser.parse().unwrap_err();
+        assert!(error.to_string().contains("found 'é'"));
+    }
+
+    // A reader that hands out its input one byte per call
+    struct ByteReader<'a>(&'a [u8]);
+
+    impl Read for ByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let input = r#"["caf\u00e9 日本 😀", [1, -2.5e3, true, null, {}], {"esc\"aped": "x"}]"#;
+        let expected = JsonParser::from_str(input).parse().unwrap().to_string();
+
+        let value = JsonParser::from_reader(input.as_bytes()).parse().unwrap();
+        assert_eq!(value.to_string(), expected);
+
+        // Chunk boundaries may fall anywhere, even inside a character
+        let value = JsonParser::from_reader(ByteReader(input.as_bytes())).parse().unwrap();
+        assert_eq!(value.to_string(), expected);
+
+        // Nothing can be borrowed from a reader, so strings are always owned
+        let value = JsonParser::from_reader(input.as_bytes()).parse_borrowed().unwrap();
+        assert_eq!(value.to_string(), expected);
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        // I/O errors are reported as such rather than as the truncated document
+        let failing = br#"{"a": [1, 2"#.chain(ErrorReader);
+        let error = JsonParser::from_reader(failing).parse().unwrap_err();
+        assert!(matches!(error, DeserializeError::Io(ref e) if e.to_string() == "disk on fire"));
+
+        // Invalid UTF-8 is located in the input, even past a complete value
+        let error = JsonParser::from_reader(&b"[1]\n \xFF"[..]).parse().unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::InvalidUtf8(5)));
+        assert_eq!(error.position().map(|p| (p.line, p.column)), Some((2, 2)));
+
+        // So is a character cut short by the end of the input
+        let error = JsonParser::from_reader(ByteReader(b"\"\xE6\x97")).parse().unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::InvalidUtf8(1)));
+
+        // Syntax errors are unaffected
+        let error = JsonParser::from_reader(ByteReader(b"[1, 2")).parse().unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::UnterminatedArray));
+    }
+
+    // A reader that always fails
+    struct ErrorReader;
+
+    impl Read for ErrorReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk on fire"))
+        }
+    }
+
+    #[test]
+    fn test_reader_releases_parsed_text() {
+        // Far more input than is kept buffered, with the error at the very end
+        let mut input = String::from("{\"records\": [\n");
+        for i in 0..20_000 {
+            input.push_str(&format!("  {{\"id\": {}, \"tag\": \"é\"}},\n", i));
+        }
+        input.push_str("  {\"id\": x}]}");
+
+        let expected = syntax_error(&input);
+        let mut parser = JsonParser::from_reader(input.as_bytes());
+        match parser.parse() {
+            Err(DeserializeError::InvalidJson(error)) => {
+                assert_eq!(error.position(), expected.position());
+                assert_eq!(error.path(), "/records/20000/id");
+            }
+            other => panic!("Expected a syntax error, got {:?}", other),
+        }
+    }
+
+    // Records every callback, and fails on a chosen key
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<String>,
+        reject: Option<&'static str>,
+    }
+
+    impl JsonVisitor for Recorder {
+        fn on_object_start(&mut self) -> Result<(), DeserializeError> {
+            self.calls.push("{".to_string());
+            Ok(())
+        }
+        fn on_key(&mut self, key: &str) -> Result<(), DeserializeError> {
+            if self.reject == Some(key) {
+                return Err(DeserializeError::InvalidValue(format!("rejected {}", key)));
+            }
+            self.calls.push(format!("{}:", key));
+            Ok(())
+        }
+        fn on_object_end(&mut self) -> Result<(), DeserializeError> {
+            self.calls.push("}".to_string());
+            Ok(())
+        }
+        fn on_array_start(&mut self) -> Result<(), DeserializeError> {
+            self.calls.push("[".to_string());
+            Ok(())
+        }
+        fn on_array_end(&mut self) -> Result<(), DeserializeError> {
+            self.calls.push("]".to_string());
+            Ok(())
+        }
+        fn on_string(&mut self, value: &str) -> Result<(), DeserializeError> {
+            self.calls.push(format!("{:?}", value));
+            Ok(())
+        }
+        fn on_number(&mut self, literal: &str) -> Result<(), DeserializeError> {
+            self.calls.push(literal.to_string());
+            Ok(())
+        }
+        fn on_bool(&mut self, value: bool) -> Result<(), DeserializeError> {
+            self.calls.push(value.to_string());
+            Ok(())
+        }
+        fn on_null(&mut self) -> Result<(), DeserializeError> {
+            self.calls.push("null".to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse_with() {
+        let mut visitor = Recorder::default();
+        let input = r#"{"a": [1, 2.5e1, "x\"y"], "b": {"c": true, "d": null}}"#;
+        JsonParser::from_str(input).parse_with(&mut visitor).unwrap();
+        assert_eq!(
+            visitor.calls.join(" "),
+            r#"{ a: [ 1 2.5e1 "x\"y" ] b: { c: true d: null } }"#
+        );
+
+        // Syntax errors are reported after the callbacks for what came before
+        let mut visitor = Recorder::default();
+        let error = JsonParser::from_str("[1, 2,]").parse_with(&mut visitor).unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::UnexpectedCharacter(']')));
+        assert_eq!(visitor.calls, ["[", "1", "2"]);
+    }
+
+    #[test]
+    fn test_visitor_aborts() {
+        // The visitor's error is returned as is, and nothing after it is read
+        let mut visitor = Recorder {
+            reject: Some("stop"),
+            ..Recorder::default()
+        };
+        let input = r#"{"go": 1, "stop": 2, "never": @}"#;
+        match JsonParser::from_str(input).parse_with(&mut visitor) {
+            Err(DeserializeError::InvalidValue(msg)) => assert_eq!(msg, "rejected stop"),
+            other => panic!("Expected the visitor's error, got {:?}", other),
+        }
+        assert_eq!(visitor.calls, ["{", "go:", "1"]);
+    }
+
+    // Parses the input and returns the syntax error it produces
+    fn syntax_error(input: &str) -> SyntaxError {
+        match JsonParser::from_str(input).parse() {
+            Err(DeserializeError::InvalidJson(error)) => error,
+            other => panic!("Expected a syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_position() {
+        let error = syntax_error("{\n  \"a\": 1,\n  \"b\" 2\n}");
+        assert_eq!(
+            error.code(),
+            &ErrorCode::ExpectedCharacter {
+                expected: ':',
+                found: Some('2')
+            }
+        );
+        assert_eq!(
+            error.position(),
+            Position {
+                offset: 18,
+                line: 3,
+                column: 7
+            }
+        );
+
+        // Columns count characters, not bytes
+        let error = syntax_error("[\"日本\", x]");
+        assert_eq!(error.position().offset, 11);
+        assert_eq!(error.position().column, 8);
+
+        // End-of-input errors point just past the last byte
+        let error = syntax_error("[1, 2");
+        assert_eq!(error.position().offset, 5);
+
+        // Invalid UTF-8 is located too
+        match JsonParser::from_slice(b"{\n\"a\": \"\xFF\"}") {
+            Err(DeserializeError::InvalidJson(error)) => {
+                assert_eq!(error.position().line, 2);
+                assert_eq!(error.position().column, 7);
+            }
+            _ => panic!("Expected a syntax error"),
+        }
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let cases = [
+            ("", ErrorCode::UnexpectedEof),
+            ("[1, @]", ErrorCode::UnexpectedCharacter('@')),
+            ("[1 2]", ErrorCode::ExpectedCommaOrClose { close: ']', found: '2' }),
+            (r#"{"a": 1 "b": 2}"#, ErrorCode::ExpectedCommaOrClose { close: '}', found: '"' }),
+            ("[1, 2", ErrorCode::UnterminatedArray),
+            (r#"{"a": 1"#, ErrorCode::UnterminatedObject),
+            (r#""abc"#, ErrorCode::UnterminatedString),
+            (r#""\x""#, ErrorCode::InvalidEscape('x')),
+            (r#""\u12G4""#, ErrorCode::InvalidUnicodeEscape('G')),
+            (r#""\u12"#, ErrorCode::UnterminatedUnicodeEscape),
+            (r#""\uD83D""#, ErrorCode::LoneSurrogate(0xD83D)),
+            (r#""\uDE00""#, ErrorCode::LoneSurrogate(0xDE00)),
+            (r#""\uD83D\u0041""#, ErrorCode::InvalidLowSurrogate(0x0041)),
+            ("\"a\tb\"", ErrorCode::ControlCharacterInString(b'\t')),
+            ("-", ErrorCode::InvalidNumber),
+            ("1.", ErrorCode::MissingFractionDigits),
+            ("1e+", ErrorCode::MissingExponentDigits),
+            ("tru", ErrorCode::ExpectedCharacter { expected: 'e', found: None }),
+            ("nulL", ErrorCode::ExpectedCharacter { expected: 'l', found: Some('L') }),
+            ("{} {}", ErrorCode::TrailingCharacters),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(syntax_error(input).code(), &expected, "input: {}", input);
+        }
+
+        // Display keeps the established human-readable messages
+        assert_eq!(
+            ErrorCode::LoneSurrogate(0xD83D).to_string(),
+            "High surrogate U+D83D not followed by low surrogate"
+        );
+        assert_eq!(ErrorCode::InvalidEscape('x').to_string(), "Invalid escape sequence: \\x");
+        assert_eq!(
+            ErrorCode::ControlCharacterInString(0x09).to_string(),
+            "Unescaped control character (0x09) in string"
+        );
+    }
+
+    #[test]
+    fn test_error_path() {
+        assert_eq!(syntax_error("[1, 2, [3, x]]").path(), "/2/1");
+        assert_eq!(syntax_error(r#"{"a": {"b": [true, fals]}}"#).path(), "/a/b/1");
+        assert_eq!(syntax_error(r#"{"a": 1 "b": 2}"#).path(), "/a");
+        assert_eq!(syntax_error("[1 2]").path(), "/0");
+        assert_eq!(syntax_error("nul").path(), "");
+
+        // Keys are escaped as RFC 6901 requires, after decoding JSON escapes
+        assert_eq!(syntax_error(r#"{"a/b": {"c~d!": -}}"#).path(), "/a~1b/c~0d!");
+
+        // The duplicate key itself is reported
+        let error = syntax_error(r#"{"x": {"y": 1, "y": 2}}"#);
+        assert_eq!(error.path(), "/x/y");
+        assert_eq!(error.position().offset, 15);
+    }
+
+    // Parses the input with the options and returns the error code it produces
+    fn limit_error(input: &str, options: &ParserOptions) -> Option<ErrorCode> {
+        let result = JsonParser::from_str(input).with_options(options.clone()).parse();
+        result.err().and_then(|error| error.code().cloned())
+    }
+
+    #[test]
+    fn test_limits() {
+        // Nothing is limited by default
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert_eq!(limit_error(&nested(100_000), &ParserOptions::default()), None);
+        let shallow = ParserOptions { max_depth: Some(128), ..ParserOptions::default() };
+        assert_eq!(limit_error(&nested(128), &shallow), None);
+        assert_eq!(limit_error(&nested(129), &shallow), Some(ErrorCode::DepthLimitExceeded(128)));
+
+        let options = ParserOptions {
+            max_depth: Some(2),
+            max_string_length: Some(4),
+            max_array_length: Some(3),
+            max_object_length: Some(2),
+            max_number_length: Some(5),
+            max_input_length: Some(40),
+            ..ParserOptions::default()
+        };
+        let cases = [
+            (r#"[{"a": 1}]"#, None),
+            (r#"[{"a": []}]"#, Some(ErrorCode::DepthLimitExceeded(2))),
+            (r#"["abcd", "é\n\u0041"]"#, None),
+            (r#"["abcde"]"#, Some(ErrorCode::StringTooLong(4))),
+            (r#"["abc\u00e9"]"#, Some(ErrorCode::StringTooLong(4))),
+            (r#"{"abcde": 1}"#, Some(ErrorCode::StringTooLong(4))),
+            ("[1, 2, 3]", None),
+            ("[1, 2, 3, 4]", Some(ErrorCode::ArrayTooLong(3))),
+            (r#"{"a": 1, "b": 2}"#, None),
+            (r#"{"a": 1, "b": 2, "c": 3}"#, Some(ErrorCode::ObjectTooLong(2))),
+            ("-1.5e3", Some(ErrorCode::NumberTooLong(5))),
+            ("1.5e3", None),
+            (r#"[1, "a", "b", {"c": "d"}]"#, Some(ErrorCode::ArrayTooLong(3))),
+            (&format!("{:<40}", "[]"), None),
+            (&format!("{:<41}", "[]"), Some(ErrorCode::InputTooLong(40))),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(limit_error(input, &options), expected, "input: {}", input);
+        }
+
+        // Errors point at where the limit was crossed
+        let error = JsonParser::from_str(r#"{"list": [1, 2, 3, 4]}"#)
+            .with_options(options.clone())
+            .parse()
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Invalid JSON: Array longer than the maximum of 3 elements at line 1, column 20 in /list/3");
+        let error = JsonParser::from_str(r#"{"k": "too long"}"#).with_options(options).parse().unwrap_err();
+        assert_eq!(error.position().unwrap().offset, 6);
+
+        // Reading stops at the input limit, even from a reader
+        let input = format!("[{}]", "1, ".repeat(100_000) + "1");
+        let options = ParserOptions { max_input_length: Some(40), ..ParserOptions::default() };
+        let mut parser = JsonParser::from_reader(input.as_bytes()).with_options(options);
+        let error = parser.parse().unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::InputTooLong(40)));
+        assert_eq!(error.position().unwrap().offset, 40);
+    }
+
+    #[test]
+    fn test_json5() {
+        let json5 = ParserOptions { dialect: Dialect::Json5, ..ParserOptions::default() };
+        let input = "
+            /* Service configuration */
+            {
+                name: 'api',   // unquoted keys, single quotes
+                port: 0x1F90,
+                scale: +.5,
+                banner: 'first line \\
+second line',
+                limits: [Infinity, -Infinity, NaN,],
+            }
+        ";
+        let value = JsonParser::from_str(input).with_options(json5.clone()).parse().unwrap();
+        let JsonValue::Obj(map) = &value else {
+            panic!("Expected JsonValue::Obj");
+        };
+        assert!(matches!(map.get("name"), Some(JsonValue::Str(s)) if s == "api"));
+        assert!(matches!(map.get("port"), Some(JsonValue::Int(8080))));
+        assert!(matches!(map.get("scale"), Some(JsonValue::Float(n)) if *n == 0.5));
+        assert!(matches!(map.get("banner"), Some(JsonValue::Str(s)) if s == "first line second line"));
+        match map.get("limits") {
+            Some(JsonValue::Arr(items)) => {
+                assert!(matches!(items[0], JsonValue::Float(n) if n == f64::INFINITY));
+                assert!(matches!(items[1], JsonValue::Float(n) if n == f64::NEG_INFINITY));
+                assert!(matches!(items[2], JsonValue::Float(n) if n.is_nan()));
+            }
+            _ => panic!("Expected limits to be an array"),
+        }
+
+        // Identifier keys appear in error paths like any other
+        match JsonParser::from_str("{outer: {'inner': [1, 2 3]}}").with_options(json5.clone()).parse() {
+            Err(DeserializeError::InvalidJson(error)) => assert_eq!(error.path(), "/outer/inner/1"),
+            other => panic!("Expected a syntax error, got {:?}", other),
+        }
+
+        // Duplicate keys are still rejected, however they are written
+        let error = JsonParser::from_str("{a: 1, 'a': 2}").with_options(json5).parse().unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::DuplicateKey("a".to_string())));
+    }
+
+    #[test]
+    fn test_complex_json() {
+        let json = r#"
+        {
+            "name": "John Doe",
+            "age": 30,
+            "is_student": false,
+            "grades": [85, 90, 92],
+            "address": {
+                "street": "123 Main St",
+                "city": "Anytown"
+            },
+            "phone": null,
+            "score": 98.6,
+            "exp": 1.23e4,
+            "credit_score": -120,
+            "total_bank_balance": -20.012
+        }"#;
+
+        let mut parser = JsonParser::from_str(json);
+        let result = parser.parse();
+        assert!(result.is_ok());
+
+        // Verify that the parsed result contains correctly typed numbers
+        if let Ok(JsonValue::Obj(obj)) = &result {
+            // Verify integer
+            if let Some(JsonValue::Int(age)) = obj.get("age") {
+                assert_eq!(*age, 30);
+            } else {
+                panic!("Expected age to be an integer");
+            }
+
+            // Verify float
+            if let Some(JsonValue::Float(score)) = obj.get("score") {
+                assert_eq!(*score, 98.6);
+            } else {
+                panic!("Expected score to be a float");
+            }
+
+            // Verify exponent
+            if let Some(JsonValue::Float(exp)) = obj.get("exp") {
+                assert_eq!(*exp, 12300.0);
+            } else {
+                panic!("Expected exp to be a float");
+            }
+
+            // Verify negative int
+            if let Some(JsonValue::Int(negative_num)) = obj.get("credit_score") {
+                assert_eq!(*negative_num, -120);
+            } else {
+                panic!("Expected credit_score to be a -ve integer");
+            }
+
+            // Verify negative float
+            if let Some(JsonValue::Float(negative_num)) = obj.get("total_bank_balance") {
+                assert_eq!(*negative_num, -20.012);
+            } else {
+                panic!("Expected total_bank_balance to be a -ve float");
+            }
+        } else {
+            panic!("Failed to parse complex JSON");
+        }
+    }
+}
@@ -0,0 +1,1613 @@
+//! The pull-based event reader.
+//!
+//! `JsonReader` walks a document one token at a time and hands back a
+//! `JsonEvent` for each. The only state it keeps is a stack of the containers
+//! enclosing the current position, so documents of any size can be processed in
+//! memory proportional to their nesting depth. `JsonParser` builds its value
+//! trees on top of it.
+
+use crate::types::{Dialect, ParserOptions};
+use crate::types::error::{DeserializeError, ErrorCode, ParseWarning, Position, SyntaxError, WarningCode};
+use crate::types::source::{FeedSource, ReaderSource, Source, StrSource, locate};
+use std::borrow::Cow;
+use std::io::Read;
+use std::marker::PhantomData;
+
+/// A single step through a JSON document, as produced by `JsonReader`.
+///
+/// Containers produce a start event, the events of their contents and a
+/// matching end event. Each object member produces a `Key` event followed by
+/// the events of its value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonEvent<'a> {
+    /// The start of an object (`{`)
+    StartObject,
+    /// The key of the next object member
+    Key(Cow<'a, str>),
+    /// The end of an object (`}`)
+    EndObject,
+    /// The start of an array (`[`)
+    StartArray,
+    /// The end of an array (`]`)
+    EndArray,
+    /// A string value
+    String(Cow<'a, str>),
+    /// A number value, as its literal text in the input. The text is known to
+    /// follow the JSON number grammar, so `str::parse` converts it to any
+    /// numeric type able to hold it. JSON5 literals outside that grammar are
+    /// rewritten into it, hexadecimal ones in decimal, except for `Infinity`,
+    /// `-Infinity` and `NaN`, which `str::parse` converts to `f64`.
+    Number(Cow<'a, str>),
+    /// A boolean value
+    Bool(bool),
+    /// The null value
+    Null,
+}
+
+/// A pull-based reader that yields a document as a sequence of `JsonEvent`s.
+///
+/// Events are produced lazily, one per call to `next_event` (or per item when
+/// used as an `Iterator`). Strings and keys borrow from the input under the
+/// same rules as `JsonParser::parse_borrowed`. Syntax is checked as the reader
+/// goes, including that nothing but whitespace follows the document; duplicate
+/// keys are not detected, since the reader does not remember the keys it has
+/// seen.
+///
+/// # Examples
+///
+/// Counting the elements of a large array without building it:
+///
+/// ```
+/// use karya_json::types::{JsonEvent, JsonReader};
+///
+/// let mut reader = JsonReader::from_str(r#"[{"id": 1}, {"id": 2}, {"id": 3}]"#);
+///
+/// assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::StartArray));
+/// let mut records = 0;
+/// while reader.next_event().unwrap() == Some(JsonEvent::StartObject) {
+///     records += 1;
+///     // Skip the rest of the record, up to and including its `}`
+///     while reader.next_event().unwrap() != Some(JsonEvent::EndObject) {}
+/// }
+///
+/// assert_eq!(records, 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct JsonReader<'a, S = StrSource<'a>> {
+    /// The input JSON document, already validated as UTF-8
+    source: S,
+    /// The current byte offset in the source's text
+    position: usize,
+    /// The offset in the source's text of the token behind the last event
+    token_start: usize,
+    /// The containers enclosing the current position, outermost first
+    stack: Vec<Frame>,
+    /// What the reader expects next
+    state: State,
+    /// Whether the current token ran into the end of the text while the
+    /// source was still waiting for more
+    starved: bool,
+    /// Whether reading stopped at the input length limit
+    over_limit: bool,
+    /// How far the reader got through a token that ran past the end of the
+    /// input fed so far, so that it carries on from there
+    partial: Option<Partial>,
+    options: ParserOptions,
+    /// How many bytes have been looked at, to check that none are read over
+    /// and over again
+    #[cfg(test)]
+    pub(crate) bytes_read: usize,
+    marker: PhantomData<&'a str>,
+}
+
+/// An open container, together with the reader's place in it. The stack of
+/// frames doubles as the path reported in errors.
+#[derive(Debug, Clone)]
+enum Frame {
+    /// An array, with the index of the current element
+    Array(usize),
+    /// An object, with the number of keys read so far and the key of the
+    /// current member once it has been read
+    Object(usize, Option<MemberKey>),
+}
+
+/// The key of the object member being read.
+#[derive(Debug, Clone)]
+enum MemberKey {
+    /// By the offset of the start of the key in the input
+    At(usize),
+    /// Decoded, because the key has been dropped from the source's buffer
+    Owned(String),
+}
+
+/// An unfinished token, as far as it has been read. Offsets are into the
+/// source's text, which is not released until the token is complete.
+#[derive(Debug, Clone)]
+enum Partial {
+    /// A string, decoded up to the run of plain text starting at `run`, which
+    /// has been scanned up to `scanned`
+    String { decoded: Option<String>, run: usize, scanned: usize },
+    /// A number that ran out in a run of digits, scanned up to `scanned`
+    Digits { scanned: usize, hex: bool },
+    /// A comment or unquoted key, scanned up to the given offset
+    Scanned(usize),
+}
+
+/// The token the reader expects next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// The root value
+    Root,
+    /// An element or `]`, just after `[`
+    FirstElement,
+    /// A key or `}`, just after `{`
+    FirstMember,
+    /// A value, after `,` in an array or `:` in an object
+    Value,
+    /// A key, after `,` in an object
+    Key,
+    /// The `:` after a key
+    Colon,
+    /// `,` or the closing bracket, or the end of the input after the root value
+    AfterValue,
+    /// Nothing: the document is complete, or an error occurred
+    Done,
+}
+
+/// What reading the next event produced.
+pub(crate) enum Step<'a> {
+    /// An event
+    Event(JsonEvent<'a>),
+    /// The end of the document
+    End,
+    /// The next token runs past the end of the input fed so far
+    NeedMoreInput,
+}
+
+impl<'a> JsonReader<'a> {
+    /// Creates a new reader over the given string slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The JSON string to read
+    ///
+    /// # Returns
+    ///
+    /// A new `JsonReader` borrowing the input string
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{JsonEvent, JsonReader};
+    ///
+    /// let mut reader = JsonReader::from_str("true");
+    /// assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::Bool(true)));
+    /// assert_eq!(reader.next_event().unwrap(), None);
+    /// ```
+    // `FromStr` cannot be implemented here because the reader borrows its input.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'a str) -> Self {
+        Self::with_source(StrSource::new(input))
+    }
+
+    /// Creates a new reader over the given byte slice.
+    ///
+    /// The bytes are validated as UTF-8 once, up front, exactly as
+    /// `JsonParser::from_slice` does.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The UTF-8 encoded JSON bytes to read
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(JsonReader)` - If the input is valid UTF-8
+    /// * `Err(DeserializeError)` - If the input is not valid UTF-8
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::JsonReader;
+    ///
+    /// assert!(JsonReader::from_slice(b"[1, 2]").is_ok());
+    /// assert!(JsonReader::from_slice(b"\"\xFF\"").is_err());
+    /// ```
+    pub fn from_slice(input: &'a [u8]) -> Result<Self, DeserializeError> {
+        match std::str::from_utf8(input) {
+            Ok(input) => Ok(Self::from_str(input)),
+            Err(e) => {
+                // Everything before the first invalid byte is valid UTF-8
+                let valid = std::str::from_utf8(&input[..e.valid_up_to()]).unwrap_or_default();
+                Err(DeserializeError::InvalidJson(SyntaxError::new(
+                    ErrorCode::InvalidUtf8(e.valid_up_to()),
+                    locate(valid, e.valid_up_to()),
+                    String::new(),
+                )))
+            }
+        }
+    }
+}
+
+impl<R: Read> JsonReader<'static, ReaderSource<R>> {
+    /// Creates a new reader that reads its input from `reader`.
+    ///
+    /// The input is buffered a chunk at a time and released once it has been
+    /// read, exactly as for `JsonParser::from_reader`, so reading a document
+    /// of any size takes memory proportional only to its nesting depth and
+    /// its longest token. Strings and keys are always owned.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source of the JSON text, such as a `File` or `TcpStream`
+    ///
+    /// # Returns
+    ///
+    /// A new `JsonReader` that owns the reader
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{JsonEvent, JsonReader};
+    ///
+    /// let bytes: &[u8] = br#"[1, 2, 3]"#;
+    /// let numbers = JsonReader::from_reader(bytes)
+    ///     .filter(|event| matches!(event, Ok(JsonEvent::Number(_))))
+    ///     .count();
+    ///
+    /// assert_eq!(numbers, 3);
+    /// ```
+    pub fn from_reader(reader: R) -> Self {
+        Self::with_source(ReaderSource::new(reader))
+    }
+}
+
+impl JsonReader<'static, FeedSource> {
+    // Creates a reader over input that will be fed to it in chunks
+    pub(crate) fn feeding() -> Self {
+        Self::with_source(FeedSource::default())
+    }
+
+    pub(crate) fn source_mut(&mut self) -> &mut FeedSource {
+        &mut self.source
+    }
+}
+
+impl<'a, S: Source<'a>> JsonReader<'a, S> {
+    fn with_source(source: S) -> Self {
+        Self {
+            source,
+            position: 0,
+            token_start: 0,
+            stack: Vec::new(),
+            state: State::Root,
+            starved: false,
+            over_limit: false,
+            partial: None,
+            options: ParserOptions::default(),
+            #[cfg(test)]
+            bytes_read: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// Sets the options the reader uses, such as its resource limits.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options to use in place of the defaults
+    ///
+    /// # Returns
+    ///
+    /// The reader, using the given options
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{JsonReader, ParserOptions};
+    /// use karya_json::types::error::ErrorCode;
+    ///
+    /// let options = ParserOptions { max_array_length: Some(2), ..ParserOptions::default() };
+    /// let mut reader = JsonReader::from_str("[1, 2, 3]").with_options(options);
+    ///
+    /// let error = reader.find_map(Result::err).unwrap();
+    /// assert_eq!(error.code(), Some(&ErrorCode::ArrayTooLong(2)));
+    /// ```
+    pub fn with_options(mut self, options: ParserOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Reads the next event from the document.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(JsonEvent))` - The next event
+    /// * `Ok(None)` - If the document has been read completely, including
+    ///   checking that nothing but whitespace follows it
+    /// * `Err(DeserializeError)` - If the input is invalid JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `JsonParser::parse`, except that duplicate
+    /// keys are not reported. Once an error has been returned the reader is
+    /// finished, and further calls return `Ok(None)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{JsonEvent, JsonReader};
+    /// use std::borrow::Cow;
+    ///
+    /// let mut reader = JsonReader::from_str(r#"{"id": 7}"#);
+    ///
+    /// assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::StartObject));
+    /// assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::Key(Cow::Borrowed("id"))));
+    /// assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::Number(Cow::Borrowed("7"))));
+    /// assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::EndObject));
+    /// assert_eq!(reader.next_event().unwrap(), None);
+    /// ```
+    pub fn next_event(&mut self) -> Result<Option<JsonEvent<'a>>, DeserializeError> {
+        match self.poll_event()? {
+            Step::Event(event) => Ok(Some(event)),
+            // Only a `FeedSource` asks for more input, and `IncrementalParser`
+            // polls those itself
+            Step::End | Step::NeedMoreInput => Ok(None),
+        }
+    }
+
+    // Reads the next event, or finds that more input is needed first
+    pub(crate) fn poll_event(&mut self) -> Result<Step<'a>, DeserializeError> {
+        let result = self.read_event();
+
+        // A source that failed looks like one that ended early, so its own
+        // error takes precedence over whatever that caused
+        let result = match self.source.take_error() {
+            Some(error) => Err(error),
+            None => result,
+        };
+        // Likewise for stopping at the input length limit, which the result
+        // doesn't reflect at all if the text up to it happens to be valid
+        let result = match self.options.max_input_length {
+            Some(max) if self.over_limit => {
+                Err(self.error_at(max - self.source.base(), ErrorCode::InputTooLong(max)))
+            }
+            _ => result,
+        };
+        if result.is_err() {
+            self.state = State::Done;
+        }
+        result
+    }
+
+    /// Skips the next value, including everything nested inside it.
+    ///
+    /// Call it after a `Key` event to skip that member's value, or where an
+    /// array element is expected to skip the element. Where an object key is
+    /// expected the whole member is skipped. If the enclosing container has no
+    /// more values, its end event is consumed instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `next_event`; the skipped text is still
+    /// checked for syntax errors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{JsonEvent, JsonReader};
+    /// use std::borrow::Cow;
+    ///
+    /// let mut reader = JsonReader::from_str(r#"{"big": [[1, 2], {"x": 3}], "id": 7}"#);
+    ///
+    /// reader.next_event().unwrap(); // StartObject
+    /// reader.next_event().unwrap(); // Key("big")
+    /// reader.skip_value().unwrap();
+    ///
+    /// assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::Key(Cow::Borrowed("id"))));
+    /// ```
+    pub fn skip_value(&mut self) -> Result<(), DeserializeError> {
+        let mut depth = 0usize;
+        loop {
+            match self.next_event()? {
+                Some(JsonEvent::StartObject | JsonEvent::StartArray) => depth += 1,
+                Some(JsonEvent::EndObject | JsonEvent::EndArray) => {
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                    depth -= 1;
+                }
+                // A key is always followed by its value
+                Some(JsonEvent::Key(_)) => continue,
+                Some(_) => {}
+                None => return Ok(()),
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    // Builds a syntax error located at the token behind the last event, for
+    // problems only noticed once the event has been handed out
+    pub(crate) fn token_error(&self, code: ErrorCode) -> DeserializeError {
+        self.error_at(self.token_start, code)
+    }
+
+    // Builds a warning located at the token behind the last event
+    pub(crate) fn token_warning(&self, code: WarningCode) -> ParseWarning {
+        ParseWarning::new(code, self.source.locate(self.token_start), self.json_pointer())
+    }
+
+    pub(crate) fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    // Advances the state machine by one event
+    fn read_event(&mut self) -> Result<Step<'a>, DeserializeError> {
+        loop {
+            self.skip_whitespace();
+            // The offsets kept for an unfinished token stay valid until it ends
+            if self.partial.is_none() {
+                self.release();
+            }
+            self.token_start = self.position;
+            self.starved = false;
+            let state = self.state;
+
+            let token = self.read_token();
+            if self.starved {
+                // The token runs past the input fed so far. Go back to its
+                // start; reading it again once more input has arrived carries
+                // on from where `partial` says this attempt got to.
+                self.position = self.token_start;
+                self.state = state;
+                return Ok(Step::NeedMoreInput);
+            }
+            if let Some(step) = token? {
+                return Ok(step);
+            }
+        }
+    }
+
+    // Reads the token at the current position. Punctuation that produces no
+    // event of its own gives `None`.
+    fn read_token(&mut self) -> Result<Option<Step<'a>>, DeserializeError> {
+        let state = self.state;
+        if state != State::Done && self.options.dialect.allows_comments() && self.peek_byte() == Some(b'/') {
+            self.skip_comment()?;
+            return Ok(None);
+        }
+
+        let trailing_commas = self.options.dialect.allows_trailing_commas();
+        let event = match state {
+            State::Value
+                if trailing_commas
+                    && matches!(self.stack.last(), Some(Frame::Array(_)))
+                    && self.peek_byte() == Some(b']') =>
+            {
+                self.close()
+            }
+            State::Key if trailing_commas && self.peek_byte() == Some(b'}') => self.close(),
+            State::Root | State::Value => self.read_value()?,
+            State::FirstElement if self.peek_byte() == Some(b']') => self.close(),
+            State::FirstElement => self.read_value()?,
+            State::FirstMember if self.peek_byte() == Some(b'}') => self.close(),
+            State::FirstMember | State::Key => self.read_key()?,
+            State::Colon => {
+                self.expect_char(':')?;
+                self.state = State::Value;
+                return Ok(None);
+            }
+            State::AfterValue => {
+                let close = match self.stack.last() {
+                    Some(Frame::Array(_)) => ']',
+                    Some(Frame::Object(..)) => '}',
+                    None => {
+                        // Ensure we've consumed all inputs
+                        if self.peek_byte().is_some() {
+                            return Err(self.error(ErrorCode::TrailingCharacters));
+                        }
+                        self.state = State::Done;
+                        return Ok(Some(Step::End));
+                    }
+                };
+
+                match self.peek_char() {
+                    Some(',') => {
+                        self.position += 1;
+                        match self.stack.last_mut() {
+                            Some(Frame::Array(index)) => {
+                                *index += 1;
+                                self.state = State::Value;
+                            }
+                            Some(Frame::Object(_, key)) => {
+                                // Until the next key is read the path points at the object itself
+                                *key = None;
+                                self.state = State::Key;
+                            }
+                            None => {}
+                        }
+                        return Ok(None);
+                    }
+                    Some(c) if c == close => self.close(),
+                    Some(c) => {
+                        return Err(self.error(ErrorCode::ExpectedCommaOrClose { close, found: c }));
+                    }
+                    None if close == ']' => {
+                        return Err(self.error(ErrorCode::UnterminatedArray));
+                    }
+                    None => {
+                        return Err(self.error(ErrorCode::UnterminatedObject));
+                    }
+                }
+            }
+            State::Done => return Ok(Some(Step::End)),
+        };
+        Ok(Some(Step::Event(event)))
+    }
+
+    // Reads a value, or the start of a container
+    fn read_value(&mut self) -> Result<JsonEvent<'a>, DeserializeError> {
+        if let Some(Frame::Array(index)) = self.stack.last()
+            && let Some(max) = self.options.max_array_length
+            && *index >= max
+        {
+            return Err(self.error(ErrorCode::ArrayTooLong(max)));
+        }
+
+        let event = match self.peek_byte() {
+            Some(b'"') => JsonEvent::String(self.parse_string()?),
+            Some(b'\'') if self.json5() => JsonEvent::String(self.parse_string()?),
+            Some(b'-' | b'0'..=b'9') => JsonEvent::Number(self.parse_number()?),
+            Some(b'+' | b'.' | b'I' | b'N') if self.json5() => JsonEvent::Number(self.parse_number()?),
+            Some(b't' | b'f') => JsonEvent::Bool(self.parse_boolean()?),
+            Some(b'n') => {
+                self.parse_null()?;
+                JsonEvent::Null
+            }
+            Some(b'[') => {
+                self.check_depth()?;
+                self.position += 1;
+                self.stack.push(Frame::Array(0));
+                self.state = State::FirstElement;
+                return Ok(JsonEvent::StartArray);
+            }
+            Some(b'{') => {
+                self.check_depth()?;
+                self.position += 1;
+                self.stack.push(Frame::Object(0, None));
+                self.state = State::FirstMember;
+                return Ok(JsonEvent::StartObject);
+            }
+            Some(_) => {
+                let c = self.peek_char().unwrap_or_default();
+                return Err(self.error(ErrorCode::UnexpectedCharacter(c)));
+            }
+            None => return Err(self.error(ErrorCode::UnexpectedEof)),
+        };
+        self.state = State::AfterValue;
+        Ok(event)
+    }
+
+    // Reads an object key; the colon after it is left for the next event
+    fn read_key(&mut self) -> Result<JsonEvent<'a>, DeserializeError> {
+        if let Some(Frame::Object(members, _)) = self.stack.last()
+            && let Some(max) = self.options.max_object_length
+            && *members >= max
+        {
+            return Err(self.error(ErrorCode::ObjectTooLong(max)));
+        }
+
+        let key_start = self.position;
+        let key = self.parse_key()?;
+
+        // Keys are recorded by offset and only decoded if an error needs the path
+        if let Some(Frame::Object(members, current)) = self.stack.last_mut() {
+            *members += 1;
+            *current = Some(MemberKey::At(self.source.base() + key_start));
+        }
+        self.state = State::Colon;
+        Ok(JsonEvent::Key(key))
+    }
+
+    // Parses an object key, which JSON5 also allows to be an identifier
+    fn parse_key(&mut self) -> Result<Cow<'a, str>, DeserializeError> {
+        match self.peek_char() {
+            Some(c) if self.json5() && is_identifier_start(c) => self.parse_identifier(),
+            _ => self.parse_string(),
+        }
+    }
+
+    // Parses an unquoted JSON5 key, an ECMAScript identifier name without
+    // escape sequences
+    fn parse_identifier(&mut self) -> Result<Cow<'a, str>, DeserializeError> {
+        let start = self.position;
+        let limit = self.options.max_string_length.unwrap_or(usize::MAX);
+        if let Some(Partial::Scanned(scanned)) = self.partial.take() {
+            self.position = scanned;
+        }
+
+        while let Some(c) = self.peek_char()
+            && (is_identifier_start(c) || c.is_alphanumeric() || c == '\u{200C}' || c == '\u{200D}')
+        {
+            self.position += c.len_utf8();
+            if self.position - start > limit {
+                return Err(self.error_at(start, ErrorCode::StringTooLong(limit)));
+            }
+        }
+        if self.starved {
+            self.partial = Some(Partial::Scanned(self.position));
+        }
+        Ok(self.slice(start, self.position))
+    }
+
+    // Skips a `//` or `/* */` comment
+    fn skip_comment(&mut self) -> Result<(), DeserializeError> {
+        let start = self.position;
+        self.position += 1;
+        let kind = self.peek_byte();
+        // Carry on through a comment that ran past the end of the input fed so far
+        let body = match self.partial.take() {
+            Some(Partial::Scanned(scanned)) => scanned,
+            _ => start + 2,
+        };
+        match kind {
+            Some(b'/') => {
+                // A line comment runs up to the end of the line, which in JSON5
+                // may also be a Unicode line or paragraph separator
+                let json5 = self.json5();
+                self.position = body;
+                while let Some(c) = self.peek_char() {
+                    if c == '\n' || c == '\r' || (json5 && matches!(c, '\u{2028}' | '\u{2029}')) {
+                        break;
+                    }
+                    self.position += c.len_utf8();
+                }
+                if self.starved {
+                    self.partial = Some(Partial::Scanned(self.position));
+                }
+                Ok(())
+            }
+            Some(b'*') => {
+                self.position = body;
+                loop {
+                    match self.next_byte() {
+                        Some(b'*') if self.peek_byte() == Some(b'/') => {
+                            self.position += 1;
+                            return Ok(());
+                        }
+                        Some(_) => {}
+                        None => {
+                            if self.starved {
+                                // A `*` at the very end may yet be followed by `/`
+                                self.partial = Some(Partial::Scanned((self.position - 1).max(start + 2)));
+                            }
+                            return Err(self.error_at(start, ErrorCode::UnterminatedComment));
+                        }
+                    }
+                }
+            }
+            _ => Err(self.error_at(start, ErrorCode::UnexpectedCharacter('/'))),
+        }
+    }
+
+    // Checks that a container can open at the current position
+    fn check_depth(&self) -> Result<(), DeserializeError> {
+        match self.options.max_depth {
+            Some(max) if self.stack.len() >= max => Err(self.error(ErrorCode::DepthLimitExceeded(max))),
+            _ => Ok(()),
+        }
+    }
+
+    // Consumes the closing bracket of the innermost container
+    fn close(&mut self) -> JsonEvent<'a> {
+        self.position += 1;
+        self.state = State::AfterValue;
+        match self.stack.pop() {
+            Some(Frame::Array(_)) => JsonEvent::EndArray,
+            _ => JsonEvent::EndObject,
+        }
+    }
+
+    // String parsing according to RFC 8259. Strings without escape sequences
+    // are borrowed from the input where the source allows it; the first escape
+    // switches to an owned copy.
+    fn parse_string(&mut self) -> Result<Cow<'a, str>, DeserializeError> {
+        let quote = self.position;
+        let json5 = self.json5();
+        // JSON5 strings may also be single-quoted, and contain control
+        // characters other than line breaks
+        let delimiter = match self.peek_byte() {
+            Some(b'\'') if json5 => b'\'',
+            _ => b'"',
+        };
+        self.expect_char(char::from(delimiter))?;
+        let mut result: Option<String> = None;
+        let limit = self.options.max_string_length.unwrap_or(usize::MAX);
+        let mut start = self.position;
+        if let Some(Partial::String { decoded, run, scanned }) = self.partial.take() {
+            result = decoded;
+            start = run;
+            self.position = scanned;
+        }
+
+        loop {
+            // Take the run of bytes up to the next quote, backslash or control
+            // character in one go. All three are ASCII, so the run always ends
+            // on a character boundary.
+            let room = limit - result.as_ref().map_or(0, String::len);
+            while let Some(b) = self.peek_byte() {
+                if b == delimiter || b == b'\\' || (b < 0x20 && !(json5 && b != b'\n' && b != b'\r')) {
+                    break;
+                }
+                if self.position - start == room {
+                    return Err(self.error_at(quote, ErrorCode::StringTooLong(limit)));
+                }
+                self.position += 1;
+            }
+            let end = self.position;
+
+            match self.next_byte() {
+                Some(b) if b == delimiter => {
+                    return Ok(match result {
+                        Some(mut owned) => {
+                            owned.push_str(&self.source.text()[start..end]);
+                            Cow::Owned(owned)
+                        }
+                        None => self.slice(start, end),
+                    });
+                }
+                Some(b'\\') => {
+                    let mut owned = result.take().unwrap_or_default();
+                    owned.push_str(&self.source.text()[start..end]);
+                    let decoded = owned.len();
+                    let escaped = self.parse_escape(&mut owned);
+                    if self.starved {
+                        // Read the escape again once all of it has arrived
+                        owned.truncate(decoded);
+                        self.partial = Some(Partial::String { decoded: Some(owned), run: end, scanned: end });
+                        return Err(self.error(ErrorCode::UnterminatedString));
+                    }
+                    escaped?;
+                    if owned.len() > limit {
+                        return Err(self.error_at(quote, ErrorCode::StringTooLong(limit)));
+                    }
+                    result = Some(owned);
+                }
+                Some(b) => {
+                    // RFC 8259 prohibits unescaped control characters (U+0000 through U+001F)
+                    return Err(self.error_at(
+                        self.position - 1,
+                        ErrorCode::ControlCharacterInString(b),
+                    ));
+                }
+                None => {
+                    if self.starved {
+                        self.partial = Some(Partial::String { decoded: result, run: start, scanned: end });
+                    }
+                    return Err(self.error(ErrorCode::UnterminatedString));
+                }
+            }
+            start = self.position;
+        }
+    }
+
+    // Escape sequence parsing, called after the backslash has been consumed
+    fn parse_escape(&mut self, result: &mut String) -> Result<(), DeserializeError> {
+        let escape_start = self.position - 1;
+        if self.json5() {
+            return self.parse_json5_escape(escape_start, result);
+        }
+        match self.next_byte() {
+            Some(b'"') => result.push('"'),
+            Some(b'\\') => result.push('\\'),
+            Some(b'/') => result.push('/'),
+            Some(b'b') => result.push('\u{0008}'),
+            Some(b'f') => result.push('\u{000C}'),
+            Some(b'n') => result.push('\n'),
+            Some(b'r') => result.push('\r'),
+            Some(b't') => result.push('\t'),
+            Some(b'u') => result.push(self.parse_unicode_escape(escape_start)?),
+            Some(_) => {
+                // RFC 8259 only allows specific escape sequences
+                self.position -= 1;
+                let c = self.peek_char().unwrap_or_default();
+                return Err(self.error_at(escape_start, ErrorCode::InvalidEscape(c)));
+            }
+            None => {
+                return Err(self.error(ErrorCode::UnterminatedString));
+            }
+        }
+        Ok(())
+    }
+
+    // JSON5 escape sequences, which add `\'`, `\v`, `\0` and `\xHH` to those
+    // of JSON, continue the string across escaped line breaks, and let any
+    // other character but a digit escape itself
+    fn parse_json5_escape(&mut self, escape_start: usize, result: &mut String) -> Result<(), DeserializeError> {
+        match self.next_byte() {
+            Some(b'b') => result.push('\u{0008}'),
+            Some(b'f') => result.push('\u{000C}'),
+            Some(b'n') => result.push('\n'),
+            Some(b'r') => result.push('\r'),
+            Some(b't') => result.push('\t'),
+            Some(b'v') => result.push('\u{000B}'),
+            Some(b'0') if !matches!(self.peek_byte(), Some(b'0'..=b'9')) => result.push('\0'),
+            Some(b'x') => result.push(self.parse_hex_escape(escape_start)?),
+            Some(b'u') => result.push(self.parse_unicode_escape(escape_start)?),
+            // Line continuations
+            Some(b'\n') => {}
+            Some(b'\r') => {
+                if self.peek_byte() == Some(b'\n') {
+                    self.position += 1;
+                }
+            }
+            Some(b) if !b.is_ascii_digit() => {
+                self.position -= 1;
+                match self.next_char() {
+                    Some('\u{2028}' | '\u{2029}') | None => {}
+                    Some(c) => result.push(c),
+                }
+            }
+            Some(_) => {
+                self.position -= 1;
+                let c = self.peek_char().unwrap_or_default();
+                return Err(self.error_at(escape_start, ErrorCode::InvalidEscape(c)));
+            }
+            None => {
+                return Err(self.error(ErrorCode::UnterminatedString));
+            }
+        }
+        Ok(())
+    }
+
+    // Parses the two hex digits of a JSON5 `\xHH` escape, a Latin-1 character.
+    // A bad digit makes the whole escape invalid, as it is not a Unicode escape.
+    fn parse_hex_escape(&mut self, escape_start: usize) -> Result<char, DeserializeError> {
+        let mut code = 0u8;
+        for _ in 0..2 {
+            let digit = match self.next_char() {
+                Some(c) => c.to_digit(16),
+                None => return Err(self.error(ErrorCode::UnterminatedString)),
+            };
+            match digit {
+                Some(digit) => code = code * 16 + digit as u8,
+                None => return Err(self.error_at(escape_start, ErrorCode::InvalidEscape('x'))),
+            }
+        }
+        Ok(char::from(code))
+    }
+
+    // Number parsing. JSON5 literals that fall outside the JSON grammar are
+    // rewritten into it, so that every literal converts the same way.
+    fn parse_number(&mut self) -> Result<Cow<'a, str>, DeserializeError> {
+        let start = self.position;
+        let json5 = self.json5();
+
+        // A number that ran out in a run of digits still does if only more
+        // digits have arrived. Anything else is read from the start again,
+        // which can only happen a few times before the number ends.
+        if let Some(Partial::Digits { scanned, hex }) = self.partial.take() {
+            self.position = scanned;
+            while let Some(b) = self.peek_byte()
+                && (b.is_ascii_digit() || (hex && b.is_ascii_hexdigit()))
+            {
+                self.position += 1;
+            }
+            if self.starved {
+                self.partial = Some(Partial::Digits { scanned: self.position, hex });
+                return Err(self.error(ErrorCode::InvalidNumber));
+            }
+            self.position = start;
+        }
+
+        // Handle negative numbers, and in JSON5 positive ones
+        let negative = self.peek_byte() == Some(b'-');
+        let plus = json5 && self.peek_byte() == Some(b'+');
+        if negative || plus {
+            self.position += 1;
+        }
+        let digits = self.position;
+        let mut hex = false;
+        let mut bare_point = None;
+
+        match self.peek_byte() {
+            Some(b'I') if json5 => self.expect_literal("Infinity")?,
+            Some(b'N') if json5 => self.expect_literal("NaN")?,
+            Some(b'0') if json5 && self.skip_hex_prefix() => {
+                hex = true;
+                let hex_digits = self.position;
+                while let Some(b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F') = self.peek_byte() {
+                    self.position += 1;
+                }
+                if self.starved {
+                    self.partial = Some(Partial::Digits { scanned: self.position, hex: true });
+                }
+                if self.position == hex_digits {
+                    return Err(self.error(ErrorCode::InvalidNumber));
+                }
+            }
+            _ => {
+                // Parse integer part, which JSON5 allows to be left out before
+                // a decimal point
+                match self.peek_byte() {
+                    Some(b'0') => {
+                        self.position += 1;
+                    }
+                    Some(b'1'..=b'9') => {
+                        self.skip_digits();
+                    }
+                    Some(b'.') if json5 => {}
+                    _ => {
+                        return Err(self.error(ErrorCode::InvalidNumber));
+                    }
+                }
+
+                // Parse decimal part. JSON5 allows the digits after the point
+                // to be left out, though not on both sides of it.
+                if self.peek_byte() == Some(b'.') {
+                    self.position += 1;
+
+                    if self.skip_digits() == 0 {
+                        if !json5 || self.position - 1 == digits {
+                            return Err(self.error(ErrorCode::MissingFractionDigits));
+                        }
+                        bare_point = Some(self.position - digits);
+                    }
+                }
+
+                // Parse exponent
+                if let Some(b'e' | b'E') = self.peek_byte() {
+                    self.position += 1;
+
+                    // Handle exponent sign
+                    if let Some(b'+' | b'-') = self.peek_byte() {
+                        self.position += 1;
+                    }
+
+                    if self.skip_digits() == 0 {
+                        return Err(self.error(ErrorCode::MissingExponentDigits));
+                    }
+                }
+            }
+        }
+
+        if let Some(max) = self.options.max_number_length
+            && self.position - start > max
+        {
+            return Err(self.error_at(start, ErrorCode::NumberTooLong(max)));
+        }
+
+        let text = &self.source.text()[digits..self.position];
+        let leading_point = text.starts_with('.');
+        if !(plus || hex || leading_point || bare_point.is_some() || (negative && text == "NaN")) {
+            // The literal is sliced straight out of the input where the source allows it
+            return Ok(self.slice(start, self.position));
+        }
+
+        let mut literal = String::with_capacity(self.position - start + 1);
+        if negative && text != "NaN" {
+            literal.push('-');
+        }
+        if hex {
+            match u128::from_str_radix(&text[2..], 16) {
+                Ok(value) => literal.push_str(&value.to_string()),
+                Err(_) => {
+                    let literal = self.source.text()[start..self.position].to_owned();
+                    return Err(self.error_at(start, ErrorCode::NumberOutOfRange(literal)));
+                }
+            }
+        } else {
+            if leading_point {
+                literal.push('0');
+            }
+            match bare_point {
+                Some(end) => {
+                    literal.push_str(&text[..end]);
+                    literal.push('0');
+                    literal.push_str(&text[end..]);
+                }
+                None => literal.push_str(text),
+            }
+        }
+        Ok(Cow::Owned(literal))
+    }
+
+    // Consumes the `0x` or `0X` that starts a hexadecimal number, if present
+    fn skip_hex_prefix(&mut self) -> bool {
+        self.position += 1;
+        if let Some(b'x' | b'X') = self.peek_byte() {
+            self.position += 1;
+            return true;
+        }
+        self.position -= 1;
+        false
+    }
+
+    // Boolean parsing
+    fn parse_boolean(&mut self) -> Result<bool, DeserializeError> {
+        match self.peek_byte() {
+            Some(b't') => {
+                self.expect_literal("true")?;
+                Ok(true)
+            }
+            Some(b'f') => {
+                self.expect_literal("false")?;
+                Ok(false)
+            }
+            Some(_) => {
+                let c = self.peek_char().unwrap_or_default();
+                Err(self.error(ErrorCode::UnexpectedCharacter(c)))
+            }
+            None => Err(self.error(ErrorCode::UnexpectedEof)),
+        }
+    }
+
+    // Null parsing
+    fn parse_null(&mut self) -> Result<(), DeserializeError> {
+        self.expect_literal("null")
+    }
+
+    // Helper methods
+    fn parse_hex_digits(&mut self, count: usize) -> Result<u32, DeserializeError> {
+        let mut code_point = 0u32;
+        for _ in 0..count {
+            code_point = code_point * 16
+                + match self.next_char() {
+                    Some(c) => c.to_digit(16).ok_or_else(|| {
+                        self.error_at(
+                            self.position - c.len_utf8(),
+                            ErrorCode::InvalidUnicodeEscape(c),
+                        )
+                    })?,
+                    None => {
+                        return Err(self.error(ErrorCode::UnterminatedUnicodeEscape));
+                    }
+                };
+        }
+        Ok(code_point)
+    }
+
+    fn parse_unicode_escape(&mut self, escape_start: usize) -> Result<char, DeserializeError> {
+        let code_point = self.parse_hex_digits(4)?;
+
+        // Handle surrogate pairs according to RFC 8259
+        if (0xD800..=0xDBFF).contains(&code_point) {
+            // High surrogate, must be followed by low surrogate
+            if self.peek_byte() == Some(b'\\') {
+                let low_start = self.position;
+                self.position += 1; // Consume the backslash
+                if self.peek_byte() == Some(b'u') {
+                    self.position += 1; // Consume the 'u'
+                    let low_surrogate = self.parse_hex_digits(4)?;
+                    return if (0xDC00..=0xDFFF).contains(&low_surrogate) {
+                        // Calculate the combined code point
+                        let combined = 0x10000 + (((code_point - 0xD800) << 10) | (low_surrogate - 0xDC00));
+                        char::from_u32(combined).ok_or_else(|| {
+                            self.error_at(escape_start, ErrorCode::InvalidCodePoint(combined))
+                        })
+                    } else {
+                        Err(self.error_at(
+                            low_start,
+                            ErrorCode::InvalidLowSurrogate(low_surrogate)
+                        ))
+                    }
+                }
+            }
+            return Err(self.error_at(
+                escape_start,
+                ErrorCode::LoneSurrogate(code_point)
+            ));
+        } else if (0xDC00..=0xDFFF).contains(&code_point) {
+            return Err(self.error_at(
+                escape_start,
+                ErrorCode::LoneSurrogate(code_point)
+            ));
+        }
+
+        char::from_u32(code_point).ok_or_else(|| {
+            self.error_at(escape_start, ErrorCode::InvalidCodePoint(code_point))
+        })
+    }
+
+    fn expect_char(&mut self, expected: char) -> Result<(), DeserializeError> {
+        match self.next_char() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.error_at(
+                self.position - c.len_utf8(),
+                ErrorCode::ExpectedCharacter {
+                    expected,
+                    found: Some(c),
+                },
+            )),
+            None => Err(self.error(ErrorCode::ExpectedCharacter {
+                expected,
+                found: None,
+            })),
+        }
+    }
+
+    fn expect_literal(&mut self, literal: &str) -> Result<(), DeserializeError> {
+        for expected in literal.chars() {
+            match self.next_char() {
+                Some(c) if c == expected => continue,
+                Some(c) => {
+                    return Err(self.error_at(
+                        self.position - c.len_utf8(),
+                        ErrorCode::ExpectedCharacter {
+                            expected,
+                            found: Some(c),
+                        },
+                    ));
+                }
+                None => {
+                    return Err(self.error(ErrorCode::ExpectedCharacter {
+                        expected,
+                        found: None,
+                    }));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Builds a syntax error located at the current position
+    fn error(&self, code: ErrorCode) -> DeserializeError {
+        self.error_at(self.position, code)
+    }
+
+    // Builds a syntax error located at the given byte offset
+    fn error_at(&self, offset: usize, code: ErrorCode) -> DeserializeError {
+        // A starved token is read again once more input arrives, so its error
+        // is thrown away. Locating it would cost time in proportion to the
+        // buffered text and nesting depth on every chunk fed.
+        if self.starved {
+            let position = Position { offset, line: 0, column: 0 };
+            return DeserializeError::InvalidJson(SyntaxError::new(code, position, String::new()));
+        }
+        DeserializeError::InvalidJson(SyntaxError::new(
+            code,
+            self.source.locate(offset),
+            self.json_pointer(),
+        ))
+    }
+
+    // Renders the current path as a JSON Pointer (RFC 6901)
+    fn json_pointer(&self) -> String {
+        let mut pointer = String::new();
+        for frame in &self.stack {
+            let key = match frame {
+                Frame::Array(index) => {
+                    pointer.push('/');
+                    pointer.push_str(&index.to_string());
+                    continue;
+                }
+                Frame::Object(_, None) => continue,
+                Frame::Object(_, Some(MemberKey::At(offset))) => {
+                    decode_key(self.source.text(), offset - self.source.base(), self.options.dialect)
+                }
+                Frame::Object(_, Some(MemberKey::Owned(key))) => key.clone(),
+            };
+            pointer.push('/');
+            pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+        }
+        pointer
+    }
+
+    // Lets the source drop text before the current position. Called only
+    // between tokens, when no offsets into the text are held except the keys
+    // on the path, which are decoded first if they are about to go.
+    fn release(&mut self) {
+        let len = self.source.discardable(self.position);
+        if len == 0 {
+            return;
+        }
+
+        let base = self.source.base();
+        for frame in self.stack.iter_mut().rev() {
+            if let Frame::Object(_, Some(key)) = frame {
+                // Keys further out come earlier in the input, so once one has
+                // been decoded all of them have
+                let MemberKey::At(offset) = key else { break };
+                *key = MemberKey::Owned(decode_key(self.source.text(), *offset - base, self.options.dialect));
+            }
+        }
+        self.source.discard(len);
+        self.position -= len;
+    }
+
+    // Returns part of the text with the input's lifetime, copying it only if
+    // the source cannot lend it out
+    fn slice(&self, start: usize, end: usize) -> Cow<'a, str> {
+        match self.source.borrow(start, end) {
+            Some(text) => Cow::Borrowed(text),
+            None => Cow::Owned(self.source.text()[start..end].to_owned()),
+        }
+    }
+
+    // Advances past a run of ASCII digits, returning how many were consumed
+    fn skip_digits(&mut self) -> usize {
+        let start = self.position;
+        while let Some(b'0'..=b'9') = self.peek_byte() {
+            self.position += 1;
+        }
+        if self.starved {
+            self.partial = Some(Partial::Digits { scanned: self.position, hex: false });
+        }
+        self.position - start
+    }
+
+    // Reading past the end of the text asks the source for more
+    #[inline]
+    fn peek_byte(&mut self) -> Option<u8> {
+        loop {
+            if let Some(&b) = self.source.text().as_bytes().get(self.position) {
+                // Past the input length limit the text is treated as ending
+                if self.options.max_input_length.is_some_and(|max| self.source.base() + self.position >= max) {
+                    self.over_limit = true;
+                    return None;
+                }
+                #[cfg(test)]
+                {
+                    self.bytes_read += 1;
+                }
+                return Some(b);
+            }
+            if !self.source.fill() {
+                // The text can only run out early while a source is being fed
+                self.starved |= !self.source.is_final();
+                return None;
+            }
+        }
+    }
+
+    fn next_byte(&mut self) -> Option<u8> {
+        let b = self.peek_byte();
+        if b.is_some() {
+            self.position += 1;
+        }
+        b
+    }
+
+    // Decodes the full character at the current position. Only needed where a
+    // non-ASCII character may have to be reported back to the caller.
+    fn peek_char(&mut self) -> Option<char> {
+        self.peek_byte()?;
+        self.source.text()[self.position..].chars().next()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.peek_char();
+        if let Some(c) = c {
+            self.position += c.len_utf8();
+        }
+        c
+    }
+
+    // RFC 8259 only allows space, tab, line feed and carriage return between
+    // tokens; other Unicode whitespace is accepted only when asked for, or in
+    // JSON5, which also counts the byte order mark as whitespace
+    fn skip_whitespace(&mut self) {
+        let json5 = self.json5();
+        while let Some(b) = self.peek_byte() {
+            match b {
+                b' ' | b'\t' | b'\n' | b'\r' => self.position += 1,
+                _ if self.options.lenient_whitespace || json5 => match self.peek_char() {
+                    Some(c) if c.is_whitespace() || (json5 && c == '\u{FEFF}') => self.position += c.len_utf8(),
+                    _ => break,
+                },
+                _ => break,
+            }
+        }
+    }
+
+    // Whether the input is JSON5
+    fn json5(&self) -> bool {
+        self.options.dialect == Dialect::Json5
+    }
+}
+
+impl<'a, S: Source<'a>> Iterator for JsonReader<'a, S> {
+    type Item = Result<JsonEvent<'a>, DeserializeError>;
+
+    /// Returns the next event, as `next_event` does. Iteration stops at the
+    /// end of the document or after the first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+// Decodes the key that starts at `offset` in `text`. The key parsed
+// successfully once already, so it will again.
+fn decode_key(text: &str, offset: usize, dialect: Dialect) -> String {
+    JsonReader::from_str(&text[offset..])
+        .with_options(ParserOptions { dialect, ..ParserOptions::default() })
+        .parse_key()
+        .map(Cow::into_owned)
+        .unwrap_or_default()
+}
+
+// Whether a JSON5 identifier may start with the character. Letters stand in
+// for the Unicode ID_Start property.
+fn is_identifier_start(c: char) -> bool {
+    c == '$' || c == '_' || c.is_alphabetic()
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::source::private::Source as _;
+
+    #[test]
+    fn test_parse_string() {
+        let mut reader = JsonReader::from_str(r#""Hello, World!""#);
+        assert_eq!(reader.parse_string().unwrap(), "Hello, World!");
+    }
+
+    #[test]
+    fn test_parse_boolean() {
+        let mut reader = JsonReader::from_str("true");
+        assert!(reader.parse_boolean().unwrap());
+
+        let mut reader = JsonReader::from_str("false");
+        assert!(!reader.parse_boolean().unwrap());
+    }
+
+    #[test]
+    fn test_parse_null() {
+        let mut reader = JsonReader::from_str("null");
+        assert!(reader.parse_null().is_ok());
+    }
+
+    #[test]
+    fn test_unicode_escape_sequences() {
+        // Basic Unicode escape
+        let mut reader = JsonReader::from_str(r#""\u0041\u0042\u0043""#);
+        assert_eq!(reader.parse_string().unwrap(), "ABC");
+
+        // Surrogate pair (represents 😀 emoji, U+1F600)
+        let mut reader = JsonReader::from_str(r#""\uD83D\uDE00""#);
+        assert_eq!(reader.parse_string().unwrap(), "😀");
+    }
+
+    #[test]
+    fn test_invalid_surrogate_pairs() {
+        // High surrogate without low surrogate
+        let mut reader = JsonReader::from_str(r#""\uD83D""#);
+        assert!(reader.parse_string().is_err());
+
+        // Low surrogate without high surrogate
+        let mut reader = JsonReader::from_str(r#""\uDE00""#);
+        assert!(reader.parse_string().is_err());
+
+        // High surrogate followed by something that's not a low surrogate
+        let mut reader = JsonReader::from_str(r#""\uD83Dz""#);
+        assert!(reader.parse_string().is_err());
+    }
+
+    // Reads the whole input, returning every event
+    fn events(input: &str) -> Vec<JsonEvent<'_>> {
+        JsonReader::from_str(input).collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn test_events() {
+        use JsonEvent::*;
+
+        assert_eq!(
+            events(r#"{"a": [1, -2.50e3, "x\ty"], "b": {}, "c": [], "d": null, "e": false}"#),
+            vec![
+                StartObject,
+                Key("a".into()),
+                StartArray,
+                Number("1".into()),
+                Number("-2.50e3".into()),
+                String("x\ty".into()),
+                EndArray,
+                Key("b".into()),
+                StartObject,
+                EndObject,
+                Key("c".into()),
+                StartArray,
+                EndArray,
+                Key("d".into()),
+                Null,
+                Key("e".into()),
+                Bool(false),
+                EndObject,
+            ]
+        );
+        assert_eq!(events(" 42 "), vec![Number("42".into())]);
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let mut reader = JsonReader::from_str(r#"[{"a": [1, {"b": 2}]}, "keep", 3]"#);
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::StartArray));
+        reader.skip_value().unwrap();
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::String("keep".into())));
+        reader.skip_value().unwrap();
+        // With no values left, the end of the array is skipped
+        reader.skip_value().unwrap();
+        assert_eq!(reader.next_event().unwrap(), None);
+
+        // Where a key is expected, the whole member goes
+        let mut reader = JsonReader::from_str(r#"{"a": {"x": 1}, "b": 2}"#);
+        reader.next_event().unwrap();
+        reader.skip_value().unwrap();
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::Key("b".into())));
+
+        // Skipped text is still checked
+        let mut reader = JsonReader::from_str("[[1, 2 3], 4]");
+        reader.next_event().unwrap();
+        assert!(reader.skip_value().is_err());
+    }
+
+    #[test]
+    fn test_errors_end_iteration() {
+        let mut reader = JsonReader::from_str("[1, @, 2]");
+        assert!(matches!(reader.next(), Some(Ok(JsonEvent::StartArray))));
+        assert!(matches!(reader.next(), Some(Ok(JsonEvent::Number(_)))));
+        match reader.next() {
+            Some(Err(error)) => {
+                assert_eq!(error.code(), Some(&ErrorCode::UnexpectedCharacter('@')));
+                assert_eq!(error.to_string(), "Invalid JSON: Unexpected character: @ at line 1, column 5 in /1");
+            }
+            other => panic!("Expected an error, got {:?}", other),
+        }
+        assert!(reader.next().is_none());
+
+        // Trailing characters are reported once the document is complete
+        let mut reader = JsonReader::from_str("{} x");
+        assert!(reader.by_ref().take(2).all(|event| event.is_ok()));
+        assert!(matches!(reader.next(), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_whitespace() {
+        let count = |input: &str, options: ParserOptions| {
+            let events: Result<Vec<_>, _> = JsonReader::from_str(input).with_options(options).collect();
+            events.map(|events| events.len())
+        };
+
+        // Only the four whitespace characters of RFC 8259 are allowed by default
+        assert_eq!(count(" \t\r\n[ 1 ,\n\t2 ] \r\n", ParserOptions::default()).unwrap(), 4);
+        for space in ['\u{0B}', '\u{0C}', '\u{A0}', '\u{2028}', '\u{3000}'] {
+            let input = format!("[1,{}2]", space);
+            let error = count(&input, ParserOptions::default()).unwrap_err();
+            assert_eq!(error.code(), Some(&ErrorCode::UnexpectedCharacter(space)));
+
+            let lenient = ParserOptions { lenient_whitespace: true, ..ParserOptions::default() };
+            assert_eq!(count(&input, lenient).unwrap(), 4);
+        }
+    }
+
+    #[test]
+    fn test_json5() {
+        use JsonEvent::*;
+        fn read(input: &str) -> Result<Vec<JsonEvent<'_>>, Option<ErrorCode>> {
+            let json5 = ParserOptions { dialect: Dialect::Json5, ..ParserOptions::default() };
+            let events: Result<Vec<_>, _> = JsonReader::from_str(input).with_options(json5).collect();
+            events.map_err(|error| error.code().cloned())
+        }
+
+        let input = "// Header\n{ $id: 0x1F, 'single': 'it\\'s \"\\x41\\\n\\v\\0', ünï_2: [+1, .5, 5., -.5e1, /* c */ +Infinity, -NaN,], }\u{FEFF}";
+        assert_eq!(
+            read(input).unwrap(),
+            vec![
+                StartObject,
+                Key("$id".into()),
+                Number("31".into()),
+                Key("single".into()),
+                String("it's \"A\u{0B}\0".into()),
+                Key("ünï_2".into()),
+                StartArray,
+                Number("1".into()),
+                Number("0.5".into()),
+                Number("5.0".into()),
+                Number("-0.5e1".into()),
+                Number("Infinity".into()),
+                Number("NaN".into()),
+                EndArray,
+                EndObject,
+            ]
+        );
+        // Literals already in the JSON grammar are still borrowed
+        let events = read("[-Infinity, 1.5]").unwrap();
+        assert!(matches!(&events[1], Number(Cow::Borrowed("-Infinity"))));
+        assert!(matches!(&events[2], Number(Cow::Borrowed("1.5"))));
+
+        let cases = [
+            ("[1,,]", ErrorCode::UnexpectedCharacter(',')),
+            ("{a: 1,,}", ErrorCode::ExpectedCharacter { expected: '"', found: Some(',') }),
+            ("{a,}", ErrorCode::ExpectedCharacter { expected: ':', found: Some(',') }),
+            ("{1: 2}", ErrorCode::ExpectedCharacter { expected: '"', found: Some('1') }),
+            ("[1 / 2]", ErrorCode::UnexpectedCharacter('/')),
+            ("[1 /* 2", ErrorCode::UnterminatedComment),
+            ("[.]", ErrorCode::MissingFractionDigits),
+            ("[0x]", ErrorCode::InvalidNumber),
+            ("0x1000000000000000000000000000000000", ErrorCode::NumberOutOfRange("0x1000000000000000000000000000000000".into())),
+            ("'a\nb'", ErrorCode::ControlCharacterInString(b'\n')),
+            ("'\\1'", ErrorCode::InvalidEscape('1')),
+            ("'\\xG0'", ErrorCode::InvalidEscape('x')),
+            ("'\\x4'", ErrorCode::InvalidEscape('x')),
+        ];
+        for (input, code) in cases {
+            assert_eq!(read(input).unwrap_err(), Some(code), "input: {}", input);
+        }
+
+        // None of it is JSON
+        for input in ["[1,]", "{\"a\": 1,}", "{a: 1}", "'a'", "+1", ".5", "5.", "0x1", "Infinity", "NaN", "[] // c", "\"\\v\""] {
+            assert!(JsonReader::from_str(input).collect::<Result<Vec<_>, _>>().is_err(), "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_jsonc() {
+        use JsonEvent::*;
+        fn read(input: &str) -> Result<Vec<JsonEvent<'_>>, Option<ErrorCode>> {
+            let jsonc = ParserOptions { dialect: Dialect::Jsonc, ..ParserOptions::default() };
+            let events: Result<Vec<_>, _> = JsonReader::from_str(input).with_options(jsonc).collect();
+            events.map_err(|error| error.code().cloned())
+        }
+
+        let input = "/* Settings */\n{\n  // Editor\n  \"tabs\": [2, 4,], /**/\n  \"wrap\": true,\n} // end";
+        assert_eq!(
+            read(input).unwrap(),
+            vec![
+                StartObject,
+                Key("tabs".into()),
+                StartArray,
+                Number("2".into()),
+                Number("4".into()),
+                EndArray,
+                Key("wrap".into()),
+                Bool(true),
+                EndObject,
+            ]
+        );
+        // Comments separate tokens, just as whitespace does
+        assert_eq!(read("[1/**/,2]").unwrap().len(), 4);
+
+        // Everything else is as strict as JSON
+        let cases = [
+            ("{a: 1}", ErrorCode::ExpectedCharacter { expected: '"', found: Some('a') }),
+            ("['a']", ErrorCode::UnexpectedCharacter('\'')),
+            ("[+1]", ErrorCode::UnexpectedCharacter('+')),
+            ("[.5]", ErrorCode::UnexpectedCharacter('.')),
+            ("[0x1]", ErrorCode::ExpectedCommaOrClose { close: ']', found: 'x' }),
+            ("[Infinity]", ErrorCode::UnexpectedCharacter('I')),
+            ("[\"\\v\"]", ErrorCode::InvalidEscape('v')),
+            ("[1,\u{A0}2]", ErrorCode::UnexpectedCharacter('\u{A0}')),
+            ("// a\u{2028}1", ErrorCode::UnexpectedEof),
+            ("[1,,]", ErrorCode::UnexpectedCharacter(',')),
+            ("{,}", ErrorCode::ExpectedCharacter { expected: '"', found: Some(',') }),
+            ("/* open", ErrorCode::UnterminatedComment),
+        ];
+        for (input, code) in cases {
+            assert_eq!(read(input).unwrap_err(), Some(code), "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_reader_releases_parsed_text() {
+        let mut input = String::from("[");
+        for i in 0..50_000 {
+            input.push_str(&format!("{{\"id\": {}}},", i));
+        }
+        input.push_str("{}]");
+
+        let mut reader = JsonReader::from_reader(input.as_bytes());
+        let mut objects = 0;
+        let mut largest = 0;
+        while let Some(event) = reader.next_event().unwrap() {
+            if event == JsonEvent::StartObject {
+                objects += 1;
+            }
+            largest = largest.max(reader.source.text().len());
+        }
+        assert_eq!(objects, 50_001);
+        assert!(largest < input.len() / 4, "buffered {} bytes", largest);
+    }
+}
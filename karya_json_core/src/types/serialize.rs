@@ -0,0 +1,270 @@
+//! Writing values out as JSON text.
+//!
+//! JSON has no way to write the infinities and NaN that a `Float` can hold.
+//! `Display` always succeeds and writes them as `null`, which loses the value
+//! without a trace; `JsonValue::to_json_string` lets `SerializeOptions` choose
+//! between rejecting them, writing `null`, and writing JSON5's literals.
+
+use crate::types::container::private::Shape;
+use crate::types::error::{SerializeError, SerializeErrorCode};
+use crate::types::map::ObjectMap;
+use crate::types::{GenericJsonValue, MapBackend, Nested, escape_json_string, float};
+use std::borrow::Borrow;
+use std::fmt;
+
+/// Options for `JsonValue::to_json_string`.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{JsonValue, NonFinitePolicy, SerializeOptions};
+///
+/// let value = JsonValue::Arr(vec![JsonValue::Float(1.5), JsonValue::Float(f64::INFINITY)].into());
+///
+/// let error = value.to_json_string(&SerializeOptions::default()).unwrap_err();
+/// assert_eq!(error.to_string(), "Invalid value for JSON serialization: Infinity is not a finite number in /1");
+///
+/// let options = SerializeOptions { non_finite: NonFinitePolicy::Literal, ..SerializeOptions::default() };
+/// assert_eq!(value.to_json_string(&options).unwrap(), "[1.5,Infinity]");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SerializeOptions {
+    /// What to do with infinite and NaN numbers. Defaults to rejecting them.
+    pub non_finite: NonFinitePolicy,
+}
+
+/// What the serializer does with a number JSON cannot represent: an infinite
+/// or NaN `Float`, or one of JSON5's `Infinity` and `NaN` literals kept by
+/// `NumberMode::Lossless`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum NonFinitePolicy {
+    /// Fail with `SerializeErrorCode::NonFiniteNumber`, giving the number and
+    /// the JSON Pointer path to it
+    #[default]
+    Error,
+    /// Write `null` in its place, as `Display` does
+    Null,
+    /// Write `Infinity`, `-Infinity` or `NaN`. The output is then JSON5,
+    /// which `Dialect::Json5` parses back to the same numbers, but not JSON.
+    Literal,
+}
+
+impl<B: MapBackend> GenericJsonValue<B> {
+    /// Serializes the value as JSON text, applying `options`.
+    ///
+    /// The output is the same as that of `Display` except for the handling of
+    /// non-finite numbers, which `options.non_finite` controls.
+    pub fn to_json_string(&self, options: &SerializeOptions) -> Result<String, SerializeError> {
+        let mut out = String::new();
+        match write_value(&mut out, self, options) {
+            Ok(()) => Ok(out),
+            Err(WriteError::Value(error)) => Err(error),
+            Err(WriteError::Format(_)) => unreachable!("writing to a String never fails"),
+        }
+    }
+}
+
+/// Why `write_value` stopped.
+pub(crate) enum WriteError {
+    /// The output refused the text
+    Format(fmt::Error),
+    /// The value cannot be written under the options given
+    Value(SerializeError),
+}
+
+impl From<fmt::Error> for WriteError {
+    fn from(error: fmt::Error) -> Self {
+        WriteError::Format(error)
+    }
+}
+
+/// Writes `value`, a `GenericJsonValue` or a `JsonValueRef`, to `out` as JSON
+/// text.
+pub(crate) fn write_value<W: fmt::Write, V: Nested>(
+    out: &mut W,
+    value: &V,
+    options: &SerializeOptions,
+) -> Result<(), WriteError> {
+    // The containers being written are kept on an explicit stack, together
+    // with how many members have been written and the key of the last, so
+    // values nested to any depth can be written and the path to any value
+    // recovered
+    let mut stack: Vec<Level<'_, V>> = Vec::new();
+    let mut value = value;
+
+    loop {
+        match value.shape() {
+            Shape::Int(n) => write!(out, "{}", n)?,
+            Shape::UInt(n) => write!(out, "{}", n)?,
+            Shape::Int128(n) => write!(out, "{}", n)?,
+            Shape::UInt128(n) => write!(out, "{}", n)?,
+            Shape::Float(n) if n.is_finite() => float::write_f64(out, n)?,
+            Shape::Float(n) => {
+                let literal = if n.is_nan() {
+                    "NaN"
+                } else if n > 0.0 {
+                    "Infinity"
+                } else {
+                    "-Infinity"
+                };
+                write_non_finite(out, literal, &stack, options)?;
+            }
+            Shape::Number(n) if n.is_finite() => write!(out, "{}", n)?,
+            Shape::Number(n) => write_non_finite(out, n.as_str(), &stack, options)?,
+            #[cfg(feature = "decimal")]
+            Shape::Decimal(n) => write!(out, "{}", n)?,
+            Shape::Bool(b) => write!(out, "{}", b)?,
+            Shape::Str(s) => escape_json_string(out, s)?,
+            Shape::Arr(arr) => {
+                write!(out, "[")?;
+                stack.push(Level { members: Members::Arr(arr.iter()), written: 0, key: "" });
+            }
+            Shape::Obj(map) => {
+                write!(out, "{{")?;
+                stack.push(Level { members: Members::Obj(map.iter()), written: 0, key: "" });
+            }
+            Shape::Null => write!(out, "null")?,
+        }
+
+        // Move on to the next value, closing the containers that have run out
+        value = loop {
+            let Some(level) = stack.last_mut() else {
+                return Ok(());
+            };
+            let next = match &mut level.members {
+                Members::Arr(items) => items.next().map(|val| (None, val)),
+                Members::Obj(entries) => entries.next().map(|(key, val)| (Some(key.borrow()), val)),
+            };
+            match next {
+                Some((key, val)) => {
+                    if level.written > 0 {
+                        write!(out, ",")?;
+                    }
+                    level.written += 1;
+                    if let Some(key) = key {
+                        // Properly escape the key
+                        escape_json_string(out, key)?;
+                        write!(out, ":")?;
+                        level.key = key;
+                    }
+                    break val;
+                }
+                None => {
+                    match level.members {
+                        Members::Arr(_) => write!(out, "]")?,
+                        Members::Obj(_) => write!(out, "}}")?,
+                    }
+                    stack.pop();
+                }
+            }
+        };
+    }
+}
+
+// Writes an infinity or NaN as the policy says, given its JSON5 literal
+fn write_non_finite<W: fmt::Write, V: Nested>(
+    out: &mut W,
+    literal: &str,
+    stack: &[Level<'_, V>],
+    options: &SerializeOptions,
+) -> Result<(), WriteError> {
+    match options.non_finite {
+        NonFinitePolicy::Null => Ok(write!(out, "null")?),
+        NonFinitePolicy::Literal => Ok(write!(out, "{}", literal)?),
+        NonFinitePolicy::Error => {
+            let code = SerializeErrorCode::NonFiniteNumber { literal: literal.to_string(), path: json_pointer(stack) };
+            Err(WriteError::Value(SerializeError::InvalidValue(code)))
+        }
+    }
+}
+
+// Renders the path to the value being written as a JSON Pointer (RFC 6901)
+fn json_pointer<V: Nested>(stack: &[Level<'_, V>]) -> String {
+    let mut pointer = String::new();
+    for level in stack {
+        pointer.push('/');
+        match level.members {
+            Members::Arr(_) => pointer.push_str(&(level.written - 1).to_string()),
+            Members::Obj(_) => pointer.push_str(&level.key.replace('~', "~0").replace('/', "~1")),
+        }
+    }
+    pointer
+}
+
+/// A container being written: the members still to write, how many have been
+/// written, and for objects the key of the last.
+struct Level<'v, V: Nested> {
+    members: Members<'v, V>,
+    written: usize,
+    key: &'v str,
+}
+
+/// The members of a container that have still to be written.
+enum Members<'v, V: Nested> {
+    Arr(std::slice::Iter<'v, V>),
+    Obj(<V::Map as ObjectMap<V::Key, V>>::Iter<'v>),
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Dialect, JsonMap, JsonParser, JsonValue, NumberMode, ParserOptions};
+
+    fn options(non_finite: NonFinitePolicy) -> SerializeOptions {
+        SerializeOptions { non_finite }
+    }
+
+    fn non_finite(literal: &str, path: &str) -> SerializeErrorCode {
+        SerializeErrorCode::NonFiniteNumber { literal: literal.to_string(), path: path.to_string() }
+    }
+
+    #[test]
+    fn test_non_finite_policies() {
+        let value = JsonValue::Arr(vec![
+            JsonValue::Float(f64::NEG_INFINITY),
+            JsonValue::Float(f64::NAN),
+            JsonValue::Float(-2.5),
+        ].into());
+        assert_eq!(value.to_json_string(&options(NonFinitePolicy::Null)).unwrap(), "[null,null,-2.5]");
+        assert_eq!(value.to_json_string(&options(NonFinitePolicy::Literal)).unwrap(), "[-Infinity,NaN,-2.5]");
+        assert_eq!(value.to_string(), "[null,null,-2.5]");
+
+        // Literals read back as the same numbers under JSON5
+        let json5 = ParserOptions { dialect: Dialect::Json5, ..ParserOptions::default() };
+        let text = value.to_json_string(&options(NonFinitePolicy::Literal)).unwrap();
+        let parsed = JsonParser::from_str(&text).with_options(json5.clone()).parse().unwrap();
+        assert!(matches!(&parsed, JsonValue::Arr(items) if matches!(items[0], JsonValue::Float(n) if n == f64::NEG_INFINITY)));
+
+        // Lossless numbers holding JSON5 literals are treated alike
+        let lossless = ParserOptions { number_mode: NumberMode::Lossless, ..json5 };
+        let parsed = JsonParser::from_str("[1.50, NaN]").with_options(lossless).parse().unwrap();
+        assert_eq!(parsed.to_json_string(&options(NonFinitePolicy::Literal)).unwrap(), "[1.50,NaN]");
+        let error = parsed.to_json_string(&SerializeOptions::default()).unwrap_err();
+        assert_eq!(error.code(), Some(&non_finite("NaN", "/1")));
+    }
+
+    #[test]
+    fn test_error_path() {
+        let mut inner = JsonMap::new();
+        inner.insert("a/b".to_string(), JsonValue::Arr(vec![JsonValue::Null, JsonValue::Float(f64::INFINITY)].into()));
+        let mut outer = JsonMap::new();
+        outer.insert("ok".to_string(), JsonValue::Int(1));
+        outer.insert("rates".to_string(), JsonValue::Obj(inner.into()));
+        let value = JsonValue::Obj(outer.into());
+
+        let error = value.to_json_string(&SerializeOptions::default()).unwrap_err();
+        assert_eq!(error.code(), Some(&non_finite("Infinity", "/rates/a~1b/1")));
+        assert_eq!(error.to_string(), "Invalid value for JSON serialization: Infinity is not a finite number in /rates/a~1b/1");
+
+        // At the top level the path is empty
+        let error = JsonValue::Float(f64::NAN).to_json_string(&SerializeOptions::default()).unwrap_err();
+        assert_eq!(error.code(), Some(&non_finite("NaN", "")));
+        assert_eq!(error.to_string(), "Invalid value for JSON serialization: NaN is not a finite number");
+
+        // Finite values serialize exactly as `Display` writes them
+        let value = JsonParser::from_str(r#"{"a": [1, -0.5, "x\n", {"b": null}]}"#).parse().unwrap();
+        assert_eq!(value.to_json_string(&SerializeOptions::default()).unwrap(), value.to_string());
+    }
+}
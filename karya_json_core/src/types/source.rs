@@ -0,0 +1,393 @@
+//! Input sources the parser can read from.
+//!
+//! A source hands the parser a window of UTF-8 text. `StrSource` is the whole of
+//! a borrowed string, so strings can be sliced straight out of it. `ReaderSource`
+//! pulls bytes from a `std::io::Read` in chunks, validates them as UTF-8 as they
+//! arrive and drops text the parser has finished with, so a document never has
+//! to fit in memory at once. `FeedSource` does the same for chunks pushed to an
+//! `IncrementalParser`.
+//!
+//! Sources are created through the `JsonParser` constructors and are not meant
+//! to be used directly; the types are public so that parsers over them can be
+//! named.
+
+use crate::types::error::{DeserializeError, ErrorCode, Position, SyntaxError};
+use std::io::{ErrorKind, Read};
+
+/// How many bytes `ReaderSource` asks its reader for at a time. Consumed text is
+/// only dropped once at least this much has built up, so the buffer stays
+/// within a small multiple of it plus the longest single token.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A source of JSON text for `JsonParser`.
+///
+/// This trait is sealed: it is implemented for `StrSource`, `ReaderSource` and
+/// `FeedSource` only.
+pub trait Source<'a>: private::Source<'a> {}
+
+impl<'a> Source<'a> for StrSource<'a> {}
+impl<'a, R: Read> Source<'a> for ReaderSource<R> {}
+impl<'a> Source<'a> for FeedSource {}
+
+pub(crate) mod private {
+    use super::*;
+
+    /// The operations the parser needs from its input.
+    ///
+    /// Offsets are byte offsets into `text()`, which always starts and ends on a
+    /// character boundary.
+    pub trait Source<'a> {
+        /// The text currently available to the parser
+        fn text(&self) -> &str;
+
+        /// Returns `text()[start..end]` with the input's own lifetime, if the
+        /// source can lend it out without copying
+        fn borrow(&self, start: usize, end: usize) -> Option<&'a str>;
+
+        /// Appends more text, returning `false` if there is none to be had
+        fn fill(&mut self) -> bool;
+
+        /// Whether the text ends where the input does. Only a `FeedSource`
+        /// still waiting for more chunks returns `false`.
+        fn is_final(&self) -> bool;
+
+        /// The offset of `text()` within the whole input
+        fn base(&self) -> usize;
+
+        /// How much of the text before `offset` should be dropped now
+        fn discardable(&self, offset: usize) -> usize;
+
+        /// Drops the first `len` bytes of `text()`
+        fn discard(&mut self, len: usize);
+
+        /// Computes the line and column of an offset into `text()`
+        fn locate(&self, offset: usize) -> Position;
+
+        /// Takes the error that stopped the source early, if any
+        fn take_error(&mut self) -> Option<DeserializeError>;
+    }
+}
+
+/// A source over a borrowed string slice.
+#[derive(Debug, Clone)]
+pub struct StrSource<'a> {
+    input: &'a str,
+}
+
+impl<'a> StrSource<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Self { input }
+    }
+}
+
+impl<'a> private::Source<'a> for StrSource<'a> {
+    #[inline]
+    fn text(&self) -> &str {
+        self.input
+    }
+
+    fn borrow(&self, start: usize, end: usize) -> Option<&'a str> {
+        Some(&self.input[start..end])
+    }
+
+    #[inline]
+    fn fill(&mut self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn is_final(&self) -> bool {
+        true
+    }
+
+    fn base(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn discardable(&self, _offset: usize) -> usize {
+        0
+    }
+
+    fn discard(&mut self, _len: usize) {}
+
+    fn locate(&self, offset: usize) -> Position {
+        locate(self.input, offset)
+    }
+
+    fn take_error(&mut self) -> Option<DeserializeError> {
+        None
+    }
+}
+
+/// A buffered source over a `std::io::Read`.
+///
+/// Bytes are read in chunks of 64 KiB. Reads interrupted by a signal are
+/// retried; any other I/O error, or bytes that are not valid UTF-8, end the
+/// input and are reported by the parser in place of whatever syntax error the
+/// early end caused.
+#[derive(Debug)]
+pub struct ReaderSource<R> {
+    reader: R,
+    buffer: Buffer,
+    /// Scratch space each read goes into before it is validated
+    chunk: Vec<u8>,
+    /// Whether the reader is exhausted or has failed
+    done: bool,
+    error: Option<DeserializeError>,
+}
+
+impl<R: Read> ReaderSource<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Buffer::default(),
+            chunk: vec![0; CHUNK_SIZE],
+            done: false,
+            error: None,
+        }
+    }
+}
+
+impl<'a, R: Read> private::Source<'a> for ReaderSource<R> {
+    fn text(&self) -> &str {
+        &self.buffer.text
+    }
+
+    fn borrow(&self, _start: usize, _end: usize) -> Option<&'a str> {
+        None
+    }
+
+    fn fill(&mut self) -> bool {
+        while !self.done {
+            let before = self.buffer.text.len();
+            match self.reader.read(&mut self.chunk) {
+                Ok(0) => {
+                    self.done = true;
+                    self.error = self.buffer.end().err();
+                }
+                Ok(read) => {
+                    if let Err(error) = self.buffer.push(&self.chunk[..read]) {
+                        self.error = Some(error);
+                        self.done = true;
+                    }
+                    // A read may end partway through a character and add nothing
+                    if self.buffer.text.len() > before {
+                        return true;
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.error = Some(DeserializeError::Io(e));
+                    self.done = true;
+                }
+            }
+        }
+        false
+    }
+
+    fn is_final(&self) -> bool {
+        self.done
+    }
+
+    fn base(&self) -> usize {
+        self.buffer.base
+    }
+
+    fn discardable(&self, offset: usize) -> usize {
+        if offset >= CHUNK_SIZE { offset } else { 0 }
+    }
+
+    fn discard(&mut self, len: usize) {
+        self.buffer.discard(len);
+    }
+
+    fn locate(&self, offset: usize) -> Position {
+        self.buffer.locate(offset)
+    }
+
+    fn take_error(&mut self) -> Option<DeserializeError> {
+        self.error.take()
+    }
+}
+
+/// A source fed with chunks of input by the caller.
+///
+/// Unlike the other sources it never waits for more input: the text runs out
+/// at the end of the last chunk fed, and the parser asks for more unless
+/// `finish` has been called.
+#[derive(Debug, Default)]
+pub struct FeedSource {
+    buffer: Buffer,
+    /// Whether the caller has said no more input will come
+    finished: bool,
+    error: Option<DeserializeError>,
+}
+
+impl FeedSource {
+    // Appends a chunk of input. Invalid UTF-8 ends the input.
+    pub(crate) fn feed(&mut self, chunk: &[u8]) {
+        if self.finished {
+            return;
+        }
+        if let Err(error) = self.buffer.push(chunk) {
+            self.error = Some(error);
+            self.finished = true;
+        }
+    }
+
+    // Marks the end of the input
+    pub(crate) fn finish(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            self.error = self.buffer.end().err();
+        }
+    }
+}
+
+impl<'a> private::Source<'a> for FeedSource {
+    fn text(&self) -> &str {
+        &self.buffer.text
+    }
+
+    fn borrow(&self, _start: usize, _end: usize) -> Option<&'a str> {
+        None
+    }
+
+    fn fill(&mut self) -> bool {
+        false
+    }
+
+    fn is_final(&self) -> bool {
+        self.finished
+    }
+
+    fn base(&self) -> usize {
+        self.buffer.base
+    }
+
+    fn discardable(&self, offset: usize) -> usize {
+        if offset >= CHUNK_SIZE { offset } else { 0 }
+    }
+
+    fn discard(&mut self, len: usize) {
+        self.buffer.discard(len);
+    }
+
+    fn locate(&self, offset: usize) -> Position {
+        self.buffer.locate(offset)
+    }
+
+    fn take_error(&mut self) -> Option<DeserializeError> {
+        self.error.take()
+    }
+}
+
+/// Text validated as it arrives in chunks, with enough bookkeeping to keep
+/// locating offsets after the start of it has been dropped.
+#[derive(Debug, Default)]
+struct Buffer {
+    /// Validated text not yet dropped
+    text: String,
+    /// The start of a character split across chunks
+    pending: Vec<u8>,
+    /// The offset of `text` within the whole input
+    base: usize,
+    /// Newlines in the text already dropped
+    lines: usize,
+    /// Characters dropped since the last of those newlines
+    column: usize,
+}
+
+impl Buffer {
+    // Validates a chunk and appends it to the text. On invalid UTF-8 the
+    // valid part before it is still appended.
+    fn push(&mut self, mut chunk: &[u8]) -> Result<(), DeserializeError> {
+        // Complete a character left over from the previous chunk first
+        while !self.pending.is_empty() {
+            let Some((&b, rest)) = chunk.split_first() else {
+                return Ok(());
+            };
+            self.pending.push(b);
+            chunk = rest;
+            match std::str::from_utf8(&self.pending) {
+                Ok(c) => {
+                    self.text.push_str(c);
+                    self.pending.clear();
+                }
+                Err(e) if e.error_len().is_some() => return Err(self.utf8_error()),
+                Err(_) => {}
+            }
+        }
+
+        match std::str::from_utf8(chunk) {
+            Ok(valid) => self.text.push_str(valid),
+            Err(e) => {
+                let (valid, rest) = chunk.split_at(e.valid_up_to());
+                self.text.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                if e.error_len().is_some() {
+                    return Err(self.utf8_error());
+                }
+                // Otherwise the chunk ends partway through a character
+                self.pending.extend_from_slice(rest);
+            }
+        }
+        Ok(())
+    }
+
+    // Checks that the input did not end partway through a character
+    fn end(&mut self) -> Result<(), DeserializeError> {
+        if self.pending.is_empty() {
+            Ok(())
+        } else {
+            Err(self.utf8_error())
+        }
+    }
+
+    // A UTF-8 error at the end of the validated text
+    fn utf8_error(&self) -> DeserializeError {
+        let position = self.locate(self.text.len());
+        DeserializeError::InvalidJson(SyntaxError::new(
+            ErrorCode::InvalidUtf8(position.offset),
+            position,
+            String::new(),
+        ))
+    }
+
+    fn discard(&mut self, len: usize) {
+        let dropped = &self.text[..len];
+        match dropped.rfind('\n') {
+            Some(last) => {
+                self.lines += dropped.matches('\n').count();
+                self.column = dropped[last + 1..].chars().count();
+            }
+            None => self.column += dropped.chars().count(),
+        }
+        self.text.drain(..len);
+        self.base += len;
+    }
+
+    fn locate(&self, offset: usize) -> Position {
+        let position = locate(&self.text, offset);
+        Position {
+            offset: self.base + offset,
+            line: self.lines + position.line,
+            column: if position.line == 1 {
+                self.column + position.column
+            } else {
+                position.column
+            },
+        }
+    }
+}
+
+// Computes the line and column of a byte offset in the input
+pub(crate) fn locate(input: &str, offset: usize) -> Position {
+    let before = &input[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        offset,
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
@@ -0,0 +1,243 @@
+//! A borrowed, zero-copy counterpart to `JsonValue`.
+//!
+//! `JsonValueRef<'a>` keeps strings and object keys as `Cow<'a, str>`. Strings
+//! that contain no escape sequences borrow straight from the parser input, so
+//! reading a handful of fields out of a large document allocates only for the
+//! containers and for escaped strings.
+
+#[cfg(feature = "decimal")]
+use crate::types::Decimal;
+use crate::types::serialize;
+use crate::types::{JsonArray, JsonMap, JsonNumber, JsonObject, JsonValue, NonFinitePolicy, SerializeOptions};
+use std::borrow::Cow;
+use std::fmt;
+
+/// Represents a JSON value that borrows its strings from the parser input.
+///
+/// The variants mirror those of `JsonValue`, except that strings and object
+/// keys are `Cow<'a, str>`: `Cow::Borrowed` when the string could be sliced
+/// directly out of the input, and `Cow::Owned` when escape sequences had to be
+/// decoded.
+///
+/// Use `JsonParser::parse_borrowed` to obtain one, and `into_owned` to detach it
+/// from the input.
+///
+/// # Examples
+///
+/// ```
+/// use karya_json::types::{JsonParser, JsonValueRef};
+/// use std::borrow::Cow;
+///
+/// let input = r#"{"name": "Alice", "quote": "say \"hi\""}"#;
+/// let value = JsonParser::from_str(input).parse_borrowed().unwrap();
+///
+/// if let JsonValueRef::Obj(map) = &value {
+///     // No escapes: borrowed straight from `input`
+///     assert!(matches!(map.get("name"), Some(JsonValueRef::Str(Cow::Borrowed("Alice")))));
+///     // Escaped: decoded into an owned string
+///     assert!(matches!(map.get("quote"), Some(JsonValueRef::Str(Cow::Owned(_)))));
+/// }
+/// ```
+#[derive(Debug)]
+pub enum JsonValueRef<'a> {
+    /// A 64-bit signed integer
+    Int(i64),
+    /// A 64-bit unsigned integer
+    UInt(u64),
+    /// A 128-bit signed integer
+    Int128(i128),
+    /// A 128-bit unsigned integer
+    UInt128(u128),
+    /// A 64-bit floating point number
+    Float(f64),
+    /// A number kept exactly as written
+    Number(JsonNumber),
+    /// An exact decimal number
+    #[cfg(feature = "decimal")]
+    Decimal(Decimal),
+    /// A boolean value (true or false)
+    Bool(bool),
+    /// A UTF-8 encoded string, borrowed from the input where possible
+    Str(Cow<'a, str>),
+    /// An ordered array of JSON values
+    Arr(JsonArray<JsonValueRef<'a>>),
+    /// A key-value map where keys are strings and values are JSON values,
+    /// kept in the order the keys were inserted
+    Obj(JsonObject<JsonValueRef<'a>>),
+    /// The JSON null value
+    Null,
+}
+
+impl JsonValueRef<'_> {
+    /// Converts this value into an owned `JsonValue`, copying any borrowed strings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use karya_json::types::{JsonParser, JsonValue};
+    ///
+    /// let owned = {
+    ///     let input = String::from(r#"["a", "b"]"#);
+    ///     JsonParser::from_str(&input).parse_borrowed().unwrap().into_owned()
+    /// };
+    ///
+    /// assert!(matches!(owned, JsonValue::Arr(ref items) if items.len() == 2));
+    /// ```
+    pub fn into_owned(self) -> JsonValue {
+        // The containers being converted, with the members left to convert and
+        // those already converted, are kept on an explicit stack so values
+        // nested to any depth can be converted
+        let mut stack = Vec::new();
+        let mut next = self;
+
+        loop {
+            let mut value = match next {
+                JsonValueRef::Int(n) => Some(JsonValue::Int(n)),
+                JsonValueRef::UInt(n) => Some(JsonValue::UInt(n)),
+                JsonValueRef::Int128(n) => Some(JsonValue::Int128(n)),
+                JsonValueRef::UInt128(n) => Some(JsonValue::UInt128(n)),
+                JsonValueRef::Float(n) => Some(JsonValue::Float(n)),
+                JsonValueRef::Number(n) => Some(JsonValue::Number(n)),
+                #[cfg(feature = "decimal")]
+                JsonValueRef::Decimal(n) => Some(JsonValue::Decimal(n)),
+                JsonValueRef::Bool(b) => Some(JsonValue::Bool(b)),
+                JsonValueRef::Str(s) => Some(JsonValue::Str(s.into_owned())),
+                JsonValueRef::Arr(arr) => {
+                    let converted = Vec::with_capacity(arr.len());
+                    stack.push(Converting::Arr(arr.into_iter(), converted));
+                    None
+                }
+                JsonValueRef::Obj(map) => {
+                    let converted = JsonMap::with_capacity(map.len());
+                    stack.push(Converting::Obj(map.into_iter(), converted, String::new()));
+                    None
+                }
+                JsonValueRef::Null => Some(JsonValue::Null),
+            };
+
+            // Store the converted value in its container and move on to the
+            // next, completing the containers that have run out
+            next = loop {
+                let Some(level) = stack.last_mut() else {
+                    // Only reached once the root value has been converted
+                    return value.unwrap_or(JsonValue::Null);
+                };
+                let done = match level {
+                    Converting::Arr(rest, converted) => {
+                        converted.extend(value.take());
+                        match rest.next() {
+                            Some(val) => break val,
+                            None => JsonValue::Arr(std::mem::take(converted).into()),
+                        }
+                    }
+                    Converting::Obj(rest, converted, key) => {
+                        if let Some(val) = value.take() {
+                            converted.insert(std::mem::take(key), val);
+                        }
+                        match rest.next() {
+                            Some((k, val)) => {
+                                *key = k.into_owned();
+                                break val;
+                            }
+                            None => JsonValue::Obj(std::mem::take(converted).into()),
+                        }
+                    }
+                };
+                value = Some(done);
+                stack.pop();
+            };
+        }
+    }
+}
+
+/// A container that `into_owned` is converting: the members still to convert,
+/// those converted so far and, for objects, the key of the current member.
+enum Converting<'a> {
+    Arr(std::vec::IntoIter<JsonValueRef<'a>>, Vec<JsonValue>),
+    Obj(std::vec::IntoIter<(Cow<'a, str>, JsonValueRef<'a>)>, JsonMap, String),
+}
+
+impl fmt::Display for JsonValueRef<'_> {
+    /// Formats the JsonValueRef as a JSON string, exactly as the equivalent
+    /// `JsonValue` would be formatted.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = SerializeOptions { non_finite: NonFinitePolicy::Null };
+        serialize::write_value(f, self, &options).map_err(|_| fmt::Error)
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Dialect, JsonParser, NumberMode, ParserOptions};
+
+    #[test]
+    fn test_strings_borrow_unless_escaped() {
+        let input = r#"{"plain": "abc", "esc\naped": "a\/c"}"#;
+        let value = JsonParser::from_str(input).parse_borrowed().unwrap();
+
+        let JsonValueRef::Obj(map) = &value else {
+            panic!("Expected JsonValueRef::Obj");
+        };
+        for (key, val) in map {
+            match (key.as_ref(), val) {
+                ("plain", JsonValueRef::Str(s)) => {
+                    assert!(matches!(key, Cow::Borrowed(_)));
+                    assert!(matches!(s, Cow::Borrowed("abc")));
+                }
+                ("esc\naped", JsonValueRef::Str(s)) => {
+                    assert!(matches!(key, Cow::Owned(_)));
+                    assert!(matches!(s, Cow::Owned(_)));
+                    assert_eq!(s, "a/c");
+                }
+                _ => panic!("Unexpected member {:?}", key),
+            }
+        }
+    }
+
+    #[test]
+    fn test_into_owned() {
+        let input = r#"{"list": [1, 2.5, true, null, "x"], "nested": {"k": "v"}}"#;
+        let owned = JsonParser::from_str(input).parse_borrowed().unwrap().into_owned();
+
+        let JsonValue::Obj(map) = &owned else {
+            panic!("Expected JsonValue::Obj");
+        };
+        match map.get("list") {
+            Some(JsonValue::Arr(items)) => {
+                assert!(matches!(items[0], JsonValue::Int(1)));
+                assert!(matches!(items[1], JsonValue::Float(n) if n == 2.5));
+                assert!(matches!(items[2], JsonValue::Bool(true)));
+                assert!(matches!(items[3], JsonValue::Null));
+                assert!(matches!(&items[4], JsonValue::Str(s) if s == "x"));
+            }
+            _ => panic!("Expected list to be an array"),
+        }
+        match map.get("nested") {
+            Some(JsonValue::Obj(nested)) => {
+                assert!(matches!(nested.get("k"), Some(JsonValue::Str(s)) if s == "v"));
+            }
+            _ => panic!("Expected nested to be an object"),
+        }
+    }
+
+    #[test]
+    fn test_display_matches_owned() {
+        let input = r#"[{"a\tb": "c\"d"}, -1, 0.5]"#;
+        let borrowed = JsonParser::from_str(input).parse_borrowed().unwrap();
+        let display = borrowed.to_string();
+        assert_eq!(display, borrowed.into_owned().to_string());
+        assert_eq!(display, r#"[{"a\tb":"c\"d"},-1,0.5]"#);
+
+        // Non-finite numbers are written as `null`, floats and lossless alike
+        let json5 = ParserOptions { dialect: Dialect::Json5, ..ParserOptions::default() };
+        let lossless = ParserOptions { number_mode: NumberMode::Lossless, ..json5.clone() };
+        for options in [json5, lossless] {
+            let borrowed = JsonParser::from_str("[NaN, -Infinity, 1.50]").with_options(options).parse_borrowed().unwrap();
+            let display = borrowed.to_string();
+            assert_eq!(display, borrowed.into_owned().to_string());
+            assert!(display.starts_with("[null,null,"));
+        }
+    }
+}
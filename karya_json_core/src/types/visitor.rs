@@ -0,0 +1,103 @@
+//! The push-based visitor interface.
+//!
+//! A `JsonVisitor` receives a callback for every part of a document as
+//! `JsonParser::parse_with` reads it, in the SAX style. No value tree is built,
+//! so aggregates over large payloads can be computed in memory proportional to
+//! the document's nesting depth.
+
+use crate::types::error::DeserializeError;
+
+/// Callbacks for the parts of a JSON document, driven by `JsonParser::parse_with`.
+///
+/// Every method has a default implementation that does nothing, so visitors
+/// only implement the callbacks they care about. The callbacks are made in
+/// document order: containers are bracketed by their start and end callbacks,
+/// and each object member is reported as `on_key` followed by the callbacks for
+/// its value.
+///
+/// Returning an error from any callback stops parsing immediately, and
+/// `parse_with` returns that error unchanged. Any of the `DeserializeError`
+/// variants may be used, such as `InvalidValue` for data the visitor rejects.
+///
+/// # Examples
+///
+/// Summing a field over every record without building them:
+///
+/// ```
+/// use karya_json::types::{JsonParser, JsonVisitor};
+/// use karya_json::types::error::DeserializeError;
+///
+/// #[derive(Default)]
+/// struct TotalAmount {
+///     in_amount: bool,
+///     total: f64,
+/// }
+///
+/// impl JsonVisitor for TotalAmount {
+///     fn on_key(&mut self, key: &str) -> Result<(), DeserializeError> {
+///         self.in_amount = key == "amount";
+///         Ok(())
+///     }
+///
+///     fn on_number(&mut self, literal: &str) -> Result<(), DeserializeError> {
+///         if self.in_amount {
+///             self.total += literal.parse::<f64>().unwrap_or_default();
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let input = r#"[{"id": 1, "amount": 2.5}, {"id": 2, "amount": 4}]"#;
+/// let mut visitor = TotalAmount::default();
+/// JsonParser::from_str(input).parse_with(&mut visitor).unwrap();
+///
+/// assert_eq!(visitor.total, 6.5);
+/// ```
+pub trait JsonVisitor {
+    /// Called at the start of an object (`{`)
+    fn on_object_start(&mut self) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    /// Called with the key of each object member, before its value
+    fn on_key(&mut self, _key: &str) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    /// Called at the end of an object (`}`)
+    fn on_object_end(&mut self) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    /// Called at the start of an array (`[`)
+    fn on_array_start(&mut self) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    /// Called at the end of an array (`]`)
+    fn on_array_end(&mut self) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    /// Called with each string value, after escape sequences are decoded
+    fn on_string(&mut self, _value: &str) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    /// Called with each number, as its literal text in the input. The text
+    /// follows the JSON number grammar, so `str::parse` converts it to any
+    /// numeric type able to hold it; see `JsonEvent::Number` for JSON5.
+    fn on_number(&mut self, _literal: &str) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    /// Called with each boolean value
+    fn on_bool(&mut self, _value: bool) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    /// Called for each null value
+    fn on_null(&mut self) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+}